@@ -1,6 +1,9 @@
 use corman::{
-    components::board::Board,
-    evaluator::{static_eval::StaticEval, transposition::TranspositionTable},
+    components::{board::Board, pieces::Color},
+    evaluator::{
+        config::SearchConfig, limits::SearchLimits, static_eval::StaticEval,
+        transposition::TranspositionTable,
+    },
     moves::{magic, move_type::Scenario},
 };
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
@@ -10,7 +13,7 @@ use std::time::Duration;
 static INIT: Once = Once::new();
 
 fn init_magic() {
-    INIT.call_once(|| magic::init());
+    INIT.call_once(magic::init);
 }
 
 // A mix of positions to benchmark against.
@@ -23,6 +26,10 @@ const POSITIONS: &[(&str, &str)] = &[
         "mid_game",
         "r1bqk2r/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PPP2PPP/R1BQK2R w KQkq - 0 8",
     ),
+    (
+        "italian",
+        "r2q1rk1/ppp2ppp/2np1n2/2b1p1B1/2B1P1b1/2NP1N2/PPP2PPP/R2Q1RK1 b - - 0 8",
+    ),
     ("endgame", "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P2/8/1K6 w - - 0 1"),
     (
         "tactics",
@@ -30,6 +37,41 @@ const POSITIONS: &[(&str, &str)] = &[
     ),
 ];
 
+// The first ten Win At Chess positions and their solutions.
+const TACTICS: &[(&str, &str)] = &[
+    (
+        "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+        "g3g6",
+    ),
+    ("8/7p/5k2/5p2/p1p2P2/Pr1pPK2/1P1R3P/8 b - - 0 1", "b3b2"),
+    (
+        "5rk1/1ppb3p/p1pb4/6q1/3P1p1r/2P1R2P/PP1BQ1P1/5RKN w - - 0 1",
+        "e3g3",
+    ),
+    (
+        "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - 0 1",
+        "h6h7",
+    ),
+    ("5k2/6pp/p1qN4/1p1p4/3P4/2PKP2Q/PP3r2/3R4 b - - 0 1", "c6c4"),
+    ("7k/p7/1R5K/6r1/6p1/6P1/8/8 w - - 0 1", "b6b7"),
+    (
+        "rnbqkb1r/pppp1ppp/8/4P3/6n1/7P/PPPNPPP1/R1BQKBNR b KQkq - 0 1",
+        "g4e3",
+    ),
+    (
+        "r4q1k/p2bR1rp/2p2Q1N/5p2/5p2/2P5/PP3PPP/R5K1 w - - 0 1",
+        "e7f7",
+    ),
+    (
+        "3q1rk1/p4pp1/2pb3p/3p4/6Pr/1PNQ4/P1PB1PP1/4RRK1 b - - 0 1",
+        "d6h2",
+    ),
+    (
+        "2br2k1/2q3rn/p2NppQ1/2p1P3/Pp5R/4P3/1P3PPP/3R2K1 w - - 0 1",
+        "h4h7",
+    ),
+];
+
 fn bench_move_generation(c: &mut Criterion) {
     init_magic();
     let mut group = c.benchmark_group("move_generation");
@@ -40,6 +82,35 @@ fn bench_move_generation(c: &mut Criterion) {
     group.finish();
 }
 
+// Perft exercises make/unmake and the occupancy caches without any evaluation.
+fn bench_perft(c: &mut Criterion) {
+    init_magic();
+    let mut group = c.benchmark_group("perft_3");
+    group.sample_size(20);
+    for (name, fen) in POSITIONS {
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        group.bench_function(*name, |b| b.iter(|| black_box(board.clone().perft(3))));
+    }
+    group.finish();
+}
+
+// Probes a single square instead of building the whole attack map of a side.
+fn bench_square_attacked(c: &mut Criterion) {
+    init_magic();
+    let mut group = c.benchmark_group("is_in_check");
+    for (name, fen) in POSITIONS {
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        let king = board.position.get('K');
+        group.bench_function(format!("{}_targeted", name), |b| {
+            b.iter(|| black_box(board.position.is_in_check(Color::White)))
+        });
+        group.bench_function(format!("{}_attack_map", name), |b| {
+            b.iter(|| black_box(king & board.position.attacked_squares(Color::Black)))
+        });
+    }
+    group.finish();
+}
+
 fn bench_static_eval(c: &mut Criterion) {
     init_magic();
     let mut group = c.benchmark_group("static_eval");
@@ -64,18 +135,98 @@ fn bench_search(c: &mut Criterion) {
             b.iter(|| {
                 let mut scenario = Scenario::new(board.clone());
                 let tt = TranspositionTable::new();
-                black_box(scenario.minimax_alpha_beta(4, i32::MIN + 1, i32::MAX - 1, &tt, true))
+                black_box(scenario.negamax(4, i32::MIN + 1, i32::MAX - 1, &tt, true))
             })
         });
     }
     group.finish();
 }
 
+// Lazy SMP: time to complete an iterative deepening with one and four threads.
+fn bench_time_to_depth(c: &mut Criterion) {
+    init_magic();
+    let mut group = c.benchmark_group("time_to_depth_6");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(30));
+    let limits = SearchLimits {
+        depth: Some(6),
+        ..SearchLimits::default()
+    };
+    for (name, fen) in POSITIONS {
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        for threads in [1, 4] {
+            let config = SearchConfig {
+                threads,
+                ..SearchConfig::default()
+            };
+            group.bench_function(format!("{}_{}_threads", name, threads), |b| {
+                b.iter(|| {
+                    let tt = TranspositionTable::new();
+                    black_box(
+                        Scenario::with_config(board.clone(), config)
+                            .iterative_deepening(&limits, &tt, None, None),
+                    )
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+// Lazy SMP: time to search the tactics set with one and four threads, and how
+// many positions each finds the solution of.
+fn bench_tactics(c: &mut Criterion) {
+    init_magic();
+    let mut group = c.benchmark_group("tactics_depth_5");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(30));
+    let limits = SearchLimits {
+        depth: Some(5),
+        ..SearchLimits::default()
+    };
+    for threads in [1, 4] {
+        let config = SearchConfig {
+            threads,
+            ..SearchConfig::default()
+        };
+        let solved = || {
+            TACTICS
+                .iter()
+                .filter(|(fen, best)| {
+                    let board = Board::from_forsyth_edwards(fen).unwrap();
+                    let tt = TranspositionTable::new();
+                    Scenario::with_config(board, config)
+                        .iterative_deepening(&limits, &tt, None, None)
+                        .is_some_and(|result| result.best_move.to_uci() == *best)
+                })
+                .count()
+        };
+        let mut reported = false;
+        group.bench_function(format!("{}_threads", threads), |b| {
+            if !reported {
+                println!(
+                    "tactics_depth_5/{}_threads solved {}/{}",
+                    threads,
+                    solved(),
+                    TACTICS.len()
+                );
+                reported = true;
+            }
+            b.iter(|| black_box(solved()))
+        });
+    }
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_move_generation,
+    bench_perft,
+    bench_square_attacked,
     bench_static_eval,
-    bench_search
+    bench_search,
+    bench_time_to_depth,
+    bench_tactics
 );
 
 criterion_main!(benches);
@@ -1,13 +1,17 @@
 use std::fmt;
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 
-use crate::moves::move_type::{Move, MoveKind};
+use crate::moves::{
+    generators, magic,
+    move_type::{Move, MoveKind},
+    notation,
+};
 
 use super::{
-    castle::{Castle, CastleSide},
-    constants, hash,
-    pieces::{Bitboard, Color, PieceKind},
+    castle::{self, Castle, CastleSide, CastlingRights, RookFiles},
+    constants, en_passant, hash,
+    pieces::{Bitboard, Color, Piece, PieceKind},
     position::BBPosition,
 };
 
@@ -28,15 +32,18 @@ pub struct NullMoveUndo {
     reps_50: u8,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     pub position: BBPosition,
     pub turn: Color,
     pub en_passant_target: Bitboard,
     pub white_can_castle: Castle,
     pub black_can_castle: Castle,
+    /// Files of the rooks the castling rights refer to.
+    pub rook_files: RookFiles,
     pub hash: u64,
     pub reps_50: u8,
+    /// Plies played since the start of the game, see [`Board::fullmove_number`].
     pub moves_count: u32,
 }
 
@@ -45,7 +52,7 @@ impl fmt::Display for Board {
         'outer: for pos in 0..64 {
             for (piece, position) in &self.position {
                 // this piece is at this board position i'm checking for
-                if (position.bits << pos).leading_zeros() == 0_u32 {
+                if position.bits & 1 << (63 - pos) != 0 {
                     write!(f, " {} ", piece)?;
                     if (pos + 1) % 8 == 0 {
                         writeln!(f)?;
@@ -63,31 +70,71 @@ impl fmt::Display for Board {
         writeln!(f)?;
         write!(f, "Turn: {}", self.turn)?;
         writeln!(f)?;
-        write!(f, "Move Number: {}", self.moves_count)?;
+        write!(f, "Move Number: {}", self.fullmove_number())?;
+        writeln!(f)?;
+        write!(f, "Castling: {}", self.castling_rights())?;
 
         Ok(())
     }
 }
 
+/// The standard starting position.
+impl Default for Board {
+    fn default() -> Self {
+        Self::from_parts(
+            BBPosition::startpos(),
+            Color::White,
+            Bitboard::new(0),
+            CastlingRights::new(Castle::Both, Castle::Both),
+            0,
+            1,
+        )
+    }
+}
+
 impl Board {
+    /// Same as [`Board::default`].
     pub fn new_game() -> Self {
-        Self::from_forsyth_edwards("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
-            .unwrap()
+        Self::default()
     }
 
     /// Parse Board position from Forsyth-Edwards notation:
     ///
     /// Notation Exaple: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
-    /// # Panics
-    /// On inconsistent notation.
+    ///
+    /// The halfmove clock and fullmove number may be missing or "-"; they then
+    /// default to 0 and 1. Use [`Board::from_forsyth_edwards_strict`] to require them.
+    /// # Errors
+    /// On inconsistent notation, including malformed ranks and missing kings.
     pub fn from_forsyth_edwards(s: &str) -> Result<Self, anyhow::Error> {
-        let pieces = s.split(" ").collect::<Vec<&str>>();
-        if pieces.len() != 6 {
+        let fields = s.split_whitespace().collect::<Vec<&str>>();
+        if !(4..=6).contains(&fields.len()) {
             return Err(anyhow!("invalid Forsyth-Edwards notation for: {}", s));
         }
-        let (position, turn, castling_rights, en_passant, reps_50, moves_count) = (
-            pieces[0], pieces[1], pieces[2], pieces[3], pieces[4], pieces[5],
-        );
+        let counter = |i: usize| fields.get(i).copied().filter(|&f| f != "-");
+        let reps_50: u8 = counter(4).map_or(Ok(0), str::parse)?;
+        let fullmove: u32 = counter(5).map_or(Ok(1), str::parse)?;
+
+        Self::from_fen_fields(&fields[..4], reps_50, fullmove)
+    }
+
+    /// Like [`Board::from_forsyth_edwards`], but requires all 6 fields separated by
+    /// single spaces, with numeric halfmove clock and fullmove number.
+    pub fn from_forsyth_edwards_strict(s: &str) -> Result<Self, anyhow::Error> {
+        let fields = s.split(" ").collect::<Vec<&str>>();
+        if fields.len() != 6 {
+            return Err(anyhow!("invalid Forsyth-Edwards notation for: {}", s));
+        }
+        let reps_50: u8 = fields[4].parse()?;
+        let fullmove: u32 = fields[5].parse()?;
+
+        Self::from_fen_fields(&fields[..4], reps_50, fullmove)
+    }
+
+    /// Builds the board from the first four FEN fields and the two counters.
+    fn from_fen_fields(fields: &[&str], reps_50: u8, fullmove: u32) -> Result<Self, anyhow::Error> {
+        let (position, turn, castling_rights, en_passant) =
+            (fields[0], fields[1], fields[2], fields[3]);
 
         let position = BBPosition::from_fen_notation(position)?;
         let turn: Color = turn.try_into()?;
@@ -95,12 +142,86 @@ impl Board {
             "-" => Bitboard { bits: 0 },
             s => Bitboard::try_from(s)?,
         };
-        let (white_can_castle, black_can_castle) = Castle::parse_from_str(castling_rights)?;
-        let reps_50: u8 = reps_50.parse()?;
-        let moves_count: u32 = moves_count.parse()?;
+        let en_passant_target =
+            en_passant::validate_en_passant_target(&position, turn, en_passant_target)?;
+        let castling = CastlingRights::from_fen_field(castling_rights, &position)?;
 
+        Ok(Self::from_parts(
+            position,
+            turn,
+            en_passant_target,
+            castling,
+            reps_50,
+            fullmove,
+        ))
+    }
+
+    /// Builds a position from a whitespace-separated piece list such as
+    /// "Ke1 Qd1 Pa2 ke8 pe7", as produced by board-recognition tools.
+    ///
+    /// Uppercase piece letters are white, lowercase black; the case of the square
+    /// does not matter. Duplicate squares and implausible piece counts are rejected.
+    pub fn from_piece_list(
+        s: &str,
+        turn: Color,
+        rights: &str,
+        ep: Option<&str>,
+    ) -> Result<Self, anyhow::Error> {
+        let mut position = BBPosition::empty();
+        let mut seen = 0u64;
+
+        for token in s.split_whitespace() {
+            let mut chars = token.chars();
+            let piece: Piece = chars.next().context("empty piece token")?.try_into()?;
+            let square_str = chars.as_str().to_ascii_lowercase();
+            let square = notation::parse_square(&square_str)
+                .filter(|_| square_str.len() == 2)
+                .with_context(|| format!("invalid square in piece token: {}", token))?;
+
+            if seen & (1 << square) != 0 {
+                return Err(anyhow!("duplicate square in piece list: {}", square_str));
+            }
+            seen |= 1 << square;
+            position.get_mut(piece).bits |= 1 << square;
+        }
+
+        position.recompute_occupied();
+        position.validate()?;
+
+        let en_passant_target = match ep {
+            None | Some("-") => Bitboard::new(0),
+            Some(s) => Bitboard::try_from(s)?,
+        };
+        let en_passant_target =
+            en_passant::validate_en_passant_target(&position, turn, en_passant_target)?;
+        let castling = CastlingRights::from_fen_field(rights, &position)?;
+
+        Ok(Self::from_parts(
+            position,
+            turn,
+            en_passant_target,
+            castling,
+            0,
+            1,
+        ))
+    }
+
+    /// Assembles a board from its fields, computing the Zobrist hash from scratch.
+    fn from_parts(
+        position: BBPosition,
+        turn: Color,
+        en_passant_target: Bitboard,
+        castling: CastlingRights,
+        reps_50: u8,
+        fullmove: u32,
+    ) -> Self {
         // Compute the Zobrist hash from scratch once at construction time.
         // All subsequent positions update it incrementally in make_unchecked_move.
+        let CastlingRights {
+            white: white_can_castle,
+            black: black_can_castle,
+            rook_files,
+        } = castling;
         let mut h = hash::castle_rights_hash(white_can_castle, black_can_castle);
         if turn == Color::White {
             h ^= hash::side_to_move_hash();
@@ -111,148 +232,163 @@ impl Board {
             }
         }
 
-        Ok(Self {
+        Self {
             position,
             turn,
             en_passant_target,
             white_can_castle,
             black_can_castle,
+            rook_files,
             hash: h,
             reps_50,
-            moves_count,
-        })
+            moves_count: 2 * fullmove.saturating_sub(1) + (turn == Color::Black) as u32,
+        }
+    }
+
+    /// The FEN fullmove number: 1 at the start, incremented after each Black move.
+    pub fn fullmove_number(&self) -> u32 {
+        self.moves_count / 2 + 1
+    }
+
+    /// Writes the position in Forsyth-Edwards notation.
+    pub fn to_forsyth_edwards(&self) -> String {
+        let turn = match self.turn {
+            Color::White => 'w',
+            Color::Black => 'b',
+        };
+        let en_passant = self
+            .en_passant_target
+            .lsb_index()
+            .map_or_else(|| "-".to_string(), notation::square_name);
+
+        format!(
+            "{} {} {} {} {} {}",
+            self.position.to_fen_notation(),
+            turn,
+            self.castling_rights(),
+            en_passant,
+            self.reps_50,
+            self.fullmove_number()
+        )
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        CastlingRights {
+            white: self.white_can_castle,
+            black: self.black_can_castle,
+            rook_files: self.rook_files,
+        }
     }
 
     pub fn attacked_squares(&self, side: Color) -> Bitboard {
         self.position.attacked_squares(side)
     }
 
-    /// calculates how castling rights get changed by the move being made
-    fn calculate_castling_rights(&self, player_move: &Move) -> (Castle, Castle) {
-        let white_queen_rook = 7; // a1
-        let white_king_rook = 0; // h1
-        let black_queen_rook = 63; // a8
-        let black_king_rook = 56; // h8
-
-        let white_can_castle = match (player_move.piece.color, player_move.piece.kind) {
-            (Color::White, PieceKind::King) => Castle::No,
-            (Color::White, PieceKind::Rook) => match (self.white_can_castle, player_move.action) {
-                (Castle::No, _) => Castle::No,
-                (Castle::King, MoveKind::Standard { from, .. }) => {
-                    if from == white_king_rook {
-                        Castle::No
-                    } else {
-                        Castle::King
-                    }
-                }
-                (Castle::Queen, MoveKind::Standard { from, .. }) => {
-                    if from == white_queen_rook {
-                        Castle::No
-                    } else {
-                        Castle::Queen
-                    }
-                }
-                (Castle::Both, MoveKind::Standard { from, .. }) => {
-                    if from == white_king_rook {
-                        Castle::Queen
-                    } else if from == white_queen_rook {
-                        Castle::King
-                    } else {
-                        Castle::Both
-                    }
-                }
-                _ => unreachable!(),
-            },
-            (Color::Black, _) => match (self.white_can_castle, player_move.action) {
-                (Castle::No, _) => Castle::No,
-                (Castle::King, MoveKind::Standard { to, .. } | MoveKind::Promote { to, .. }) => {
-                    if to == white_king_rook {
-                        Castle::No
-                    } else {
-                        Castle::King
-                    }
-                }
-                (Castle::Queen, MoveKind::Standard { to, .. } | MoveKind::Promote { to, .. }) => {
-                    if to == white_queen_rook {
-                        Castle::No
-                    } else {
-                        Castle::Queen
-                    }
-                }
-                (Castle::Both, MoveKind::Standard { to, .. } | MoveKind::Promote { to, .. }) => {
-                    if to == white_king_rook {
-                        Castle::Queen
-                    } else if to == white_queen_rook {
-                        Castle::King
-                    } else {
-                        Castle::Both
-                    }
-                }
-                _ => self.white_can_castle,
-            },
-            _ => self.white_can_castle,
+    /// Returns true if `player_move` checks the enemy king, without making it.
+    ///
+    /// The moved piece (the promoted piece, or the rook when castling) checks
+    /// directly if it attacks the king from where it lands. Any other slider
+    /// of the mover checks if the squares vacated by the move open its line
+    /// to the king.
+    pub fn gives_check(&self, player_move: &Move) -> bool {
+        let us = player_move.piece.color;
+        let Some(king_sq) = self
+            .position
+            .get(Piece::new(us.other(), PieceKind::King))
+            .lsb_index()
+        else {
+            return false;
         };
 
-        let black_can_castle = match (player_move.piece.color, player_move.piece.kind) {
-            (Color::Black, PieceKind::King) => Castle::No,
-            (Color::Black, PieceKind::Rook) => match (self.black_can_castle, player_move.action) {
-                (Castle::No, _) => Castle::No,
-                (Castle::King, MoveKind::Standard { from, .. }) => {
-                    if from == black_king_rook {
-                        Castle::No
-                    } else {
-                        Castle::King
-                    }
-                }
-                (Castle::Queen, MoveKind::Standard { from, .. }) => {
-                    if from == black_queen_rook {
-                        Castle::No
-                    } else {
-                        Castle::Queen
-                    }
-                }
-                (Castle::Both, MoveKind::Standard { from, .. }) => {
-                    if from == black_king_rook {
-                        Castle::Queen
-                    } else if from == black_queen_rook {
-                        Castle::King
-                    } else {
-                        Castle::Both
-                    }
-                }
-                _ => unreachable!(),
-            },
-            (Color::White, _) => match (self.black_can_castle, player_move.action) {
-                (Castle::No, _) => Castle::No,
-                (Castle::King, MoveKind::Standard { to, .. } | MoveKind::Promote { to, .. }) => {
-                    if to == black_king_rook {
-                        Castle::No
-                    } else {
-                        Castle::King
-                    }
-                }
-                (Castle::Queen, MoveKind::Standard { to, .. } | MoveKind::Promote { to, .. }) => {
-                    if to == black_queen_rook {
-                        Castle::No
-                    } else {
-                        Castle::Queen
+        // Squares left empty, and where the checking piece and any other
+        // moved piece end up.
+        let (vacated, (to, kind), also_filled) = match player_move.action {
+            MoveKind::Standard { from, to, .. } => (1u64 << from, (to, player_move.piece.kind), 0),
+            MoveKind::Promote {
+                from, to, to_piece, ..
+            } => (1u64 << from, (to, to_piece), 0),
+            MoveKind::EnPassant { from, to } => (
+                1u64 << from | en_passant::en_passant_captured_sq(to, us),
+                (to, PieceKind::Pawn),
+                0,
+            ),
+            MoveKind::Castle { .. } => {
+                let (king_from, king_to, rook_from, rook_to) = castle::castle_squares(player_move);
+                (
+                    1u64 << king_from | 1u64 << rook_from,
+                    (rook_to, PieceKind::Rook),
+                    1u64 << king_to,
+                )
+            }
+        };
+        let occupancy = (self.position.occupied_cells().bits & !vacated) | 1u64 << to | also_filled;
+        if Self::piece_checks(kind, us, to, king_sq, occupancy) {
+            return true;
+        }
+
+        // Discovered checks: the moved piece itself left `vacated`.
+        let own = |kind| self.position.get(Piece::new(us, kind)).bits & !vacated;
+        let queens = own(PieceKind::Queen);
+        magic::rook_attacks(king_sq, occupancy) & (own(PieceKind::Rook) | queens) != 0
+            || magic::bishop_attacks(king_sq, occupancy) & (own(PieceKind::Bishop) | queens) != 0
+    }
+
+    /// Returns true if a piece of `kind` and `color` on `from` attacks `king_sq`.
+    fn piece_checks(kind: PieceKind, color: Color, from: u8, king_sq: u8, occupancy: u64) -> bool {
+        let king = 1u64 << king_sq;
+        let piece = Bitboard::new(1u64 << from);
+        let empty = Bitboard::new(0);
+        let attacks = match kind {
+            PieceKind::Pawn => {
+                match color {
+                    Color::White => {
+                        generators::white_pawn_attack(piece, empty, Bitboard::new(king))
                     }
-                }
-                (Castle::Both, MoveKind::Standard { to, .. } | MoveKind::Promote { to, .. }) => {
-                    if to == black_king_rook {
-                        Castle::Queen
-                    } else if to == black_queen_rook {
-                        Castle::King
-                    } else {
-                        Castle::Both
+                    Color::Black => {
+                        generators::black_pawn_attack(piece, empty, Bitboard::new(king))
                     }
                 }
-                _ => self.black_can_castle,
-            },
-            _ => self.black_can_castle,
+                .bits
+            }
+            PieceKind::Knight => generators::knight(piece, empty, empty).bits,
+            PieceKind::Bishop => magic::bishop_attacks(from, occupancy),
+            PieceKind::Rook => magic::rook_attacks(from, occupancy),
+            PieceKind::Queen => {
+                magic::bishop_attacks(from, occupancy) | magic::rook_attacks(from, occupancy)
+            }
+            PieceKind::King => 0,
+        };
+        attacks & king != 0
+    }
+
+    /// calculates how castling rights get changed by the move being made:
+    /// moving the king loses both rights, moving a castling rook or having it
+    /// captured loses the rights on its side.
+    fn calculate_castling_rights(&self, player_move: &Move) -> (Castle, Castle) {
+        let update = |color: Color, rights: Castle| {
+            if rights == Castle::No {
+                return rights;
+            }
+            let own_move = player_move.piece.color == color;
+            let square = match (own_move, player_move.piece.kind, player_move.action) {
+                (true, PieceKind::King, _) => return Castle::No,
+                (true, PieceKind::Rook, MoveKind::Standard { from, .. }) => from,
+                (false, _, MoveKind::Standard { to, .. } | MoveKind::Promote { to, .. }) => to,
+                _ => return rights,
+            };
+            let rook_square =
+                |side| castle::back_rank_square(color, self.rook_files.get(color, side));
+
+            rights.without(
+                square == rook_square(CastleSide::King),
+                square == rook_square(CastleSide::Queen),
+            )
         };
 
-        (white_can_castle, black_can_castle)
+        (
+            update(Color::White, self.white_can_castle),
+            update(Color::Black, self.black_can_castle),
+        )
     }
 
     /// checks if the 50 moves rules counter should be resetted
@@ -326,6 +462,7 @@ impl Board {
             en_passant_target,
             white_can_castle,
             black_can_castle,
+            rook_files: self.rook_files,
             hash,
             reps_50,
             moves_count,
@@ -358,9 +495,8 @@ impl Board {
                 }
                 h ^= hash::piece_square_hash(player_move.piece.color, player_move.piece.kind, to);
             }
-            MoveKind::Castle(side) => {
-                let (king_from, king_to, rook_from, rook_to) =
-                    Self::castle_piece_squares(player_move.piece.color, side);
+            MoveKind::Castle { .. } => {
+                let (king_from, king_to, rook_from, rook_to) = castle::castle_squares(player_move);
                 h ^= hash::piece_square_hash(player_move.piece.color, PieceKind::King, king_from);
                 h ^= hash::piece_square_hash(player_move.piece.color, PieceKind::King, king_to);
                 h ^= hash::piece_square_hash(player_move.piece.color, PieceKind::Rook, rook_from);
@@ -397,16 +533,6 @@ impl Board {
         h
     }
 
-    /// Returns (king_from, king_to, rook_from, rook_to) bit positions for a castling move.
-    fn castle_piece_squares(color: Color, side: CastleSide) -> (u8, u8, u8, u8) {
-        match (color, side) {
-            (Color::White, CastleSide::King) => (3, 1, 0, 2), // e1→g1, h1→f1
-            (Color::White, CastleSide::Queen) => (3, 5, 7, 4), // e1→c1, a1→d1
-            (Color::Black, CastleSide::King) => (59, 57, 56, 58), // e8→g8, h8→f8
-            (Color::Black, CastleSide::Queen) => (59, 61, 63, 60), // e8→c8, a8→d8
-        }
-    }
-
     /// Applies a move to the board in place and returns the undo information needed to reverse it.
     pub fn make_move(&mut self, player_move: &Move) -> MoveUndo {
         // Compute everything that depends on the current (pre-move) state before mutating.
@@ -451,6 +577,25 @@ impl Board {
         self.moves_count -= 1;
     }
 
+    /// Counts the leaf nodes of the legal move tree `depth` plies deep.
+    /// Used to validate move generation against known reference counts.
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        let moves = self.generate_moves(false);
+        if depth <= 1 {
+            return if depth == 0 { 1 } else { moves.len() as u64 };
+        }
+
+        let mut nodes = 0;
+        for i in 0..moves.len() {
+            let m = moves.get_unsorted(i, self);
+            let undo = self.make_move(&m);
+            nodes += self.perft(depth - 1);
+            self.unmake_move(&m, undo);
+        }
+
+        nodes
+    }
+
     /// Applies a null move (pass the turn) in place and returns undo information.
     pub fn make_null_move_mut(&mut self) -> NullMoveUndo {
         let undo = NullMoveUndo {
@@ -476,3 +621,312 @@ impl Board {
         self.moves_count -= 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::hash::xorshift64, moves::magic};
+
+    #[test]
+    fn perft_matches_reference_counts() {
+        magic::init();
+        let mut board = Board::new_game();
+        assert_eq!(board.perft(1), 20);
+        assert_eq!(board.perft(2), 400);
+        assert_eq!(board.perft(3), 8_902);
+
+        // "Kiwipete": castling, en passant and promotions all show up at low depth.
+        let mut board = Board::from_forsyth_edwards(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let fen = board.to_forsyth_edwards();
+        assert_eq!(board.perft(2), 2_039);
+        assert_eq!(
+            board.to_forsyth_edwards(),
+            fen,
+            "make/unmake must restore the position"
+        );
+    }
+
+    #[test]
+    fn random_make_unmake_sequences_restore_the_board() {
+        magic::init();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        for fen in fens {
+            for _ in 0..8 {
+                let original = Board::from_forsyth_edwards(fen).unwrap();
+                let mut board = original.clone();
+                let mut played = Vec::new();
+                for _ in 0..60 {
+                    let moves = board.generate_moves(false);
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let pick = xorshift64(&mut state) as usize % moves.len();
+                    let m = moves.get_unsorted(pick, &board);
+                    let undo = board.make_move(&m);
+                    played.push((m, undo));
+
+                    // The incremental key matches one computed from scratch.
+                    let rebuilt = Board::from_forsyth_edwards(&board.to_forsyth_edwards()).unwrap();
+                    assert_eq!(board.hash, rebuilt.hash, "{}", board.to_forsyth_edwards());
+                }
+                while let Some((m, undo)) = played.pop() {
+                    board.unmake_move(&m, undo);
+                }
+
+                let diff = original.diff(&board);
+                assert!(diff.is_empty(), "{}: {}", fen, diff);
+                assert_eq!(board.hash, original.hash, "{}", fen);
+                assert_eq!(board.to_forsyth_edwards(), original.to_forsyth_edwards());
+            }
+        }
+    }
+
+    #[test]
+    fn capturing_a_rook_on_its_home_square_removes_that_right() {
+        magic::init();
+        for (fen, capture, rights) in [
+            // Bishop, knight, promoting pawn and queen captures.
+            ("r3k2r/1B6/8/8/8/8/8/4K3 w kq - 0 1", "b7a8", "k"),
+            ("r3k2r/8/6N1/8/8/8/8/4K3 w kq - 0 1", "g6h8", "q"),
+            ("r3k2r/1P6/8/8/8/8/8/4K3 w kq - 0 1", "b7a8q", "k"),
+            ("4k3/8/8/8/8/8/q7/R3K2R b KQ - 0 1", "a2a1", "K"),
+            // Chess960: the castling rook stands on b1.
+            ("4k3/8/8/8/8/n7/8/1R2K1R1 b GB - 0 1", "a3b1", "G"),
+            // Taking a rook that is not the castling one leaves the rights alone.
+            ("r2rk3/8/8/8/8/8/8/3RK3 w q - 0 1", "d1d8", "q"),
+        ] {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let after = board.try_make_move(capture).unwrap();
+            assert_eq!(
+                after.castling_rights().to_fen_field(),
+                rights,
+                "{} {}",
+                fen,
+                capture
+            );
+        }
+    }
+
+    #[test]
+    fn default_board_is_the_parsed_start_position() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let parsed = Board::from_forsyth_edwards(fen).unwrap();
+        let board = Board::default();
+
+        assert!(board.diff(&parsed).is_empty(), "{}", board.diff(&parsed));
+        assert_eq!(board.hash, parsed.hash);
+        assert_eq!(board.to_forsyth_edwards(), fen);
+        assert_eq!(board.position.occupied_all.bits, 0xffff_0000_0000_ffff);
+    }
+
+    #[test]
+    fn castling_rights_render_the_same_everywhere() {
+        magic::init();
+        let board = Board::from_forsyth_edwards("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1").unwrap();
+        let rights = board.castling_rights().to_string();
+        assert_eq!(rights, "Kq");
+
+        let fen = board.to_forsyth_edwards();
+        assert_eq!(fen.split(' ').nth(2), Some(rights.as_str()));
+        assert!(
+            board
+                .to_string()
+                .ends_with(&format!("Castling: {}", rights))
+        );
+
+        let m = board.parse_uci_move("e1g1").unwrap();
+        let diff = board.diff(&board.make_unchecked_move(&m));
+        assert!(
+            diff.to_string()
+                .contains(&format!("castling: {} -> q", rights))
+        );
+    }
+
+    #[test]
+    fn fen_counters_may_be_missing_or_dashes() {
+        let placement = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq -";
+        let board = Board::from_forsyth_edwards(placement).unwrap();
+        assert_eq!((board.reps_50, board.fullmove_number()), (0, 1));
+        assert_eq!(board.to_forsyth_edwards(), format!("{} 0 1", placement));
+        assert!(Board::from_forsyth_edwards_strict(placement).is_err());
+
+        let dashes = Board::from_forsyth_edwards(&format!("{} - -", placement)).unwrap();
+        assert_eq!((dashes.reps_50, dashes.fullmove_number()), (0, 1));
+        assert_eq!(dashes.hash, board.hash);
+
+        let fen = format!("{} 12 40", placement);
+        for board in [
+            Board::from_forsyth_edwards(&fen).unwrap(),
+            Board::from_forsyth_edwards_strict(&fen).unwrap(),
+        ] {
+            assert_eq!((board.reps_50, board.fullmove_number()), (12, 40));
+        }
+
+        assert!(Board::from_forsyth_edwards(&format!("{} x 1", placement)).is_err());
+        assert!(Board::from_forsyth_edwards("r3k2r/8/8/8/8/8/8/R3K2R w").is_err());
+    }
+
+    #[test]
+    fn the_fullmove_number_grows_after_black_moves() {
+        magic::init();
+        let board = Board::new_game();
+        let board = board.make_unchecked_move(&board.parse_uci_move("e2e4").unwrap());
+        assert!(board.to_forsyth_edwards().ends_with(" b KQkq - 0 1"));
+        let board = board.make_unchecked_move(&board.parse_uci_move("e7e5").unwrap());
+        assert!(board.to_forsyth_edwards().ends_with(" w KQkq - 0 2"));
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 17";
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        assert_eq!(board.to_forsyth_edwards(), fen);
+        let board = board.make_unchecked_move(&board.parse_uci_move("e8d8").unwrap());
+        assert_eq!(board.fullmove_number(), 18);
+    }
+
+    #[test]
+    fn piece_list_round_trips_through_fen() {
+        let board = Board::from_piece_list(
+            "Ke1 Qd1 Ra1 Rh1 Pa2 PE4 nF6 ke8 ra8 rh8 pe5",
+            Color::Black,
+            "KQk",
+            None,
+        )
+        .unwrap();
+        let fen = board.to_forsyth_edwards();
+        assert_eq!(fen, "r3k2r/8/5n2/4p3/4P3/8/P7/R2QK2R b KQk - 0 1");
+
+        let parsed = Board::from_forsyth_edwards(&fen).unwrap();
+        assert_eq!(parsed.to_forsyth_edwards(), fen);
+        assert_eq!(parsed.hash, board.hash);
+    }
+
+    #[test]
+    fn piece_list_rejects_duplicate_squares_and_missing_king() {
+        let duplicate = Board::from_piece_list("Ke1 Qe1 ke8", Color::White, "-", None);
+        assert!(duplicate.unwrap_err().to_string().contains("duplicate"));
+
+        let no_black_king = Board::from_piece_list("Ke1 Qd1 pe7", Color::White, "-", None);
+        assert!(no_black_king.unwrap_err().to_string().contains("king"));
+    }
+
+    #[test]
+    fn en_passant_target_must_match_the_pawns() {
+        let valid = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        let board = Board::from_forsyth_edwards(valid).unwrap();
+        assert_eq!(board.to_forsyth_edwards(), valid);
+        let black = "rnbqkbnr/ppp1pppp/8/8/2Pp4/8/PP1PPPPP/RNBQKBNR b KQkq c3 0 2";
+        assert_eq!(
+            Board::from_forsyth_edwards(black)
+                .unwrap()
+                .to_forsyth_edwards(),
+            black
+        );
+
+        // No white pawn can take on e6: the target is dropped, as after e7e5 in play.
+        let idle = Board::from_forsyth_edwards(
+            "rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 2",
+        )
+        .unwrap();
+        assert_eq!(idle.en_passant_target, Bitboard::new(0));
+        assert_eq!(
+            idle.hash,
+            Board::from_forsyth_edwards(&idle.to_forsyth_edwards())
+                .unwrap()
+                .hash
+        );
+
+        for (fen, error) in [
+            // No black pawn on e5.
+            (
+                "rnbqkbnr/pppppppp/8/3P4/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 2",
+                "no pawn",
+            ),
+            // A target on the wrong rank for the side to move.
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq f6 0 3",
+                "rank",
+            ),
+            // The pawn could not have come from f7.
+            (
+                "rnbqkbnr/ppp1pppp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                "double pawn push",
+            ),
+        ] {
+            let err = Board::from_forsyth_edwards(fen).unwrap_err().to_string();
+            assert!(err.contains(error), "{}: {}", fen, err);
+        }
+    }
+
+    #[test]
+    fn gives_check_agrees_with_making_the_move() {
+        magic::init();
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            // Castling queenside checks the d8 king along the d-file.
+            "3k4/8/8/8/8/8/8/R3K3 w Q - 0 1",
+        ];
+        let mut state = 0x853C_49E6_748F_EA9B;
+        let mut checks = 0;
+        for fen in fens {
+            for _ in 0..6 {
+                let mut board = Board::from_forsyth_edwards(fen).unwrap();
+                for _ in 0..40 {
+                    let moves = board.legal_moves();
+                    if moves.is_empty() {
+                        break;
+                    }
+                    for m in &moves {
+                        let mut after = board.clone();
+                        after.make_move(m);
+                        let expected = after.position.is_in_check(after.turn);
+                        checks += expected as u32;
+                        assert_eq!(
+                            board.gives_check(m),
+                            expected,
+                            "{} in {}",
+                            m.to_uci(),
+                            board.to_forsyth_edwards()
+                        );
+                    }
+                    let pick = xorshift64(&mut state) as usize % moves.len();
+                    board.make_move(&moves[pick]);
+                }
+            }
+        }
+        assert!(checks > 100, "only {} checking moves seen", checks);
+    }
+
+    #[test]
+    fn boards_compare_equal_field_by_field() {
+        magic::init();
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        assert_eq!(board, Board::from_forsyth_edwards(fen).unwrap());
+
+        let mut moved = board.clone();
+        let m = moved.parse_uci_move("e1g1").unwrap();
+        let undo = moved.make_move(&m);
+        assert_ne!(moved, board);
+        moved.unmake_move(&m, undo);
+        assert_eq!(moved, board);
+
+        // Same pieces, different counters or rights.
+        let later = Board::from_forsyth_edwards(&fen.replace("0 1", "3 9")).unwrap();
+        assert_ne!(later, board);
+        let no_rights = Board::from_forsyth_edwards(&fen.replace("KQkq", "Kkq")).unwrap();
+        assert_ne!(no_rights, board);
+        assert_eq!(no_rights.position, board.position);
+    }
+}
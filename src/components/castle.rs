@@ -1,14 +1,17 @@
+use std::{fmt, str::FromStr};
+
 use anyhow::anyhow;
 
 use crate::moves::move_type::{Move, MoveKind};
 
 use super::{
     board::Board,
-    pieces::{Color, Piece, PieceKind},
+    constants,
+    pieces::{Bitboard, Color, Piece, PieceKind},
     position::BBPosition,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Castle {
     No,
     King,
@@ -17,26 +20,265 @@ pub enum Castle {
 }
 
 impl Castle {
+    /// Parses a FEN castling field into (white, black) rights.
     pub fn parse_from_str(s: &str) -> Result<(Self, Self), anyhow::Error> {
-        match s {
-            "KQkq" => Ok((Self::Both, Self::Both)),
-            "Kkq" => Ok((Self::King, Self::Both)),
-            "Qkq" => Ok((Self::Queen, Self::Both)),
-            "kq" => Ok((Self::No, Self::Both)),
-            "k" => Ok((Self::No, Self::King)),
-            "q" => Ok((Self::No, Self::Queen)),
-            "KQk" => Ok((Self::Both, Self::King)),
-            "KQq" => Ok((Self::Both, Self::Queen)),
-            "KQ" => Ok((Self::Both, Self::No)),
-            "K" => Ok((Self::King, Self::No)),
-            "Q" => Ok((Self::Queen, Self::No)),
-            "-" => Ok((Self::No, Self::No)),
-            _ => Err(anyhow!("invalid castling right notation: {}", s)),
+        let rights: CastlingRights = s.parse()?;
+        Ok((rights.white, rights.black))
+    }
+
+    fn from_sides(king: bool, queen: bool) -> Self {
+        match (king, queen) {
+            (false, false) => Self::No,
+            (true, false) => Self::King,
+            (false, true) => Self::Queen,
+            (true, true) => Self::Both,
         }
     }
+
+    /// These rights without the king side and/or queen side ones.
+    pub(crate) fn without(self, king_side: bool, queen_side: bool) -> Self {
+        Self::from_sides(
+            self.king_side() && !king_side,
+            self.queen_side() && !queen_side,
+        )
+    }
+
+    fn king_side(self) -> bool {
+        matches!(self, Self::King | Self::Both)
+    }
+
+    fn queen_side(self) -> bool {
+        matches!(self, Self::Queen | Self::Both)
+    }
+}
+
+/// Files (0 = a .. 7 = h) of the rooks each castling right moves. Standard chess
+/// castles with the h and a rooks; Chess960 positions may use any others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RookFiles {
+    /// Indexed by [color][side]: White then Black, king side then queen side.
+    files: [[u8; 2]; 2],
+}
+
+impl RookFiles {
+    pub const STANDARD: Self = Self {
+        files: [[7, 0], [7, 0]],
+    };
+
+    pub fn get(&self, color: Color, side: CastleSide) -> u8 {
+        self.files[color_index(color)][side_index(side)]
+    }
+
+    fn set(&mut self, color: Color, side: CastleSide, file: u8) {
+        self.files[color_index(color)][side_index(side)] = file;
+    }
+}
+
+impl Default for RookFiles {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+fn side_index(side: CastleSide) -> usize {
+    match side {
+        CastleSide::King => 0,
+        CastleSide::Queen => 1,
+    }
+}
+
+/// Square index of `file` (0 = a) on the back rank of `color`.
+pub(crate) fn back_rank_square(color: Color, file: u8) -> u8 {
+    let rank = match color {
+        Color::White => 0,
+        Color::Black => 7,
+    };
+    rank * 8 + (7 - file)
 }
 
+/// Castling rights of both sides and the rooks they refer to.
+/// Displays as the FEN castling field ("KQkq", "-"); a right whose rook is not on
+/// the standard file is written with the rook's file letter, as in Shredder-FEN.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CastlingRights {
+    pub white: Castle,
+    pub black: Castle,
+    pub rook_files: RookFiles,
+}
+
+impl CastlingRights {
+    /// Rights for the standard rook files.
+    pub fn new(white: Castle, black: Castle) -> Self {
+        Self {
+            white,
+            black,
+            rook_files: RookFiles::STANDARD,
+        }
+    }
+
+    /// The FEN castling field: "KQkq" order, "-" when nobody can castle.
+    /// If any right uses a rook off the a and h files, all of them are written
+    /// as file letters (Shredder-FEN).
+    pub fn to_fen_field(&self) -> String {
+        let rights = [
+            (Color::White, CastleSide::King, self.white.king_side(), 'K'),
+            (
+                Color::White,
+                CastleSide::Queen,
+                self.white.queen_side(),
+                'Q',
+            ),
+            (Color::Black, CastleSide::King, self.black.king_side(), 'K'),
+            (
+                Color::Black,
+                CastleSide::Queen,
+                self.black.queen_side(),
+                'Q',
+            ),
+        ];
+        let shredder = rights.iter().any(|&(color, side, on, _)| {
+            on && self.rook_files.get(color, side) != RookFiles::STANDARD.get(color, side)
+        });
+
+        let mut field = String::new();
+        for (color, side, on, letter) in rights {
+            if on {
+                let c = if shredder {
+                    (b'A' + self.rook_files.get(color, side)) as char
+                } else {
+                    letter
+                };
+                field.push(match color {
+                    Color::White => c,
+                    Color::Black => c.to_ascii_lowercase(),
+                });
+            }
+        }
+
+        if field.is_empty() {
+            "-".to_string()
+        } else {
+            field
+        }
+    }
+
+    /// Parses a FEN castling field for `position`. Besides "KQkq" (X-FEN: the
+    /// outermost rook on that side of the king), rights may name the rook's file
+    /// as in Shredder-FEN ("HAha"); the side is then given by where the rook
+    /// stands relative to the king.
+    ///
+    /// White rights come before black ones and king side before queen side.
+    pub fn from_fen_field(s: &str, position: &BBPosition) -> Result<Self, anyhow::Error> {
+        let invalid = || anyhow!("invalid castling right notation: {}", s);
+        let mut rights = Self::new(Castle::No, Castle::No);
+        if s == "-" {
+            return Ok(rights);
+        }
+
+        let mut flags = [false; 4];
+        let mut last = None;
+        for c in s.chars() {
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let back_rank = match color {
+                Color::White => constants::FIRST_ROW,
+                Color::Black => constants::EIGHT_ROW,
+            };
+            let king_file =
+                Bitboard::new(position.get(Piece::new(color, PieceKind::King)).bits & back_rank)
+                    .single_squares()
+                    .next()
+                    .map(|sq| 7 - sq % 8);
+            let rooks = position.get(Piece::new(color, PieceKind::Rook)).bits & back_rank;
+            let rook_files = || Bitboard::new(rooks).single_squares().map(|sq| 7 - sq % 8);
+
+            let (side, file) = match c.to_ascii_uppercase() {
+                'K' => (
+                    CastleSide::King,
+                    king_file
+                        .and_then(|k| rook_files().filter(|&f| f > k).max())
+                        .unwrap_or(7),
+                ),
+                'Q' => (
+                    CastleSide::Queen,
+                    king_file
+                        .and_then(|k| rook_files().filter(|&f| f < k).min())
+                        .unwrap_or(0),
+                ),
+                letter @ 'A'..='H' => {
+                    let file = letter as u8 - b'A';
+                    let king_file = king_file
+                        .filter(|&k| k != file)
+                        .ok_or_else(|| anyhow!("{}: no {} king to castle with", s, color))?;
+                    if rooks & (1 << back_rank_square(color, file)) == 0 {
+                        return Err(anyhow!(
+                            "{}: no {} rook on the {} file",
+                            s,
+                            color,
+                            c.to_ascii_lowercase()
+                        ));
+                    }
+                    let side = if file > king_file {
+                        CastleSide::King
+                    } else {
+                        CastleSide::Queen
+                    };
+                    (side, file)
+                }
+                _ => return Err(invalid()),
+            };
+
+            let idx = color_index(color) * 2 + side_index(side);
+            if last.is_some_and(|last| idx <= last) {
+                return Err(invalid());
+            }
+            flags[idx] = true;
+            last = Some(idx);
+            rights.rook_files.set(color, side, file);
+        }
+        if last.is_none() {
+            return Err(invalid());
+        }
+
+        rights.white = Castle::from_sides(flags[0], flags[1]);
+        rights.black = Castle::from_sides(flags[2], flags[3]);
+
+        Ok(rights)
+    }
+}
+
+impl fmt::Display for CastlingRights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_fen_field())
+    }
+}
+
+/// Parses a standard FEN castling field: "-" or any of "KQkq" in that order,
+/// each at most once. File letters need the position: see
+/// [`CastlingRights::from_fen_field`].
+impl FromStr for CastlingRights {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.chars().all(|c| "KQkq-".contains(c)) {
+            return Err(anyhow!("invalid castling right notation: {}", s));
+        }
+
+        Self::from_fen_field(s, &BBPosition::empty())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CastleSide {
     Queen,
     King,
@@ -44,105 +286,124 @@ pub enum CastleSide {
 
 /// Returns a tuple of 2 elements. The first is Some if castling king side is a valid move.
 /// The second is some if castling queen side is a valid move.
+///
+/// Only the rights of the side to move are inspected. The castling rooks are
+/// found through `board.rook_files`, so Chess960 setups work the same way: every
+/// square the king and rook cross must be empty (apart from themselves), and no
+/// square the king crosses may be attacked by the other side.
 pub fn available_castling_moves(
     board: &Board,
     white_can_castle: Castle,
     black_can_castle: Castle,
 ) -> (Option<Move>, Option<Move>) {
-    let castle_king = Move {
-        piece: Piece::new(board.turn, PieceKind::King),
-        action: MoveKind::Castle(CastleSide::King),
+    let color = board.turn;
+    let (rights, back_rank) = match color {
+        Color::White => (white_can_castle, constants::FIRST_ROW),
+        Color::Black => (black_can_castle, constants::EIGHT_ROW),
     };
-    let castle_queen = Move {
-        piece: Piece::new(board.turn, PieceKind::King),
-        action: MoveKind::Castle(CastleSide::Queen),
+    if rights == Castle::No {
+        return (None, None);
+    }
+    let king = Piece::new(color, PieceKind::King);
+    let rook = Piece::new(color, PieceKind::Rook);
+    let Some(king_from) = Bitboard::new(board.position.get(king).bits & back_rank)
+        .single_squares()
+        .next()
+    else {
+        return (None, None);
     };
-    let occupied_squares = board.position.occupied_cells();
-
-    match (board.turn, white_can_castle, black_can_castle) {
-        (Color::White, Castle::King, _) => {
-            let attacked_squares = board.attacked_squares(Color::Black);
-            if (attacked_squares.bits
-                & 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00001110
-                != 0)
-                || (occupied_squares.bits
-                    & 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000110
-                    != 0)
-            {
-                return (None, None);
-            }
-
-            (Some(castle_king), None)
-        }
-        (Color::White, Castle::Queen, _) => {
-            let attacked_squares = board.attacked_squares(Color::Black);
-            if (attacked_squares.bits
-                & 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00111000
-                != 0)
-                || (occupied_squares.bits
-                    & 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_01110000
-                    != 0)
-            {
-                return (None, None);
-            }
 
-            (None, Some(castle_queen))
+    let occupied = board.position.occupied_cells().bits;
+    let castle_move = |side: CastleSide| {
+        let rook_from = back_rank_square(color, board.rook_files.get(color, side));
+        if board.position.get(rook).bits & (1 << rook_from) == 0 {
+            return None;
         }
-        (Color::White, Castle::Both, _) => {
-            let castle_king = available_castling_moves(board, Castle::King, black_can_castle);
-            let castle_queen = available_castling_moves(board, Castle::Queen, black_can_castle);
-            (castle_king.0, castle_queen.1)
+        let (king_to, rook_to) = castle_destinations(color, side);
+        let king_path = squares_between(king_from, king_to);
+        let others = occupied & !(1 << king_from) & !(1 << rook_from);
+        if (king_path | squares_between(rook_from, rook_to)) & others != 0 {
+            return None;
         }
-        (Color::Black, _, Castle::King) => {
-            let attacked_squares = board.attacked_squares(Color::White);
-            if (attacked_squares.bits
-                & 0b00001110_00000000_00000000_00000000_00000000_00000000_00000000_00000000
-                != 0)
-                || (occupied_squares.bits
-                    & 0b00000110_00000000_00000000_00000000_00000000_00000000_00000000_00000000
-                    != 0)
-            {
-                return (None, None);
-            }
 
-            (Some(castle_king), None)
-        }
-        (Color::Black, _, Castle::Queen) => {
-            let attacked_squares = board.attacked_squares(Color::White);
-            if (attacked_squares.bits
-                & 0b00111000_00000000_00000000_00000000_00000000_00000000_00000000_00000000
-                != 0)
-                || (occupied_squares.bits
-                    & 0b01110000_00000000_00000000_00000000_00000000_00000000_00000000_00000000
-                    != 0)
-            {
-                return (None, None);
-            }
+        // In Chess960 the castling rook can stand between an enemy rook or queen
+        // and a square the king lands on, so attacks are computed without it.
+        let attacked = if is_standard_castle(king_from, rook_from) {
+            board.attacked_squares(color.other()).bits
+        } else {
+            let mut without = board.position.clone();
+            without.get_mut(king).bits &= !(1 << king_from);
+            without.get_mut(rook).bits &= !(1 << rook_from);
+            without.recompute_occupied();
+            without.attacked_squares(color.other()).bits
+        };
 
-            (None, Some(castle_queen))
-        }
-        (Color::Black, _, Castle::Both) => {
-            let castle_king = available_castling_moves(board, white_can_castle, Castle::King);
-            let castle_queen = available_castling_moves(board, white_can_castle, Castle::Queen);
-            (castle_king.0, castle_queen.1)
-        }
-        _ => (None, None),
+        (attacked & king_path == 0).then_some(Move {
+            piece: king,
+            action: MoveKind::Castle {
+                side,
+                king_from,
+                rook_from,
+            },
+        })
+    };
+
+    (
+        rights
+            .king_side()
+            .then(|| castle_move(CastleSide::King))
+            .flatten(),
+        rights
+            .queen_side()
+            .then(|| castle_move(CastleSide::Queen))
+            .flatten(),
+    )
+}
+
+/// Squares from `a` to `b` on the same rank, both included.
+fn squares_between(a: u8, b: u8) -> u64 {
+    let (low, high) = (a.min(b), a.max(b));
+    (u64::MAX >> (63 - high)) & (u64::MAX << low)
+}
+
+/// Returns the (king_to, rook_to) squares of a castling move: the king always
+/// lands on the g or c file and the rook next to it, as in standard chess.
+pub(crate) fn castle_destinations(color: Color, side: CastleSide) -> (u8, u8) {
+    match (color, side) {
+        (Color::White, CastleSide::King) => (1, 2),
+        (Color::White, CastleSide::Queen) => (5, 4),
+        (Color::Black, CastleSide::King) => (57, 58),
+        (Color::Black, CastleSide::Queen) => (61, 60),
     }
 }
 
+/// Whether king and rook start on the standard squares (e file, a or h file).
+pub(crate) fn is_standard_castle(king_from: u8, rook_from: u8) -> bool {
+    king_from % 8 == 3 && matches!(rook_from % 8, 0 | 7)
+}
+
 /// Returns (king_from, king_to, rook_from, rook_to) bit-index tuples for a castling move.
-fn castle_squares(turn: Color, side: CastleSide) -> (u8, u8, u8, u8) {
-    match (turn, side) {
-        (Color::White, CastleSide::King) => (3, 1, 0, 2),
-        (Color::White, CastleSide::Queen) => (3, 5, 7, 4),
-        (Color::Black, CastleSide::King) => (59, 57, 56, 58),
-        (Color::Black, CastleSide::Queen) => (59, 61, 63, 60),
+pub(crate) fn castle_squares(player_move: &Move) -> (u8, u8, u8, u8) {
+    match player_move.action {
+        MoveKind::Castle {
+            side,
+            king_from,
+            rook_from,
+        } => {
+            let (king_to, rook_to) = castle_destinations(player_move.piece.color, side);
+            (king_from, king_to, rook_from, rook_to)
+        }
+        _ => unreachable!("not a castling move"),
     }
 }
 
 /// Applies a castling move in place, moving king and rook to their post-castle squares.
-pub fn apply_castling_in_place(bitboards: &mut BBPosition, turn: Color, side: CastleSide) {
-    let (king_from, king_to, rook_from, rook_to) = castle_squares(turn, side);
+/// Both are lifted before being placed, since in Chess960 the king may land on
+/// the rook's square or not move at all.
+/// Only the piece bitboards change: the caller updates the occupancy caches.
+pub fn apply_castling_in_place(bitboards: &mut BBPosition, player_move: &Move) {
+    let (king_from, king_to, rook_from, rook_to) = castle_squares(player_move);
+    let turn = player_move.piece.color;
     let king = Piece::new(turn, PieceKind::King);
     let rook = Piece::new(turn, PieceKind::Rook);
 
@@ -154,9 +415,11 @@ pub fn apply_castling_in_place(bitboards: &mut BBPosition, turn: Color, side: Ca
 }
 
 /// Reverses a castling move in place, restoring king and rook to their pre-castle squares.
-pub fn unapply_castling_in_place(bitboards: &mut BBPosition, turn: Color, side: CastleSide) {
+/// Only the piece bitboards change: the caller updates the occupancy caches.
+pub fn unapply_castling_in_place(bitboards: &mut BBPosition, player_move: &Move) {
     // Reverse: swap from/to relative to apply
-    let (king_from, king_to, rook_from, rook_to) = castle_squares(turn, side);
+    let (king_from, king_to, rook_from, rook_to) = castle_squares(player_move);
+    let turn = player_move.piece.color;
     let king = Piece::new(turn, PieceKind::King);
     let rook = Piece::new(turn, PieceKind::Rook);
 
@@ -167,15 +430,169 @@ pub fn unapply_castling_in_place(bitboards: &mut BBPosition, turn: Color, side:
     rook_bb.bits = (rook_bb.bits & !(1u64 << rook_to)) | (1u64 << rook_from);
 }
 
-/// Calculates the new board position after a castling move is made.
-pub fn bitboards_after_castling(
-    current_bitboards: &BBPosition,
-    turn: Color,
-    side: CastleSide,
-) -> BBPosition {
-    let mut new_bitboards = current_bitboards.clone();
-    apply_castling_in_place(&mut new_bitboards, turn, side);
-    new_bitboards.recompute_occupied();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::magic;
 
-    new_bitboards
+    fn castles(fen: &str) -> Vec<String> {
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        let (king, queen) =
+            available_castling_moves(&board, board.white_can_castle, board.black_can_castle);
+        let castles: Vec<_> = [king, queen].into_iter().flatten().collect();
+        assert!(castles.iter().all(|m| m.piece.color == board.turn));
+        castles.iter().map(|m| m.to_uci()).collect()
+    }
+
+    #[test]
+    fn only_the_side_to_move_rights_are_used() {
+        magic::init();
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1"), ["e1g1"]);
+        assert_eq!(
+            castles("r3k2r/8/8/8/8/8/8/R3K2R b Kkq - 0 1"),
+            ["e8g8", "e8c8"]
+        );
+        assert_eq!(
+            castles("r3k2r/8/8/8/8/8/8/R3K2R w KQq - 0 1"),
+            ["e1g1", "e1c1"]
+        );
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R b KQq - 0 1"), ["e8c8"]);
+        assert!(castles("r3k2r/8/8/8/8/8/8/R3K2R b KQ - 0 1").is_empty());
+        assert!(castles("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1").is_empty());
+    }
+
+    #[test]
+    fn enemy_attacks_block_castling() {
+        magic::init();
+        // The black rook on f8 covers f1: White can only castle queen side.
+        assert_eq!(castles("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1"), ["e1c1"]);
+        // The white rook on d1 covers d8: Black can only castle king side,
+        // and White's own rights are not affected by it.
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/3RK3 b kq - 0 1"), ["e8g8"]);
+        // b1 being attacked does not matter, it only has to be empty.
+        assert_eq!(castles("1r2k3/8/8/8/8/8/8/R3K3 w Q - 0 1"), ["e1c1"]);
+    }
+
+    #[test]
+    fn all_rights_combinations_round_trip() {
+        let sides = [Castle::No, Castle::King, Castle::Queen, Castle::Both];
+        let mut fields = Vec::new();
+        for white in sides {
+            for black in sides {
+                let rights = CastlingRights::new(white, black);
+                let field = rights.to_string();
+                assert_eq!(field, rights.to_fen_field());
+                assert_eq!(field.parse::<CastlingRights>().unwrap(), rights);
+                fields.push(field);
+            }
+        }
+        assert_eq!(fields.len(), 16);
+        assert!(fields.contains(&"Kq".to_string()));
+        assert!(fields.contains(&"-".to_string()));
+
+        for invalid in ["", "qk", "KK", "K-", "KQkqK", "x"] {
+            assert!(invalid.parse::<CastlingRights>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn file_letters_name_the_castling_rooks() {
+        let standard = Board::from_forsyth_edwards("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").unwrap();
+        assert_eq!(standard.castling_rights().to_string(), "KQkq");
+        assert_eq!(standard.rook_files, RookFiles::STANDARD);
+
+        // Chess960 start position: king on f1, rooks on e1 and g1.
+        let fen = "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1";
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        assert_eq!(
+            (board.white_can_castle, board.black_can_castle),
+            (Castle::Both, Castle::Both)
+        );
+        assert_eq!(board.rook_files.get(Color::White, CastleSide::King), 6);
+        assert_eq!(board.rook_files.get(Color::Black, CastleSide::Queen), 4);
+        assert_eq!(board.to_forsyth_edwards(), fen);
+
+        // X-FEN letters pick the outermost rook on each side of the king.
+        let x_fen = Board::from_forsyth_edwards(&fen.replace("GEge", "KQkq")).unwrap();
+        assert_eq!(x_fen.rook_files, board.rook_files);
+        assert_eq!(x_fen.hash, board.hash);
+        let partial = Board::from_forsyth_edwards(&fen.replace("GEge", "Ge")).unwrap();
+        assert_eq!(
+            (partial.white_can_castle, partial.black_can_castle),
+            (Castle::King, Castle::Queen)
+        );
+
+        for invalid in ["HAha", "EG", "GG", "gG", "GE-"] {
+            let fen = fen.replace("GEge", invalid);
+            assert!(Board::from_forsyth_edwards(&fen).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn chess960_castling_moves_king_and_rook_to_the_standard_squares() {
+        magic::init();
+        let after = |fen: &str, uci: &str| {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let m = board.parse_uci_move(uci).unwrap();
+            assert!(matches!(m.action, MoveKind::Castle { .. }), "{}", uci);
+            assert_eq!(m.to_uci(), uci);
+            board.make_unchecked_move(&m).to_forsyth_edwards()
+        };
+
+        // King on f1 between rooks on b1 and g1: king side swaps king and rook.
+        let fen = "4k3/8/8/8/8/8/8/1R3KR1 w GB - 0 1";
+        assert_eq!(castles(fen), ["f1g1", "f1b1"]);
+        assert_eq!(after(fen, "f1g1"), "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1");
+        assert_eq!(after(fen, "f1b1"), "4k3/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+
+        // The king already stands on g1: only the rook moves.
+        let fen = "4k3/8/8/8/8/8/8/6KR w H - 0 1";
+        assert_eq!(after(fen, "g1h1"), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+
+        // Black castles queen side with the king on b8 and the rook on a8.
+        let fen = "rk6/8/8/8/8/8/8/4K3 b a - 0 1";
+        assert_eq!(after(fen, "b8a8"), "2kr4/8/8/8/8/8/8/4K3 w - - 1 2");
+    }
+
+    #[test]
+    fn chess960_castling_needs_a_safe_and_empty_path() {
+        magic::init();
+        // The b1 rook shields c1 from the a1 rook: castling would leave the king in check.
+        assert!(castles("4k3/8/8/8/8/8/8/rRK5 w B - 0 1").is_empty());
+        // A piece on the rook's destination blocks castling.
+        assert!(castles("4k3/8/8/8/8/8/8/RK1N4 w A - 0 1").is_empty());
+        // An attacked square on the king's way blocks castling.
+        assert!(
+            castles(
+                "4k3/8/8/8/8/8/8/1R3K1R w HB - 0 1"
+                    .replace("4k3", "3rk3")
+                    .as_str()
+            ) == ["f1h1"]
+        );
+    }
+
+    #[test]
+    fn chess960_perft_matches_reference_counts() {
+        magic::init();
+        for (fen, counts) in [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                [21, 528, 12_189],
+            ),
+            (
+                "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                [21, 807, 18_002],
+            ),
+            (
+                "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+                [20, 479, 10_471],
+            ),
+        ] {
+            let mut board = Board::from_forsyth_edwards(fen).unwrap();
+            for (depth, count) in (1..).zip(counts) {
+                assert_eq!(board.perft(depth), count, "{} depth {}", fen, depth);
+            }
+            assert_eq!(board.to_forsyth_edwards(), fen);
+        }
+    }
 }
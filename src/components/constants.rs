@@ -1,14 +1,19 @@
-/// Masks
-pub const NOT_A_RANK: u64 =
+/// Masks. Shifting a bitboard sideways wraps pieces from one edge file to the
+/// other: the NOT_*_FILE masks clear the files such a shift can wrap onto.
+pub const NOT_A_FILE: u64 =
     0b01111111_01111111_01111111_01111111_01111111_01111111_01111111_01111111;
-pub const NOT_B_RANK: u64 =
+pub const NOT_B_FILE: u64 =
     0b10111111_10111111_10111111_10111111_10111111_10111111_10111111_10111111;
-pub const NOT_G_RANK: u64 =
+pub const NOT_G_FILE: u64 =
     0b11111101_11111101_11111101_11111101_11111101_11111101_11111101_11111101;
-pub const NOT_H_RANK: u64 =
+pub const NOT_H_FILE: u64 =
     0b11111110_11111110_11111110_11111110_11111110_11111110_11111110_11111110;
 pub const SECOND_ROW: u64 =
     0b00000000_00000000_00000000_00000000_00000000_00000000_11111111_00000000;
+pub const THIRD_ROW: u64 =
+    0b00000000_00000000_00000000_00000000_00000000_11111111_00000000_00000000;
+pub const SIXTH_ROW: u64 =
+    0b00000000_00000000_11111111_00000000_00000000_00000000_00000000_00000000;
 pub const SEVENTH_ROW: u64 =
     0b00000000_11111111_00000000_00000000_00000000_00000000_00000000_00000000;
 pub const CENTRAL_MASK: u64 =
@@ -28,6 +33,30 @@ pub const SQUARES_VALUE: [i32; 64] = [
 pub const ATTACKED_EMPTY_SQUARE_VALUE: i32 = 50;
 pub const CASTLING_VALUE: i32 = 1000;
 pub const PROMOTION_VALUE: i32 = 9000;
+/// Bonus of a quiet move giving check, larger than any piece-square delta so
+/// that checks are tried before the other quiet moves.
+pub const QUIET_CHECK_VALUE: i32 = 1500;
+
+/// "Trade when winning": bonus for the side ahead in material, per pawn of lead
+/// and per enemy piece (knight, bishop, rook, queen) no longer on the board.
+pub const EXCHANGE_BONUS_PER_PIECE: i32 = 20;
+/// Lead above which the exchange bonus stops growing.
+pub const EXCHANGE_BONUS_MAX_LEAD: i32 = 5000;
+/// Upper bound of the exchange bonus: it shapes the choice between otherwise
+/// equal lines and must never be worth trading away material for.
+pub const EXCHANGE_BONUS_CAP: i32 = 400;
+/// Knights, bishops, rooks and queen of each side in the starting position.
+pub const STARTING_PIECES: i32 = 7;
+/// Knights, bishops, rooks and queens of both sides in the starting position,
+/// in material units. The exchange bonus scales with how much of it is gone.
+pub const STARTING_NON_PAWN_MATERIAL: i32 = 62_400;
+
+/// Mop-up against a bare king: bonus per step of the lone king away from the
+/// centre, driving it to the edge where it can be mated.
+pub const MOP_UP_EDGE_VALUE: i32 = 100;
+/// Mop-up against a bare king: bonus per step the kings are closer than the
+/// widest gap, as the attacking king has to help the mate.
+pub const MOP_UP_PROXIMITY_VALUE: i32 = 50;
 
 /// We consider to be in an endgame when we are below this amount of material
 pub const ENDGAME_MATERIAL_THRESHOLD: i32 = 20_000;
@@ -0,0 +1,176 @@
+use std::fmt;
+
+use super::{
+    board::Board,
+    castle::CastlingRights,
+    pieces::{Bitboard, Color, Piece},
+};
+use crate::moves::notation::square_name;
+
+/// Differences between two boards, as returned by [`Board::diff`].
+/// Every changed field holds its (old, new) values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BoardDiff {
+    /// Pieces of the old board missing from the new one, by square index (h1 first).
+    pub removed: Vec<(u8, Piece)>,
+    /// Pieces of the new board missing from the old one, by square index (h1 first).
+    pub added: Vec<(u8, Piece)>,
+    pub turn: Option<(Color, Color)>,
+    pub castling: Option<(CastlingRights, CastlingRights)>,
+    /// En passant target square, if any.
+    pub en_passant_target: Option<(Option<u8>, Option<u8>)>,
+    pub reps_50: Option<(u8, u8)>,
+    pub fullmove_number: Option<(u32, u32)>,
+}
+
+fn changed<T: PartialEq>(old: T, new: T) -> Option<(T, T)> {
+    (old != new).then_some((old, new))
+}
+
+fn target_square(target: Bitboard) -> Option<u8> {
+    target.lsb_index()
+}
+
+impl Board {
+    /// Lists what changed from `self` to `other`: pieces removed and added
+    /// (found by XORing each piece bitboard) and the state flags that differ.
+    /// A moving piece shows up as removed from its old square and added on the new one.
+    pub fn diff(&self, other: &Board) -> BoardDiff {
+        let mut diff = BoardDiff::default();
+        for ((piece, old), (_, new)) in (&self.position).into_iter().zip(&other.position) {
+            let changed = old.bits ^ new.bits;
+            for sq in Bitboard::new(changed & old.bits).single_squares() {
+                diff.removed.push((sq, *piece));
+            }
+            for sq in Bitboard::new(changed & new.bits).single_squares() {
+                diff.added.push((sq, *piece));
+            }
+        }
+        diff.removed.sort_by_key(|&(sq, _)| sq);
+        diff.added.sort_by_key(|&(sq, _)| sq);
+
+        diff.turn = changed(self.turn, other.turn);
+        diff.castling = changed(self.castling_rights(), other.castling_rights());
+        diff.en_passant_target = changed(
+            target_square(self.en_passant_target),
+            target_square(other.en_passant_target),
+        );
+        diff.reps_50 = changed(self.reps_50, other.reps_50);
+        diff.fullmove_number = changed(self.fullmove_number(), other.fullmove_number());
+
+        diff
+    }
+}
+
+impl BoardDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == BoardDiff::default()
+    }
+}
+
+/// One change per line, e.g. `-P e2`, `+P e4`, `turn: White -> Black`.
+impl fmt::Display for BoardDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (sq, piece) in &self.removed {
+            writeln!(f, "-{} {}", char::from(*piece), square_name(*sq))?;
+        }
+        for (sq, piece) in &self.added {
+            writeln!(f, "+{} {}", char::from(*piece), square_name(*sq))?;
+        }
+        if let Some((old, new)) = self.turn {
+            writeln!(f, "turn: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = self.castling {
+            writeln!(f, "castling: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = self.en_passant_target {
+            let name = |sq: Option<u8>| sq.map_or("-".to_string(), square_name);
+            writeln!(f, "en passant: {} -> {}", name(old), name(new))?;
+        }
+        if let Some((old, new)) = self.reps_50 {
+            writeln!(f, "halfmove clock: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = self.fullmove_number {
+            writeln!(f, "move number: {} -> {}", old, new)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::castle::Castle, moves::magic};
+
+    /// Diff of `fen` and the position after `uci`, pieces only, as "-Pe2 +Pe4" style text.
+    fn piece_diff(fen: &str, uci: &str) -> (String, BoardDiff) {
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        let m = board.parse_uci_move(uci).unwrap();
+        let diff = board.diff(&board.make_unchecked_move(&m));
+
+        let removed = diff
+            .removed
+            .iter()
+            .map(|(sq, p)| format!("-{}{}", char::from(*p), square_name(*sq)));
+        let added = diff
+            .added
+            .iter()
+            .map(|(sq, p)| format!("+{}{}", char::from(*p), square_name(*sq)));
+        let text = removed.chain(added).collect::<Vec<_>>().join(" ");
+
+        (text, diff)
+    }
+
+    #[test]
+    fn standard_move_and_capture() {
+        magic::init();
+        let board = Board::new_game();
+        assert!(board.diff(&board).is_empty());
+
+        // The target is only set when an enemy pawn can take en passant.
+        let (pieces, diff) = piece_diff("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", "e2e4");
+        assert_eq!(pieces, "-Pe2 +Pe4");
+        assert_eq!(diff.turn, Some((Color::White, Color::Black)));
+        assert_eq!(
+            diff.en_passant_target.map(|(_, new)| new.map(square_name)),
+            Some(Some("e3".to_string()))
+        );
+        assert_eq!(diff.castling, None);
+
+        let (pieces, diff) = piece_diff("4k3/8/3p4/8/4N3/8/8/4K3 w - - 7 30", "e4c5");
+        assert_eq!(pieces, "-Ne4 +Nc5");
+        assert_eq!(diff.reps_50, Some((7, 8)));
+        let (pieces, diff) = piece_diff("4k3/8/3p4/8/4N3/8/8/4K3 w - - 7 30", "e4d6");
+        assert_eq!(pieces, "-Ne4 -pd6 +Nd6");
+        assert_eq!(diff.reps_50, Some((7, 0)));
+    }
+
+    #[test]
+    fn castling_moves_king_and_rook() {
+        magic::init();
+        let (pieces, diff) = piece_diff("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1");
+        assert_eq!(pieces, "-Rh1 -Ke1 +Kg1 +Rf1");
+        let (old, new) = diff.castling.unwrap();
+        assert_eq!((old.white, new.white), (Castle::Both, Castle::No));
+        assert_eq!(old.black, new.black);
+
+        let (pieces, diff) = piece_diff("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8");
+        assert_eq!(pieces, "-ke8 -ra8 +rd8 +kc8");
+        assert!(diff.to_string().contains("castling: KQkq -> KQ\n"));
+    }
+
+    #[test]
+    fn promotion_and_en_passant() {
+        magic::init();
+        let (pieces, _) = piece_diff("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q");
+        assert_eq!(pieces, "-Pa7 -nb8 +Qb8");
+
+        let (pieces, diff) = piece_diff("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6");
+        assert_eq!(pieces, "-Pe5 -pd5 +Pd6");
+        assert_eq!(
+            diff.to_string(),
+            "-P e5\n-p d5\n+P d6\nturn: White -> Black\nen passant: d6 -> -\n"
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use anyhow::anyhow;
+
 use crate::moves::{
     generators,
     move_type::{Move, MoveKind},
+    notation::square_name,
 };
 
 use super::{
@@ -19,9 +22,9 @@ use super::{
 /// (and vice versa for black). This reuses the existing generator logic with correct
 /// file-wrapping masks.
 pub fn available_en_passant_moves(board: &Board) -> (Option<Move>, Option<Move>) {
-    if board.en_passant_target.bits == 0 {
+    let Some(to) = board.en_passant_target.lsb_index() else {
         return (None, None);
-    }
+    };
 
     let target = board.en_passant_target;
     let capturing_pawn = Piece::new(board.turn, PieceKind::Pawn);
@@ -33,7 +36,6 @@ pub fn available_en_passant_moves(board: &Board) -> (Option<Move>, Option<Move>)
         Color::Black => generators::white_pawn_attack(target, Bitboard::new(0), pawns),
     };
 
-    let to = target.bits.trailing_zeros() as u8;
     let mut result = (None, None);
     let mut iter = attackers.single_squares();
 
@@ -53,14 +55,67 @@ pub fn available_en_passant_moves(board: &Board) -> (Option<Move>, Option<Move>)
     result
 }
 
-fn en_passant_captured_sq(to: u8, turn: Color) -> u64 {
+/// Bit of the pawn taken by an en passant capture landing on `to`.
+pub(crate) fn en_passant_captured_sq(to: u8, turn: Color) -> u64 {
     match turn {
         Color::White => (1u64 << to) >> 8,
         Color::Black => (1u64 << to) << 8,
     }
 }
 
+/// Checks an en passant target read from a FEN against the pawns.
+///
+/// The target must be on the rank just passed by a double push of the side
+/// not to move, with that pawn in front of it and both the target and the
+/// pawn's starting square empty; otherwise the FEN is rejected. A target no
+/// pawn of `turn` can capture onto is cleared, as
+/// [`BBPosition::calculate_en_passant_target`] would never have set it.
+pub fn validate_en_passant_target(
+    position: &BBPosition,
+    turn: Color,
+    target: Bitboard,
+) -> Result<Bitboard, anyhow::Error> {
+    let Some(to) = target.lsb_index() else {
+        return Ok(target);
+    };
+    let (rank, start) = match turn {
+        Color::White => (5, target.bits << 8),
+        Color::Black => (2, target.bits >> 8),
+    };
+    if to / 8 != rank {
+        return Err(anyhow!(
+            "en passant square {} is not on the {} rank",
+            square_name(to),
+            if rank == 5 { "6th" } else { "3rd" }
+        ));
+    }
+    let pushed_pawn = Piece::new(turn.other(), PieceKind::Pawn);
+    if position.get(pushed_pawn).bits & en_passant_captured_sq(to, turn) == 0 {
+        return Err(anyhow!(
+            "en passant square {} has no pawn in front of it",
+            square_name(to)
+        ));
+    }
+    if position.occupied_cells().bits & (target.bits | start) != 0 {
+        return Err(anyhow!(
+            "en passant square {} does not follow a double pawn push",
+            square_name(to)
+        ));
+    }
+
+    let pawns = position.get(Piece::new(turn, PieceKind::Pawn));
+    let capturers = match turn {
+        Color::White => generators::black_pawn_attack(target, Bitboard::new(0), pawns),
+        Color::Black => generators::white_pawn_attack(target, Bitboard::new(0), pawns),
+    };
+    if capturers.bits == 0 {
+        return Ok(Bitboard::new(0));
+    }
+    Ok(target)
+}
+
 /// Applies an en-passant capture in place.
+/// Only the piece bitboards change: the caller updates the occupancy caches.
 pub fn apply_en_passant_in_place(bitboards: &mut BBPosition, player_move: &Move) {
     let MoveKind::EnPassant { from, to } = player_move.action else {
         panic!("Fatal Error: apply_en_passant_in_place called with non-EnPassant move");
@@ -76,6 +131,7 @@ pub fn apply_en_passant_in_place(bitboards: &mut BBPosition, player_move: &Move)
 }
 
 /// Reverses an en-passant capture in place.
+/// Only the piece bitboards change: the caller updates the occupancy caches.
 pub fn unapply_en_passant_in_place(bitboards: &mut BBPosition, player_move: &Move) {
     let MoveKind::EnPassant { from, to } = player_move.action else {
         unreachable!("unapply_en_passant_in_place called with non-EnPassant move");
@@ -91,15 +147,3 @@ pub fn unapply_en_passant_in_place(bitboards: &mut BBPosition, player_move: &Mov
     let enemy_pawn = Piece::new(turn.other(), PieceKind::Pawn);
     bitboards.get_mut(enemy_pawn).bits |= captured_sq;
 }
-
-/// Returns the updated position after an en passant capture.
-pub fn bitboards_after_en_passant(
-    current_bitboards: &BBPosition,
-    player_move: &Move,
-) -> BBPosition {
-    let mut new_bitboards = current_bitboards.clone();
-    apply_en_passant_in_place(&mut new_bitboards, player_move);
-    new_bitboards.recompute_occupied();
-
-    new_bitboards
-}
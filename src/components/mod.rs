@@ -1,6 +1,7 @@
 pub mod board;
 pub mod castle;
 pub mod constants;
+pub mod diff;
 pub mod en_passant;
 pub mod hash;
 pub mod pieces;
@@ -38,7 +38,7 @@ impl Color {
     }
 }
 
-#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PieceKind {
     Pawn,
     Knight,
@@ -73,7 +73,7 @@ impl PieceKind {
 }
 
 // make it Copy cause a reference to Piece (usize) is 64 bits while Piece itself is 16 bits.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub color: Color,
     pub kind: PieceKind,
@@ -161,6 +161,24 @@ impl TryFrom<char> for Piece {
     }
 }
 
+impl From<Piece> for char {
+    /// FEN letter of the piece: uppercase for white, lowercase for black.
+    fn from(piece: Piece) -> Self {
+        let c = match piece.kind {
+            PieceKind::Pawn => 'p',
+            PieceKind::Knight => 'n',
+            PieceKind::Bishop => 'b',
+            PieceKind::Rook => 'r',
+            PieceKind::Queen => 'q',
+            PieceKind::King => 'k',
+        };
+        match piece.color {
+            Color::White => c.to_ascii_uppercase(),
+            Color::Black => c,
+        }
+    }
+}
+
 /// Produces the offset i need to right shift 1 to obtain the bitboard.
 pub struct SingleSquareIterator {
     bits: u64,
@@ -181,6 +199,12 @@ impl Iterator for SingleSquareIterator {
     }
 }
 
+/// A set of squares, one bit each.
+///
+/// Bit 0 is h1 and bits run from the h-file to the a-file along a rank, then
+/// up the board: bit 7 is a1, bit 56 is h8 and bit 63 is a8. The square index
+/// of a bit is its position, so `rank = index / 8` and `file = 7 - index % 8`
+/// with files counted from a.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Bitboard {
     pub bits: u64,
@@ -246,8 +270,9 @@ impl ops::Not for Bitboard {
 
 impl fmt::Display for Bitboard {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // From a8 down to h1, one rank per line.
         for pos in 0..64 {
-            if (self.bits << pos).leading_zeros() == 0_u32 {
+            if self.bits & 1 << (63 - pos) != 0 {
                 write!(f, " 1 ")?;
             } else {
                 write!(f, " 0 ")?;
@@ -305,4 +330,49 @@ impl Bitboard {
     pub fn count_bits(&self) -> i32 {
         self.bits.count_ones() as i32
     }
+
+    /// Index of the lowest set square, the one closest to h1. None if empty.
+    pub fn lsb_index(&self) -> Option<u8> {
+        (self.bits != 0).then(|| self.bits.trailing_zeros() as u8)
+    }
+
+    /// Index of the highest set square, the one closest to a8. None if empty.
+    pub fn msb_index(&self) -> Option<u8> {
+        (self.bits != 0).then(|| 63 - self.bits.leading_zeros() as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_bits_counts_set_squares() {
+        for (bits, count) in [
+            (0, 0),
+            (1, 1),
+            (1 << 63, 1),
+            (0xFF00, 8),
+            (0x8100_0000_0000_0081, 4),
+            (u64::MAX, 64),
+        ] {
+            assert_eq!(Bitboard::new(bits).count_bits(), count, "{:#x}", bits);
+        }
+    }
+
+    #[test]
+    fn corner_squares_map_to_the_documented_bits() {
+        for (name, index) in [("h1", 0), ("a1", 7), ("h8", 56), ("a8", 63)] {
+            let square = Bitboard::try_from(name).unwrap();
+            assert_eq!(square.bits, 1 << index, "{}", name);
+            assert_eq!(square.lsb_index(), Some(index), "{}", name);
+            assert_eq!(square.msb_index(), Some(index), "{}", name);
+        }
+
+        let corners = Bitboard::new(1 | 1 << 7 | 1 << 56 | 1 << 63);
+        assert_eq!(corners.lsb_index(), Some(0));
+        assert_eq!(corners.msb_index(), Some(63));
+        assert_eq!(Bitboard::new(0).lsb_index(), None);
+        assert_eq!(Bitboard::new(0).msb_index(), None);
+    }
 }
@@ -1,13 +1,16 @@
+use anyhow::anyhow;
+
 use super::{
     castle, constants, en_passant,
     pieces::{Bitboard, Color, Piece, PieceKind},
 };
 use crate::moves::{
-    generators,
+    generators, lines, magic,
     move_type::{Move, MoveKind},
+    notation::square_name,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BBPosition {
     white_pawn: Bitboard,
     white_knight: Bitboard,
@@ -28,6 +31,7 @@ pub struct BBPosition {
     /// Cached squares occupation (`occupied_white | occupied_black`).
     pub occupied_all: Bitboard,
     /// Maps each square index to the piece on it for O(1) lookup. Must be kept in sync.
+    /// Indexed like the [`Bitboard`] bits: 0 is h1, 7 is a1 and 63 is a8.
     piece_map: [Option<Piece>; 64],
 }
 
@@ -148,6 +152,28 @@ impl BBPosition {
         }
     }
 
+    /// The standard starting position, built without parsing a FEN string.
+    pub fn startpos() -> Self {
+        let mut position = Self {
+            white_pawn: Bitboard::new(0x0000_0000_0000_ff00),
+            white_knight: Bitboard::new(0x0000_0000_0000_0042),
+            white_bishop: Bitboard::new(0x0000_0000_0000_0024),
+            white_rook: Bitboard::new(0x0000_0000_0000_0081),
+            white_queen: Bitboard::new(0x0000_0000_0000_0010),
+            white_king: Bitboard::new(0x0000_0000_0000_0008),
+            black_pawn: Bitboard::new(0x00ff_0000_0000_0000),
+            black_knight: Bitboard::new(0x4200_0000_0000_0000),
+            black_bishop: Bitboard::new(0x2400_0000_0000_0000),
+            black_rook: Bitboard::new(0x8100_0000_0000_0000),
+            black_queen: Bitboard::new(0x1000_0000_0000_0000),
+            black_king: Bitboard::new(0x0800_0000_0000_0000),
+            ..Self::empty()
+        };
+        position.recompute_occupied();
+
+        position
+    }
+
     /// Recomputes all cached fields (occupied bitboards and piece map) from the 12 piece
     /// bitboards. Call this after any mutation that goes through `get_mut()` directly
     /// (castle, en passant, FEN parsing). Hot-path moves use incremental updates instead.
@@ -169,13 +195,8 @@ impl BBPosition {
         // Build into a local array first to avoid a simultaneous mutable borrow on self.piece_map
         // while self.into_iter() holds an immutable borrow of self.
         let mut new_map = [None; 64];
-        for (piece, bitboard) in self.into_iter() {
-            let mut bits = bitboard.bits;
-            while bits != 0 {
-                let sq = bits.trailing_zeros() as usize;
-                new_map[sq] = Some(*piece);
-                bits &= bits - 1;
-            }
+        for (piece, sq) in self.iter_pieces() {
+            new_map[sq as usize] = Some(piece);
         }
         self.piece_map = new_map;
     }
@@ -224,30 +245,121 @@ impl BBPosition {
         }
     }
 
+    /// Parses the piece placement field of a FEN string (ranks 8 to 1, files a to h).
+    /// Fails unless there are 8 ranks of exactly 8 squares each and one king per side.
     pub fn from_fen_notation(s: &str) -> Result<Self, anyhow::Error> {
         let mut bb = Self::empty();
-        let mut index: i32 = 63;
 
-        let t = s.replace("/", "");
+        let ranks: Vec<&str> = s.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(anyhow!(
+                "piece placement must have 8 ranks, found {}: {}",
+                ranks.len(),
+                s
+            ));
+        }
 
-        for c in t.chars() {
-            if let Some(n) = c.to_digit(10) {
-                // sequence of empty squares
-                index -= n as i32;
-                continue;
-            } else {
-                // found a piece -> update the board
-                let piece: Piece = c.try_into()?;
-                bb.get_mut(piece).bits |= 1 << index;
-                index -= 1;
+        for (i, rank) in ranks.iter().enumerate() {
+            let rank_number = 8 - i;
+            let mut files = 0;
+            for c in rank.chars() {
+                let width = match c.to_digit(10) {
+                    // sequence of empty squares
+                    Some(n @ 1..=8) => n as usize,
+                    Some(_) => return Err(anyhow!("invalid empty square count '{}' in {}", c, s)),
+                    None => {
+                        // found a piece -> update the board
+                        let piece: Piece = c.try_into()?;
+                        if files < 8 {
+                            let index = (7 - i) * 8 + (7 - files);
+                            bb.get_mut(piece).bits |= 1 << index;
+                        }
+                        1
+                    }
+                };
+                files += width;
+            }
+            if files != 8 {
+                return Err(anyhow!(
+                    "rank {} covers {} squares instead of 8: {}",
+                    rank_number,
+                    files,
+                    rank
+                ));
             }
         }
 
         bb.recompute_occupied();
+        bb.validate_kings()?;
 
         Ok(bb)
     }
 
+    /// Writes the piece placement field of a FEN string (ranks 8 to 1, files a to h).
+    pub fn to_fen_notation(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8u8).rev() {
+            let mut empty = 0;
+            for file in 0..8u8 {
+                match self.piece_at(rank * 8 + (7 - file)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.into());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen
+    }
+
+    /// Checks that each side has exactly one king.
+    fn validate_kings(&self) -> Result<(), anyhow::Error> {
+        for color in [Color::White, Color::Black] {
+            let kings = self.get(Piece::new(color, PieceKind::King)).count_bits();
+            if kings != 1 {
+                return Err(anyhow!(
+                    "{} must have exactly one king, found {}",
+                    color,
+                    kings
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the piece placement is a plausible chess position: exactly one
+    /// king per side, at most 8 pawns and 16 pieces per side, no pawns on the first
+    /// or last rank.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        self.validate_kings()?;
+        for color in [Color::White, Color::Black] {
+            let pawns = self.get(Piece::new(color, PieceKind::Pawn));
+            if pawns.count_bits() > 8 {
+                return Err(anyhow!("{} has more than 8 pawns", color));
+            }
+            if pawns.bits & (constants::FIRST_ROW | constants::EIGHT_ROW) != 0 {
+                return Err(anyhow!("{} has a pawn on the first or last rank", color));
+            }
+            if self.occupied_by(color).count_bits() > 16 {
+                return Err(anyhow!("{} has more than 16 pieces", color));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn occupied_cells(&self) -> Bitboard {
         self.occupied_all
     }
@@ -275,6 +387,30 @@ impl BBPosition {
         self.piece_map[left_shift as usize]
     }
 
+    /// Builds a position from one entry per square, indexed like [`BBPosition::piece_at`]:
+    /// 0 is h1, 7 is a1 and 63 is a8. No legality checks are made.
+    pub fn from_piece_array(arr: &[Option<Piece>; 64]) -> Self {
+        let mut position = Self::empty();
+        for (sq, piece) in arr.iter().enumerate() {
+            if let Some(piece) = piece {
+                position.get_mut(*piece).bits |= 1u64 << sq;
+            }
+        }
+        position.recompute_occupied();
+        position
+    }
+
+    /// Yields every piece with its square index, piece kind by piece kind.
+    pub fn iter_pieces(&self) -> impl Iterator<Item = (Piece, u8)> + '_ {
+        self.into_iter()
+            .flat_map(|(piece, bitboard)| bitboard.single_squares().map(|sq| (*piece, sq)))
+    }
+
+    /// Inverse of [`BBPosition::from_piece_array`].
+    pub fn to_piece_array(&self) -> [Option<Piece>; 64] {
+        self.piece_map
+    }
+
     /// Generates all possible captures by piece.
     /// This can be called with a single piece Bitboard (a Bitboard with just one single 1 inside its u64)
     /// or with a multi-pieces Bitboard.
@@ -400,17 +536,133 @@ impl BBPosition {
             .fold(Bitboard::new(0), |acc, x| acc | x)
     }
 
-    /// Returns true if at least one of the squares in the Bitboard is defended
+    /// Returns true if `square` holds a piece of `color` defended by another one.
+    /// Only looks for attackers of the square instead of building the whole
+    /// `defended_squares` map.
     pub fn square_is_defended_by(&self, square: u8, color: Color) -> bool {
-        self.defended_squares(color).bits & (1 << square) != 0
+        self.occupied_by(color).bits & (1u64 << square) != 0
+            && self.is_square_attacked(square, color)
+    }
+
+    /// Returns every piece of either color attacking `square` when only the
+    /// squares in `occupancy` are occupied.
+    ///
+    /// Works outward from the square: a knight, king or pawn standing on
+    /// `square` reaches exactly the squares from which the same kind of piece
+    /// reaches it, and a slider ray cast from `square` stops on the first
+    /// blocker. Pieces missing from `occupancy` neither attack nor block, so
+    /// exchange evaluation can lift captured pieces off to reveal x-rays.
+    pub fn attackers_to(&self, square: u8, occupancy: Bitboard) -> Bitboard {
+        let target = Bitboard::new(1u64 << square);
+        let empty = Bitboard::new(0);
+        let both = |kind| {
+            self.get(Piece::new(Color::White, kind)).bits
+                | self.get(Piece::new(Color::Black, kind)).bits
+        };
+
+        let queens = both(PieceKind::Queen);
+        let diagonal =
+            generators::bishop(target, empty, occupancy).bits & (both(PieceKind::Bishop) | queens);
+        let orthogonal =
+            generators::rook(target, empty, occupancy).bits & (both(PieceKind::Rook) | queens);
+        let knights = generators::knight(target, empty, empty).bits & both(PieceKind::Knight);
+        let kings = generators::king(target, empty, empty).bits & both(PieceKind::King);
+        // Cast as a pawn of the other color: its captures land on the squares
+        // an attacking pawn would capture from.
+        let all = Bitboard::new(u64::MAX);
+        let pawns = generators::black_pawn_attack(target, empty, all).bits & self.get('P').bits
+            | generators::white_pawn_attack(target, empty, all).bits & self.get('p').bits;
+
+        Bitboard::new((diagonal | orthogonal | knights | kings | pawns) & occupancy.bits)
+    }
+
+    /// Returns the squares a rook on `square` would reach only once the
+    /// `blockers` it hits first under `occupancy` are lifted off.
+    pub fn xray_rook_attacks(square: u8, occupancy: Bitboard, blockers: Bitboard) -> Bitboard {
+        let attacks = magic::rook_attacks(square, occupancy.bits);
+        let hit = blockers.bits & attacks;
+        Bitboard::new(attacks ^ magic::rook_attacks(square, occupancy.bits ^ hit))
+    }
+
+    /// Bishop counterpart of [`BBPosition::xray_rook_attacks`].
+    pub fn xray_bishop_attacks(square: u8, occupancy: Bitboard, blockers: Bitboard) -> Bitboard {
+        let attacks = magic::bishop_attacks(square, occupancy.bits);
+        let hit = blockers.bits & attacks;
+        Bitboard::new(attacks ^ magic::bishop_attacks(square, occupancy.bits ^ hit))
+    }
+
+    /// Returns the pieces of `color` attacking `square` directly or lined up
+    /// behind their own attackers, such as a queen backing up a rook.
+    pub fn battery_attackers_to(&self, square: u8, color: Color) -> Bitboard {
+        let ours = self.occupied_by(color);
+        let mut occupancy = self.occupied_cells();
+        let mut found = Bitboard::new(0);
+        loop {
+            let attackers = self.attackers_to(square, occupancy) & ours & !found;
+            if attackers.bits == 0 {
+                return found;
+            }
+            found = found | attackers;
+            occupancy = occupancy & !attackers;
+        }
+    }
+
+    /// Returns true if a piece of color `by` attacks or defends `square`.
+    pub fn is_square_attacked(&self, square: u8, by: Color) -> bool {
+        self.attackers_to(square, self.occupied_cells()).bits & self.occupied_by(by).bits != 0
     }
 
     pub fn is_in_check(&self, side: Color) -> bool {
-        self.get(match side {
-            Color::White => 'K',
-            Color::Black => 'k',
-        }) & self.attacked_squares(side.other())
-            != Bitboard::new(0)
+        self.get(Piece::new(side, PieceKind::King))
+            .lsb_index()
+            .is_some_and(|king| self.is_square_attacked(king, side.other()))
+    }
+
+    /// Returns the pieces of `color` pinned to their own king.
+    pub fn pinned_pieces(&self, color: Color) -> Bitboard {
+        Bitboard::new(self.pins(color).fold(0, |acc, (sq, _)| acc | 1u64 << sq))
+    }
+
+    /// Returns the squares a pinned piece on `square` can move to without
+    /// exposing its king: the ray up to and including the pinning piece.
+    /// None if the piece is not pinned.
+    pub fn pin_ray(&self, color: Color, square: u8) -> Option<Bitboard> {
+        self.pins(color)
+            .find(|&(pinned, _)| pinned == square)
+            .map(|(_, ray)| Bitboard::new(ray))
+    }
+
+    /// Yields each pinned piece of `color` with its pin ray.
+    ///
+    /// Rays cast from the king see through its own pieces and stop on the first
+    /// enemy; an enemy slider reached that way pins the single own piece
+    /// standing in between, if there is exactly one.
+    fn pins(&self, color: Color) -> impl Iterator<Item = (u8, u64)> + '_ {
+        let king = self.get(Piece::new(color, PieceKind::King)).bits;
+        let king_sq = king.trailing_zeros() as u8;
+        let (ours, enemies) = self.occupied_by_both(color);
+        let enemy = |kind| self.get(Piece::new(color.other(), kind)).bits;
+        let queens = enemy(PieceKind::Queen);
+
+        let snipers = if king == 0 {
+            0
+        } else {
+            magic::rook_attacks(king_sq, enemies.bits) & (enemy(PieceKind::Rook) | queens)
+                | magic::bishop_attacks(king_sq, enemies.bits) & (enemy(PieceKind::Bishop) | queens)
+        };
+        Bitboard::new(snipers)
+            .single_squares()
+            .filter_map(move |sniper| {
+                let sniper_bb = 1u64 << sniper;
+                let between = lines::between(king_sq, sniper);
+                let blockers = between & ours.bits;
+                (blockers.count_ones() == 1 && between & enemies.bits == 0).then(|| {
+                    (
+                        blockers.trailing_zeros() as u8,
+                        between & !blockers | sniper_bb,
+                    )
+                })
+            })
     }
 
     /// Returns true if the moving side's king is in check after a standard (or promotion) move.
@@ -562,13 +814,25 @@ impl BBPosition {
                 self.piece_map[from as usize] = None;
                 self.piece_map[to as usize] = Some(player_move.piece);
             }
-            MoveKind::Castle(side) => {
-                castle::apply_castling_in_place(self, player_move.piece.color, side);
-                self.recompute_occupied();
+            MoveKind::Castle { .. } => {
+                castle::apply_castling_in_place(self, player_move);
+                let (king_from, king_to, rook_from, rook_to) = castle::castle_squares(player_move);
+                let color = player_move.piece.color;
+                self.relocate_in_caches(
+                    color,
+                    1 << king_from | 1 << rook_from,
+                    &[
+                        (king_to, Piece::new(color, PieceKind::King)),
+                        (rook_to, Piece::new(color, PieceKind::Rook)),
+                    ],
+                );
             }
-            MoveKind::EnPassant { .. } => {
+            MoveKind::EnPassant { from, to } => {
                 en_passant::apply_en_passant_in_place(self, player_move);
-                self.recompute_occupied();
+                let color = player_move.piece.color;
+                let captured_sq = en_passant::en_passant_captured_sq(to, color);
+                self.relocate_in_caches(color, 1 << from, &[(to, player_move.piece)]);
+                self.relocate_in_caches(color.other(), captured_sq, &[]);
             }
             MoveKind::Promote {
                 from,
@@ -605,6 +869,8 @@ impl BBPosition {
                 self.piece_map[to as usize] = Some(Piece::new(player_move.piece.color, to_piece));
             }
         }
+
+        self.debug_assert_cache_consistent();
     }
 
     /// Reverses a previously applied move, restoring the captured piece if any.
@@ -638,13 +904,27 @@ impl BBPosition {
                 self.piece_map[from as usize] = Some(player_move.piece);
                 self.piece_map[to as usize] = captured;
             }
-            MoveKind::Castle(side) => {
-                castle::unapply_castling_in_place(self, player_move.piece.color, side);
-                self.recompute_occupied();
+            MoveKind::Castle { .. } => {
+                castle::unapply_castling_in_place(self, player_move);
+                let (king_from, king_to, rook_from, rook_to) = castle::castle_squares(player_move);
+                let color = player_move.piece.color;
+                self.relocate_in_caches(
+                    color,
+                    1 << king_to | 1 << rook_to,
+                    &[
+                        (king_from, Piece::new(color, PieceKind::King)),
+                        (rook_from, Piece::new(color, PieceKind::Rook)),
+                    ],
+                );
             }
-            MoveKind::EnPassant { .. } => {
+            MoveKind::EnPassant { from, to } => {
                 en_passant::unapply_en_passant_in_place(self, player_move);
-                self.recompute_occupied();
+                let color = player_move.piece.color;
+                let captured_sq = en_passant::en_passant_captured_sq(to, color);
+                self.relocate_in_caches(color, 1 << to, &[(from, player_move.piece)]);
+                let enemy_pawn = Piece::new(color.other(), PieceKind::Pawn);
+                let captured = captured_sq.trailing_zeros() as u8;
+                self.relocate_in_caches(color.other(), 0, &[(captured, enemy_pawn)]);
             }
             MoveKind::Promote {
                 from,
@@ -681,64 +961,66 @@ impl BBPosition {
                 self.piece_map[to as usize] = captured;
             }
         }
+
+        self.debug_assert_cache_consistent();
     }
 
     /// Updates the position after a move is made. This should not be called manually cause
     /// it does not updates all the other fields of a chess board
     pub fn inner_make_unchecked_move(&self, player_move: &Move) -> Self {
-        match player_move.action {
-            MoveKind::Standard { from, to, captured } => {
-                let from_bb = Bitboard::new(1 << from);
-                let to_bb = Bitboard::new(1 << to);
-
-                let mut resulting_bitboards = self.clone();
-                let piece_bitboard = resulting_bitboards.get_mut(player_move.piece);
-
-                // remove piece from the old position
-                *piece_bitboard = *piece_bitboard & !from_bb;
+        // Same incremental cache update as make/unmake: no full recomputation.
+        let mut resulting_bitboards = self.clone();
+        resulting_bitboards.apply_move(player_move);
+        resulting_bitboards
+    }
 
-                // set the piece in the new position
-                piece_bitboard.bits |= to_bb.bits;
+    /// Updates the occupancy and piece map caches of `color` for pieces that
+    /// left the `vacated` squares and now stand on the `filled` ones.
+    /// Vacated squares are cleared first, so a square can be both (Chess960 castling).
+    fn relocate_in_caches(&mut self, color: Color, vacated: u64, filled: &[(u8, Piece)]) {
+        let filled_bits = filled.iter().fold(0, |acc, &(sq, _)| acc | 1u64 << sq);
+        let occupied = match color {
+            Color::White => &mut self.occupied_white,
+            Color::Black => &mut self.occupied_black,
+        };
+        occupied.bits = occupied.bits & !vacated | filled_bits;
+        self.occupied_all = self.occupied_white | self.occupied_black;
 
-                if let Some(cap) = captured {
-                    resulting_bitboards.get_mut(cap).bits &= !to_bb.bits;
-                }
+        for sq in Bitboard::new(vacated).single_squares() {
+            self.piece_map[sq as usize] = None;
+        }
+        for &(sq, piece) in filled {
+            self.piece_map[sq as usize] = Some(piece);
+        }
+    }
 
-                resulting_bitboards.recompute_occupied();
-                resulting_bitboards
+    /// Checks that the piece bitboards are disjoint and match the cached fields.
+    fn debug_assert_cache_consistent(&self) {
+        if cfg!(all(debug_assertions, feature = "engine-diagnostics")) {
+            let mut seen = 0u64;
+            for (piece, bitboard) in self {
+                let overlap = seen & bitboard.bits;
+                assert!(
+                    overlap == 0,
+                    "{} overlaps another piece on {}",
+                    piece,
+                    square_name(overlap.trailing_zeros() as u8)
+                );
+                seen |= bitboard.bits;
             }
-            MoveKind::Castle(side) => {
-                castle::bitboards_after_castling(self, player_move.piece.color, side)
-            }
-            MoveKind::EnPassant { .. } => en_passant::bitboards_after_en_passant(self, player_move),
-            MoveKind::Promote {
-                from,
-                to,
-                to_piece,
-                captured,
-            } => {
-                let from_bb = Bitboard::new(1 << from);
-                let to_bb = Bitboard::new(1 << to);
-
-                let mut resulting_bitboards = self.clone();
-                let pawn_bitboard = resulting_bitboards.get_mut(player_move.piece);
-
-                // remove pawn from the old position
-                pawn_bitboard.bits &= !from_bb.bits;
-
-                let new_piece_bitboard =
-                    resulting_bitboards.get_mut(Piece::new(player_move.piece.color, to_piece));
-
-                // set the piece it is promoting to in the new position
-                new_piece_bitboard.bits |= to_bb.bits;
 
-                if let Some(cap) = captured {
-                    resulting_bitboards.get_mut(cap).bits &= !to_bb.bits;
-                }
-
-                resulting_bitboards.recompute_occupied();
-                resulting_bitboards
-            }
+            let mut fresh = self.clone();
+            fresh.recompute_occupied();
+            assert_eq!(
+                self.occupied_white, fresh.occupied_white,
+                "stale white occupancy"
+            );
+            assert_eq!(
+                self.occupied_black, fresh.occupied_black,
+                "stale black occupancy"
+            );
+            assert_eq!(self.occupied_all, fresh.occupied_all, "stale occupancy");
+            assert_eq!(self.piece_map, fresh.piece_map, "stale piece map");
         }
     }
 
@@ -759,7 +1041,7 @@ impl BBPosition {
                 }
 
                 let possible_en_passant_doer =
-                    (to.bits << 1 & constants::NOT_H_RANK) | (to.bits >> 1 & constants::NOT_A_RANK);
+                    (to.bits << 1 & constants::NOT_H_FILE) | (to.bits >> 1 & constants::NOT_A_FILE);
                 match player_move.piece.color {
                     Color::White => {
                         let black_pawns = self.get('p').bits;
@@ -781,3 +1063,316 @@ impl BBPosition {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{board::Board, hash::xorshift64},
+        moves::magic,
+    };
+
+    fn fen_error(placement: &str) -> String {
+        BBPosition::from_fen_notation(placement)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn malformed_piece_placement_is_an_error() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        assert!(BBPosition::from_fen_notation(start).is_ok());
+
+        assert!(
+            fen_error("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").contains("rank 7 covers 7")
+        );
+        assert!(
+            fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR").contains("rank 1 covers 9")
+        );
+        assert!(
+            fen_error("rnbqkbnr/pppppppp/8/8/8/45/PPPPPPPP/RNBQKBNR").contains("rank 3 covers 9")
+        );
+        assert!(fen_error("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR").contains("8 ranks"));
+        assert!(fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8").contains("8 ranks"));
+        assert!(fen_error("rnbqkbnr/pppppppp/8/8/8/9/PPPPPPPP/RNBQKBNR").contains("'9'"));
+        assert!(
+            fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR")
+                .contains("White must have exactly one king")
+        );
+        assert!(fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNK").contains("found 2"));
+
+        let board = crate::components::board::Board::from_forsyth_edwards(
+            "rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+        );
+        assert!(
+            board
+                .unwrap_err()
+                .to_string()
+                .contains("Black must have exactly one king")
+        );
+    }
+
+    #[test]
+    fn occupancy_is_a_bitwise_union_even_if_boards_overlap() {
+        let mut position = BBPosition::empty();
+        // Deliberately break the disjointness invariant: two pieces on e4 (sq 27)
+        // and two more on the adjacent d4 (sq 28), which would carry with addition.
+        position.get_mut('P').bits |= 1 << 27 | 1 << 28;
+        position.get_mut('N').bits |= 1 << 27;
+        position.get_mut('n').bits |= 1 << 27 | 1 << 28;
+        position.recompute_occupied();
+
+        let union = (&position)
+            .into_iter()
+            .fold(0, |acc, (_, bitboard)| acc | bitboard.bits);
+        assert_eq!(union, 1 << 27 | 1 << 28);
+        assert_eq!(position.occupied_cells().bits, union);
+        assert_eq!(position.occupied_by(Color::White).bits, 1 << 27 | 1 << 28);
+        assert_eq!(position.occupied_by(Color::Black).bits, 1 << 27 | 1 << 28);
+        assert_eq!(position.empty_cells().bits, !union);
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "engine-diagnostics"))]
+    #[should_panic(expected = "overlaps another piece on e4")]
+    fn overlapping_boards_are_reported_after_a_move() {
+        let mut position = BBPosition::empty();
+        position.get_mut('K').bits |= 1 << 3; // e1
+        position.get_mut('k').bits |= 1 << 59; // e8
+        position.get_mut('P').bits |= 1 << 27;
+        position.get_mut('n').bits |= 1 << 27;
+        position.recompute_occupied();
+
+        let king_move = Move {
+            piece: 'K'.try_into().unwrap(),
+            action: MoveKind::Standard {
+                from: 3,
+                to: 11,
+                captured: None,
+            },
+        };
+        position.apply_move(&king_move);
+    }
+
+    #[test]
+    fn targeted_attack_test_agrees_with_the_full_attack_map() {
+        magic::init();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+        let mut state = 0x2545_F491_4F6C_DD1D;
+        for fen in fens {
+            let mut board = Board::from_forsyth_edwards(fen).unwrap();
+            for _ in 0..80 {
+                for by in [Color::White, Color::Black] {
+                    let position = &board.position;
+                    let full = position.attacked_squares(by).bits;
+                    // attacked_squares leaves out most squares holding pieces
+                    // of `by`, which is_square_attacked counts as defended.
+                    let free = !position.occupied_by(by).bits;
+                    for sq in (0..64).filter(|sq| free & 1 << sq != 0) {
+                        assert_eq!(
+                            position.is_square_attacked(sq, by),
+                            full & 1 << sq != 0,
+                            "square {} by {:?} in {}",
+                            square_name(sq),
+                            by,
+                            board.to_forsyth_edwards()
+                        );
+                    }
+                    let king = position.get(Piece::new(by.other(), PieceKind::King));
+                    assert_eq!(
+                        position.is_in_check(by.other()),
+                        king & position.attacked_squares(by) != Bitboard::new(0)
+                    );
+                    let defended = position.defended_squares(by).bits;
+                    for sq in 0..64 {
+                        assert_eq!(
+                            position.square_is_defended_by(sq, by),
+                            defended & 1 << sq != 0,
+                            "square {} defended by {:?} in {}",
+                            square_name(sq),
+                            by,
+                            board.to_forsyth_edwards()
+                        );
+                    }
+                }
+
+                let moves = board.generate_moves(false);
+                if moves.is_empty() {
+                    break;
+                }
+                let pick = xorshift64(&mut state) as usize % moves.len();
+                let m = moves.get_unsorted(pick, &board);
+                board.make_move(&m);
+            }
+        }
+    }
+
+    #[test]
+    fn attackers_to_lists_both_colors_and_reveals_x_rays() {
+        magic::init();
+        let squares = |names: &str| {
+            Bitboard::new(names.split_whitespace().fold(0, |acc, name| {
+                acc | 1 << crate::moves::notation::parse_square(name).unwrap()
+            }))
+        };
+        // e4 is hit by the d3 pawn, the f6 knight and the e2 queen, with the
+        // e1 rook behind the queen and the h7 bishop behind the g6 knight.
+        let position = BBPosition::from_fen_notation("k7/7b/5nN1/8/8/3P4/4Q3/K3R3").unwrap();
+        let e4 = crate::moves::notation::parse_square("e4").unwrap();
+        let occupied = position.occupied_cells();
+
+        assert_eq!(position.attackers_to(e4, occupied), squares("d3 f6 e2"));
+        assert_eq!(
+            position.attackers_to(e4, occupied & !squares("e2")),
+            squares("d3 f6 e1")
+        );
+        assert_eq!(
+            position.attackers_to(e4, occupied & !squares("g6")),
+            squares("d3 f6 e2 h7")
+        );
+        assert!(position.is_square_attacked(e4, Color::Black));
+    }
+
+    #[test]
+    fn pins_are_found_along_every_line_to_the_king() {
+        magic::init();
+        let squares = |names: &str| {
+            Bitboard::new(names.split_whitespace().fold(0, |acc, name| {
+                acc | 1 << crate::moves::notation::parse_square(name).unwrap()
+            }))
+        };
+        // The d2 bishop, e2 knight and f2 rook are pinned; the b1 knight and c1
+        // bishop shield the king from the a1 rook together, so neither is.
+        let board =
+            Board::from_forsyth_edwards("4r1k1/8/8/b7/7q/8/3BNR2/rNB1K3 w - - 0 1").unwrap();
+        let position = &board.position;
+        let square = |name| crate::moves::notation::parse_square(name).unwrap();
+
+        assert_eq!(position.pinned_pieces(Color::White), squares("d2 e2 f2"));
+        assert_eq!(position.pinned_pieces(Color::Black), Bitboard::new(0));
+        assert_eq!(
+            position.pin_ray(Color::White, square("e2")),
+            Some(squares("e3 e4 e5 e6 e7 e8"))
+        );
+        assert_eq!(position.pin_ray(Color::White, square("c1")), None);
+
+        let from = |name| {
+            let mut to: Vec<_> = board
+                .legal_moves()
+                .iter()
+                .filter(|m| m.to_uci().starts_with(name))
+                .map(|m| m.to_uci()[2..].to_string())
+                .collect();
+            to.sort();
+            to
+        };
+        assert_eq!(from("d2"), ["a5", "b4", "c3"]);
+        assert!(from("e2").is_empty());
+        assert!(from("f2").is_empty());
+    }
+
+    #[test]
+    fn x_rays_find_the_sliders_behind_the_first_attacker() {
+        magic::init();
+        let squares = |names: &str| {
+            Bitboard::new(names.split_whitespace().fold(0, |acc, name| {
+                acc | 1 << crate::moves::notation::parse_square(name).unwrap()
+            }))
+        };
+        // On e6: the e1 queen backs up the e2 rook and the c4 bishop the d5 pawn.
+        let position = BBPosition::from_fen_notation("k3r3/8/8/3P4/2B5/8/4R3/4Q2K").unwrap();
+        let e6 = crate::moves::notation::parse_square("e6").unwrap();
+        let occupied = position.occupied_cells();
+        let white = position.occupied_by(Color::White);
+
+        assert_eq!(
+            BBPosition::xray_rook_attacks(e6, occupied, white),
+            squares("e1")
+        );
+        assert_eq!(
+            BBPosition::xray_bishop_attacks(e6, occupied, white),
+            squares("c4")
+        );
+        // Enemy pieces are not lifted off unless passed as blockers.
+        assert_eq!(
+            BBPosition::xray_rook_attacks(e6, occupied, squares("e8")),
+            Bitboard::new(0)
+        );
+
+        assert_eq!(
+            position.battery_attackers_to(e6, Color::White),
+            squares("e1 e2 d5 c4")
+        );
+        assert_eq!(
+            position.battery_attackers_to(e6, Color::Black),
+            squares("e8")
+        );
+    }
+
+    #[test]
+    fn piece_map_slots_follow_the_bitboard_indices() {
+        let position = BBPosition::from_fen_notation("r6k/8/8/3q4/8/8/8/R3K2N").unwrap();
+        let expected = [
+            (0, 'N'),  // h1
+            (3, 'K'),  // e1
+            (7, 'R'),  // a1
+            (36, 'q'), // d5
+            (56, 'k'), // h8
+            (63, 'r'), // a8
+        ];
+        for (sq, piece) in expected {
+            let piece = Piece::try_from(piece).unwrap();
+            assert_eq!(
+                position.piece_map[sq],
+                Some(piece),
+                "{}",
+                square_name(sq as u8)
+            );
+            assert_eq!(position.get(piece).lsb_index(), Some(sq as u8));
+        }
+        let occupied = position.piece_map.iter().filter(|p| p.is_some()).count();
+        assert_eq!(occupied, expected.len());
+    }
+
+    #[test]
+    fn start_position_round_trips_through_the_piece_array() {
+        let start =
+            BBPosition::from_fen_notation("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").unwrap();
+        let array = start.to_piece_array();
+        assert_eq!(array[3], Piece::try_from('K').ok());
+        assert_eq!(array[59], Piece::try_from('k').ok());
+        assert_eq!(array.iter().filter(|p| p.is_none()).count(), 32);
+
+        let rebuilt = BBPosition::from_piece_array(&array);
+        assert_eq!(rebuilt.to_fen_notation(), start.to_fen_notation());
+        assert_eq!(rebuilt.occupied_cells(), start.occupied_cells());
+        assert_eq!(rebuilt.to_piece_array(), array);
+    }
+
+    #[test]
+    fn iter_pieces_lists_the_start_position() {
+        let start =
+            BBPosition::from_fen_notation("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").unwrap();
+        let pieces: Vec<_> = start.iter_pieces().collect();
+        assert_eq!(pieces.len(), 32);
+
+        let rank = |r: u8| {
+            let mut on_rank: Vec<_> = pieces
+                .iter()
+                .filter(|(_, sq)| sq / 8 == r)
+                .map(|(piece, sq)| (7 - sq % 8, char::from(*piece)))
+                .collect();
+            on_rank.sort();
+            on_rank.into_iter().map(|(_, c)| c).collect::<String>()
+        };
+        assert_eq!(rank(0), "RNBQKBNR");
+        assert_eq!(rank(1), "PPPPPPPP");
+        assert_eq!(rank(6), "pppppppp");
+        assert_eq!(rank(7), "rnbqkbnr");
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, anyhow};
+
+use crate::components::board::Board;
+use crate::moves::move_type::Move;
+
+use super::polyglot::polyglot_key;
+
+const ENTRY_SIZE: usize = 16;
+
+/// One Polyglot book entry. All fields are stored big-endian on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    /// `to file | to rank << 3 | from file << 6 | from rank << 9 | promotion << 12`,
+    /// promotion being 0 (none), 1 (knight), 2 (bishop), 3 (rook) or 4 (queen).
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    /// The move in UCI notation. Castling keeps the Polyglot form of the king
+    /// taking its own rook (e.g. "e1h1"), which `Board::parse_uci_move` accepts.
+    pub fn uci_move(&self) -> String {
+        let square = |bits: u16| {
+            let file = (b'a' + (bits & 7) as u8) as char;
+            let rank = (b'1' + (bits >> 3 & 7) as u8) as char;
+            format!("{}{}", file, rank)
+        };
+        let promotion = match self.raw_move >> 12 & 7 {
+            1 => "n",
+            2 => "b",
+            3 => "r",
+            4 => "q",
+            _ => "",
+        };
+
+        format!(
+            "{}{}{}",
+            square(self.raw_move >> 6),
+            square(self.raw_move),
+            promotion
+        )
+    }
+}
+
+/// Opening book in the Polyglot `.bin` format: 16-byte entries sorted by key.
+///
+/// Entries are looked up by the [`polyglot_key`] of the position, not by the
+/// engine's own Zobrist hash.
+#[derive(Debug, Default)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Loads the book at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read opening book {}", path.display()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(anyhow!(
+                "opening book size {} is not a multiple of {} bytes",
+                bytes.len(),
+                ENTRY_SIZE
+            ));
+        }
+        let mut entries: Vec<_> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::from_bytes)
+            .collect();
+        // Polyglot books are sorted already, a stable sort keeps their order of equal keys.
+        entries.sort_by_key(|e| e.key);
+
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All the entries stored for `key`.
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = self.entries.partition_point(|e| e.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// The entries for `board` paired with their move, if it is legal there.
+    /// Illegal moves (a key collision or a corrupt book) are skipped.
+    fn legal_entries<'a>(
+        &'a self,
+        board: &'a Board,
+    ) -> impl Iterator<Item = (&'a BookEntry, Move)> + 'a {
+        self.entries(polyglot_key(board))
+            .iter()
+            .filter_map(|e| board.parse_uci_move(&e.uci_move()).map(|m| (e, m)))
+    }
+
+    /// The book move with the highest weight, the first one stored among equals.
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        self.legal_entries(board)
+            .fold(None::<(&BookEntry, Move)>, |best, (e, m)| match best {
+                Some((b, _)) if b.weight >= e.weight => best,
+                _ => Some((e, m)),
+            })
+            .map(|(_, m)| m)
+    }
+
+    /// A book move chosen with a probability proportional to its weight.
+    /// `roll` is the caller's random number; it is reduced modulo the total weight.
+    pub fn weighted_move(&self, board: &Board, roll: u64) -> Option<Move> {
+        let candidates: Vec<_> = self
+            .legal_entries(board)
+            .filter(|(e, _)| e.weight > 0)
+            .collect();
+        let total: u64 = candidates.iter().map(|(e, _)| u64::from(e.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick = roll % total;
+        for (e, m) in candidates {
+            let weight = u64::from(e.weight);
+            if pick < weight {
+                return Some(m);
+            }
+            pick -= weight;
+        }
+        unreachable!("the pick is below the total weight")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::magic;
+
+    /// Polyglot key of the start position.
+    const START_KEY: u64 = 0x463b96181691fc9c;
+
+    fn entry(key: u64, from: &str, to: &str, weight: u16) -> [u8; ENTRY_SIZE] {
+        let square = |s: &str| {
+            let b = s.as_bytes();
+            u16::from(b[0] - b'a') | u16::from(b[1] - b'1') << 3
+        };
+        let raw_move = square(to) | square(from) << 6;
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&weight.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn the_book_returns_its_moves_for_the_start_position() {
+        magic::init();
+        let bytes = [
+            entry(START_KEY, "d2", "d4", 10),
+            entry(START_KEY, "e2", "e4", 30),
+            entry(START_KEY, "e2", "e5", 50),
+            entry(START_KEY ^ 1, "g1", "f3", 100),
+        ]
+        .concat();
+        let book = Book::from_bytes(&bytes).unwrap();
+        let board = Board::default();
+        assert_eq!(polyglot_key(&board), START_KEY);
+
+        assert_eq!(book.entries(START_KEY).len(), 3);
+        // e2e5 is not legal and is never played.
+        assert_eq!(book.best_move(&board).unwrap().to_uci(), "e2e4");
+        assert_eq!(book.weighted_move(&board, 9).unwrap().to_uci(), "d2d4");
+        assert_eq!(book.weighted_move(&board, 10).unwrap().to_uci(), "e2e4");
+        let (after_e4, _) = board.play_uci_moves(&["e2e4"]);
+        assert!(book.best_move(&after_e4).is_none());
+        assert!(Book::from_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn castling_is_decoded_as_the_king_taking_its_rook() {
+        magic::init();
+        let board = Board::from_forsyth_edwards("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let book = Book::from_bytes(&entry(polyglot_key(&board), "e1", "h1", 1)).unwrap();
+        assert_eq!(book.best_move(&board).unwrap().to_uci(), "e1g1");
+    }
+}
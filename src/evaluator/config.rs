@@ -0,0 +1,301 @@
+use anyhow::anyhow;
+
+/// Search tunables. The defaults are the tuned values; the UCI layer can change
+/// them between searches through the spin options in [`SPIN_OPTIONS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchConfig {
+    /// Null move pruning: a side that can pass and still reach beta prunes the node.
+    pub null_move: bool,
+    /// Principal variation search: after the first move, the others are only
+    /// searched with a null window, and searched again when they beat alpha.
+    pub pvs: bool,
+    /// Depth reduction used for null move pruning.
+    pub null_move_r: i32,
+    /// How many additional plies the quiescence search explores beyond the main horizon.
+    pub quiescence_depth: i32,
+    /// Quiet checking moves are also searched in this many first quiescence plies.
+    pub quiescence_check_plies: i32,
+    /// Razoring applies while the remaining depth is at most this.
+    pub razor_depth: i32,
+    /// Razoring margin per ply of remaining depth (1 pawn = 1000).
+    pub razor_margin: i32,
+    /// Internal iterative deepening only runs from this depth on: shallower nodes
+    /// are cheap enough that poor ordering does not matter.
+    pub iid_min_depth: i32,
+    /// Depth reduction of the internal iterative deepening search.
+    pub iid_reduction: i32,
+    /// Extends the TT move when it is singular: much better than every alternative.
+    /// Off by default: on the tactics suite it cost nodes without solving more.
+    pub singular_extensions: bool,
+    /// Singular extensions only run from this remaining depth on.
+    pub singular_min_depth: i32,
+    /// Per ply of remaining depth, how far below the TT score the alternatives
+    /// must stay for the TT move to be singular.
+    pub singular_margin: i32,
+    /// Most plies a single line can be extended by, so forced sequences
+    /// cannot extend the search forever.
+    pub max_extensions: i32,
+    /// Delta pruning margin: a quiescence capture is skipped when even winning the
+    /// captured piece plus this margin cannot bring the score up to alpha.
+    pub delta_margin: i32,
+    /// Threads searching the position: the main one and `threads - 1` lazy SMP
+    /// helpers sharing the transposition table. With a single thread the search
+    /// is reproducible.
+    pub threads: i32,
+    /// How much worse than an even position a draw is for the side that started
+    /// the search (1 pawn = 1000). Positive values make the engine avoid draws,
+    /// negative values make it seek them.
+    pub contempt: i32,
+    /// Time kept aside on every move for the communication with the GUI, in
+    /// milliseconds. The hard time limit of a move leaves it out of the clock.
+    pub move_overhead: i32,
+    /// Moves the clock is expected to last for when `go` gives no `movestogo`.
+    pub expected_moves: i32,
+    /// Largest random amount added to or taken from the score of each root move
+    /// (1 pawn = 1000) when choosing the move to play, so that repeated games
+    /// differ. The reported scores are left alone. 0 turns the noise off.
+    pub eval_noise: i32,
+    /// Seed of the root noise: the same seed picks the same moves again.
+    pub noise_seed: i32,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            null_move: true,
+            pvs: true,
+            null_move_r: 2,
+            quiescence_depth: 4,
+            quiescence_check_plies: 1,
+            razor_depth: 3,
+            razor_margin: 1500,
+            iid_min_depth: 5,
+            iid_reduction: 2,
+            delta_margin: 2000,
+            threads: rayon::current_num_threads() as i32,
+            max_extensions: 4,
+            singular_extensions: false,
+            singular_min_depth: 6,
+            singular_margin: 100,
+            contempt: 0,
+            move_overhead: 30,
+            expected_moves: 30,
+            eval_noise: 0,
+            noise_seed: 0,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Razoring margin at `depth`, or None if razoring does not apply there.
+    pub fn razor_margin_at(&self, depth: i32) -> Option<i32> {
+        (1..=self.razor_depth)
+            .contains(&depth)
+            .then(|| self.razor_margin * (depth + 1))
+    }
+
+    /// Sets the spin or check option `name` (case insensitive) from its UCI value.
+    /// Out-of-range values are clamped; returns the value actually applied
+    /// (1 or 0 for check options).
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<i32, anyhow::Error> {
+        if let Some(option) = CHECK_OPTIONS
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+        {
+            let value = match value.trim() {
+                v if v.eq_ignore_ascii_case("true") => true,
+                v if v.eq_ignore_ascii_case("false") => false,
+                _ => return Err(anyhow!("invalid value for {}: {}", option.name, value)),
+            };
+            (option.set)(self, value);
+            return Ok(value as i32);
+        }
+
+        let option = SPIN_OPTIONS
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("unknown option: {}", name))?;
+        let value: i32 = value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("invalid value for {}: {}", option.name, value))?;
+        let value = value.clamp(option.min, option.max);
+        (option.set)(self, value);
+
+        Ok(value)
+    }
+
+    /// The `option` lines announced on `uci`, with the current values as defaults.
+    pub fn uci_options(&self) -> Vec<String> {
+        let spins = SPIN_OPTIONS.iter().map(|o| {
+            format!(
+                "option name {} type spin default {} min {} max {}",
+                o.name,
+                (o.get)(self),
+                o.min,
+                o.max
+            )
+        });
+        let checks = CHECK_OPTIONS.iter().map(|o| {
+            format!(
+                "option name {} type check default {}",
+                o.name,
+                (o.get)(self)
+            )
+        });
+
+        spins.chain(checks).collect()
+    }
+}
+
+/// A UCI spin option mapped to a field of [`SearchConfig`].
+pub struct SpinOption {
+    pub name: &'static str,
+    pub min: i32,
+    pub max: i32,
+    pub get: fn(&SearchConfig) -> i32,
+    pub set: fn(&mut SearchConfig, i32),
+}
+
+/// A UCI check option mapped to a boolean field of [`SearchConfig`].
+pub struct CheckOption {
+    pub name: &'static str,
+    pub get: fn(&SearchConfig) -> bool,
+    pub set: fn(&mut SearchConfig, bool),
+}
+
+/// Every switch exposed as a UCI check option.
+pub const CHECK_OPTIONS: &[CheckOption] = &[
+    CheckOption {
+        name: "NullMove",
+        get: |config| config.null_move,
+        set: |config, value| config.null_move = value,
+    },
+    CheckOption {
+        name: "PVS",
+        get: |config| config.pvs,
+        set: |config, value| config.pvs = value,
+    },
+    CheckOption {
+        name: "SingularExtensions",
+        get: |config| config.singular_extensions,
+        set: |config, value| config.singular_extensions = value,
+    },
+];
+
+macro_rules! spin_options {
+    ($($name:literal => $field:ident, $min:expr, $max:expr;)*) => {
+        /// Every tunable exposed as a UCI spin option.
+        pub const SPIN_OPTIONS: &[SpinOption] = &[
+            $(SpinOption {
+                name: $name,
+                min: $min,
+                max: $max,
+                get: |config| config.$field,
+                set: |config, value| config.$field = value,
+            },)*
+        ];
+    };
+}
+
+spin_options! {
+    "NullMoveR" => null_move_r, 1, 4;
+    "QuiescenceDepth" => quiescence_depth, 0, 16;
+    "QuiescenceCheckPlies" => quiescence_check_plies, 0, 4;
+    "RazorDepth" => razor_depth, 0, 6;
+    "RazorMargin" => razor_margin, 0, 10_000;
+    "IIDMinDepth" => iid_min_depth, 2, 20;
+    "IIDReduction" => iid_reduction, 1, 4;
+    "DeltaMargin" => delta_margin, 0, 10_000;
+    "MaxExtensions" => max_extensions, 0, 16;
+    "SingularMinDepth" => singular_min_depth, 2, 20;
+    "SingularMargin" => singular_margin, 0, 2000;
+    "Contempt" => contempt, -5000, 5000;
+    "Threads" => threads, 1, 256;
+    "MoveOverhead" => move_overhead, 0, 5000;
+    "ExpectedMoves" => expected_moves, 1, 200;
+    "EvalNoise" => eval_noise, 0, 1000;
+    "NoiseSeed" => noise_seed, 0, 1_000_000;
+}
+
+/// Splits `setoption name <id> [value <x>]` into its name and value.
+/// The name may contain spaces; the value is empty when missing.
+pub fn parse_setoption(tokens: &[&str]) -> (String, String) {
+    let value_idx = tokens.iter().position(|&t| t == "value");
+    let name = tokens[2.min(tokens.len())..value_idx.unwrap_or(tokens.len())].join(" ");
+    let value = value_idx.map_or(String::new(), |vi| tokens[vi + 1..].join(" "));
+
+    (name, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setoption(config: &mut SearchConfig, line: &str) -> Result<i32, anyhow::Error> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (name, value) = parse_setoption(&tokens);
+        config.set_option(&name, &value)
+    }
+
+    #[test]
+    fn every_spin_option_sets_its_field_and_clamps() {
+        for option in SPIN_OPTIONS {
+            let mut config = SearchConfig::default();
+            let value = if (option.get)(&config) == option.min {
+                option.min + 1
+            } else {
+                option.min
+            };
+
+            let line = format!("setoption name {} value {}", option.name, value);
+            assert_eq!(setoption(&mut config, &line).unwrap(), value);
+            assert_eq!((option.get)(&config), value, "{}", option.name);
+            assert_ne!(config, SearchConfig::default(), "{}", option.name);
+
+            let line = format!("setoption name {} value {}", option.name, option.max + 1);
+            assert_eq!(setoption(&mut config, &line).unwrap(), option.max);
+            let line = format!("setoption name {} value {}", option.name, i32::MIN);
+            assert_eq!(setoption(&mut config, &line).unwrap(), option.min);
+        }
+    }
+
+    #[test]
+    fn invalid_values_and_unknown_names_are_ignored() {
+        let mut config = SearchConfig::default();
+        assert!(setoption(&mut config, "setoption name NullMoveR value three").is_err());
+        assert!(setoption(&mut config, "setoption name NullMoveR").is_err());
+        assert!(setoption(&mut config, "setoption name NoSuchOption value 10").is_err());
+        assert_eq!(config, SearchConfig::default());
+
+        assert!(setoption(&mut config, "setoption name nullmover value 3").is_ok());
+        assert_eq!(config.null_move_r, 3);
+    }
+
+    #[test]
+    fn check_options_take_true_or_false() {
+        let mut config = SearchConfig::default();
+        for option in CHECK_OPTIONS {
+            let flipped = !(option.get)(&config);
+            let line = format!("setoption name {} value {}", option.name, flipped);
+            assert_eq!(setoption(&mut config, &line).unwrap(), flipped as i32);
+            assert_eq!((option.get)(&config), flipped, "{}", option.name);
+
+            let line = format!("setoption name {} value 1", option.name);
+            assert!(setoption(&mut config, &line).is_err());
+            assert_eq!((option.get)(&config), flipped, "{}", option.name);
+        }
+        assert!(
+            config
+                .uci_options()
+                .contains(&"option name SingularExtensions type check default true".to_string())
+        );
+    }
+
+    #[test]
+    fn default_razor_margins_match_the_tuned_table() {
+        let config = SearchConfig::default();
+        let margins: Vec<_> = (0..5).map(|d| config.razor_margin_at(d)).collect();
+        assert_eq!(margins, [None, Some(3000), Some(4500), Some(6000), None]);
+    }
+}
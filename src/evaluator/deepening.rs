@@ -0,0 +1,821 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::moves::move_type::{Move, Scenario};
+
+use super::hash_walk::HashWalk;
+use super::limits::SearchLimits;
+use super::stats::SearchStats;
+use super::stop::StopHandle;
+use super::time_manager::TimeManager;
+use super::transposition::TranspositionTable;
+
+/// Outcome of a completed iteration.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub depth: i32,
+    /// Relative to the side to move.
+    pub score: i32,
+    /// Counters of the iteration.
+    pub stats: SearchStats,
+    /// Nodes searched by all the threads since the search started, earlier
+    /// iterations included.
+    pub nodes: u64,
+}
+
+/// Progress of a search, sent while it runs. Scores are relative to the side
+/// to move.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchMessage {
+    /// An iteration completed.
+    Iteration {
+        depth: i32,
+        seldepth: i32,
+        score: i32,
+        pv: Vec<Move>,
+        /// Nodes searched by all the threads since the search started.
+        nodes: u64,
+        /// Time since the search started.
+        time: Duration,
+    },
+    /// A root move beat the best one of the current iteration.
+    NewBest { mv: Move, score: i32, pv: Vec<Move> },
+    /// Always the last message: the search is over. `best` is None if there
+    /// is no legal move.
+    Finished {
+        best: Option<Move>,
+        /// Without a legal move, the score of the game over
+        /// ([`Scenario::game_over_score`]).
+        score: i32,
+        /// Counters of the whole search, helper threads included.
+        stats: SearchStats,
+    },
+}
+
+impl Scenario {
+    /// Iterative deepening within `limits`: searches depth 1, 2, ... and returns
+    /// the result of the last completed iteration, None if there is no legal move.
+    ///
+    /// The progress is sent on `tx`, which is dropped once
+    /// [`SearchMessage::Finished`] was sent, so a receiver sees the channel
+    /// close right after it.
+    ///
+    /// The deadline, the nodes limit and the
+    /// [`StopHandle`](super::stop::StopHandle) are checked during
+    /// the search and an unfinished iteration is dropped. The first iteration
+    /// always completes, so a move is always found. Under a clock, the
+    /// [`TimeManager`] is told about every iteration and decides when no more
+    /// should start and when the running one is dropped. With a time budget,
+    /// an only legal move is returned after the first iteration.
+    ///
+    /// A ponder search ([`SearchLimits::ponder`]) runs without a deadline until
+    /// the [`PonderHandle`](super::stop::PonderHandle) is hit, and then goes on
+    /// from the iteration it was at, with its time budget running from the hit.
+    ///
+    /// `previous` is a result known from an earlier search: the depths up to its
+    /// own are not searched again.
+    ///
+    /// With more than one of `config.threads`, the other threads search the same
+    /// position meanwhile (lazy SMP): each runs an iterative deepening of its own,
+    /// every other one a ply ahead, and only fills the shared `tt`. They are
+    /// stopped once this search is over.
+    pub fn iterative_deepening(
+        &mut self,
+        limits: &SearchLimits,
+        tt: &TranspositionTable,
+        previous: Option<SearchResult>,
+        tx: Option<Sender<SearchMessage>>,
+    ) -> Option<SearchResult> {
+        let start = Instant::now();
+        self.aborted.store(false, Ordering::Relaxed);
+        self.shared_nodes.store(0, Ordering::Relaxed);
+
+        // A finished game has nothing to search.
+        if let Some(score) = self.game_over_score() {
+            if let Some(tx) = tx {
+                let _ = tx.send(SearchMessage::Finished {
+                    best: None,
+                    score,
+                    stats: SearchStats::default(),
+                });
+            }
+            return None;
+        }
+
+        let helpers_stop = StopHandle::new();
+        let (best, mut total) = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.config.threads.max(1))
+                .map(|id| {
+                    let mut helper = self.helper(helpers_stop.clone());
+                    scope.spawn(move || helper.helper_deepening(id, limits.max_depth(), tt))
+                })
+                .collect();
+
+            let (best, mut total) = self.deepen(limits, tt, previous, start, tx.as_ref());
+            helpers_stop.stop();
+            for helper in helpers {
+                total.merge(&helper.join().expect("search thread panicked"));
+            }
+            (best, total)
+        });
+
+        total.elapsed = start.elapsed();
+        if let Some(tx) = tx {
+            let _ = tx.send(SearchMessage::Finished {
+                best: best.as_ref().map(|result| result.best_move),
+                score: best.as_ref().map_or(0, |result| result.score),
+                stats: total,
+            });
+        }
+
+        best
+    }
+
+    /// The iterations of [`Scenario::iterative_deepening`] on this thread, sending
+    /// their progress on `tx`. Returns the last result with the counters of all
+    /// the iterations.
+    fn deepen(
+        &mut self,
+        limits: &SearchLimits,
+        tt: &TranspositionTable,
+        previous: Option<SearchResult>,
+        start: Instant,
+        tx: Option<&Sender<SearchMessage>>,
+    ) -> (Option<SearchResult>, SearchStats) {
+        let mut time = TimeManager::new(limits, self.board.turn, &self.config);
+        // Nobody listening is not a reason to stop searching.
+        let send = |message| {
+            if let Some(tx) = tx {
+                let _ = tx.send(message);
+            }
+        };
+        let mut total = SearchStats::default();
+        let first_depth = previous.as_ref().map_or(1, |result| result.depth + 1);
+        let mut best = previous;
+        for depth in first_depth..=limits.max_depth() {
+            // The clock of a ponder search only starts at the ponderhit, which
+            // can come before the search started.
+            let clock_start = if limits.ponder {
+                self.ponder.hit_at().map(|hit| hit.max(start))
+            } else {
+                Some(start)
+            };
+            if best.is_some() {
+                if let (Some(time), Some(clock_start)) = (&time, clock_start)
+                    && time.should_stop(clock_start.elapsed())
+                {
+                    break;
+                }
+                let nodes = self.shared_nodes.load(Ordering::Relaxed);
+                if limits.nodes.is_some_and(|max| nodes >= max) || self.stop.is_stopped() {
+                    break;
+                }
+            }
+            let budget = time
+                .as_ref()
+                .filter(|_| best.is_some())
+                .map(TimeManager::iteration_limit);
+            self.deadline = budget
+                .zip(clock_start)
+                .map(|(budget, start)| start + budget);
+            self.ponder_budget = budget.filter(|_| self.deadline.is_none() && limits.ponder);
+            self.node_limit = limits.nodes.filter(|_| best.is_some());
+            self.stop_armed = best.is_some();
+
+            let iteration_start = Instant::now();
+            let (iteration_best, mut stats) = self.root_negamax(depth, tt, tx);
+            stats.elapsed = iteration_start.elapsed();
+            total.merge(&stats);
+            if self.is_aborted() {
+                break;
+            }
+            let Some((best_move, score)) = iteration_best else {
+                break;
+            };
+
+            let result = SearchResult {
+                best_move,
+                depth,
+                score,
+                stats,
+                nodes: self.shared_nodes.load(Ordering::Relaxed),
+            };
+            tracing::debug!(
+                depth,
+                nps = result.stats.nps(),
+                "iteration done: {}",
+                result.stats
+            );
+            send(SearchMessage::Iteration {
+                depth,
+                seldepth: result.stats.seldepth,
+                score,
+                pv: self.principal_variation(best_move, depth, tt),
+                nodes: result.nodes,
+                time: start.elapsed(),
+            });
+            best = Some(result);
+            if let Some(time) = &mut time {
+                time.update(best_move, score);
+            }
+            // Deeper iterations cannot change an only move: play it right away
+            // unless the search is meant to go on.
+            if time.is_some() && self.root_moves().len() == 1 {
+                break;
+            }
+        }
+        self.deadline = None;
+        self.ponder_budget = None;
+        self.node_limit = None;
+        self.stop_armed = false;
+
+        (best, total)
+    }
+
+    /// `best_move` followed by the line stored in `tt` after it. The stored move
+    /// of the root can come from a helper thread, a ply deeper.
+    fn principal_variation(
+        &self,
+        best_move: Move,
+        depth: i32,
+        tt: &TranspositionTable,
+    ) -> Vec<Move> {
+        let mut board = self.board.clone();
+        board.make_move(&best_move);
+        let plies = (depth - 1).max(0) as usize;
+        let mut pv = vec![best_move];
+        pv.extend(HashWalk::new(&board, tt, plies, 1).mainline());
+        pv
+    }
+
+    /// A lazy SMP helper: a copy of the scenario with counters and an abort flag
+    /// of its own, searching until `stop`. It shares the nodes count.
+    fn helper(&self, stop: StopHandle) -> Scenario {
+        Scenario {
+            stats: SearchStats::default(),
+            killers: Vec::new(),
+            aborted: Arc::new(AtomicBool::new(false)),
+            stop,
+            stop_armed: true,
+            deadline: None,
+            ponder_budget: None,
+            node_limit: None,
+            ..self.clone()
+        }
+    }
+
+    /// Iterative deepening of the helper `id`, up to `max_depth` or until it is
+    /// stopped: the odd helpers start a ply ahead. Its results only reach the
+    /// main search through `tt`. Returns the counters of all its iterations.
+    fn helper_deepening(
+        &mut self,
+        id: i32,
+        max_depth: i32,
+        tt: &TranspositionTable,
+    ) -> SearchStats {
+        let mut total = SearchStats::default();
+        for depth in 1 + id % 2..=max_depth {
+            let (best, stats) = self.root_negamax(depth, tt, None);
+            total.merge(&stats);
+            if self.is_aborted() || best.is_none() {
+                break;
+            }
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::components::board::Board;
+    use crate::evaluator::{config::SearchConfig, stats};
+    use crate::moves::magic;
+
+    /// Runs the search, returning its result and every message it sent.
+    fn search_with_messages(
+        mut scenario: Scenario,
+        limits: &SearchLimits,
+        tt: &TranspositionTable,
+    ) -> (Option<SearchResult>, Vec<SearchMessage>) {
+        let (tx, rx) = mpsc::channel();
+        let result = scenario.iterative_deepening(limits, tt, None, Some(tx));
+        (result, rx.into_iter().collect())
+    }
+
+    /// A scenario searched by one thread, for the tests measuring time: the
+    /// lazy SMP helpers of the tests running at the same time share the cores.
+    fn single_threaded(board: Board) -> Scenario {
+        let config = SearchConfig {
+            threads: 1,
+            ..SearchConfig::default()
+        };
+        Scenario::with_config(board, config)
+    }
+
+    /// The depth, nodes and score of each completed iteration.
+    fn iterations(messages: &[SearchMessage]) -> Vec<(i32, u64, i32)> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                SearchMessage::Iteration {
+                    depth,
+                    nodes,
+                    score,
+                    ..
+                } => Some((*depth, *nodes, *score)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_timed_search_stops_in_time_with_a_legal_move() {
+        magic::init();
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r2q1rk1/ppp2ppp/2np1n2/2b1p1B1/2B1P1b1/2NP1N2/PPP2PPP/R2Q1RK1 b - - 0 8",
+        ];
+        for fen in fens {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let limits = SearchLimits {
+                movetime: Some(Duration::from_millis(100)),
+                ..SearchLimits::default()
+            };
+            let tt = TranspositionTable::new();
+
+            let start = Instant::now();
+            let (result, messages) =
+                search_with_messages(single_threaded(board.clone()), &limits, &tt);
+            let elapsed = start.elapsed();
+            let result = result.expect("the position has legal moves");
+            let depths: Vec<_> = iterations(&messages).iter().map(|i| i.0).collect();
+
+            // Depth 1 always completes, then the search gives up at the deadline.
+            // Unoptimized builds are too slow, and too dependent on load, to time.
+            if cfg!(not(debug_assertions)) {
+                assert!(
+                    elapsed < Duration::from_millis(400),
+                    "{:?} {}",
+                    elapsed,
+                    fen
+                );
+            }
+            assert!(board.legal_moves().contains(&result.best_move), "{}", fen);
+            assert_eq!(depths.last(), Some(&result.depth));
+            assert!(depths.iter().copied().eq(1..=result.depth), "{:?}", depths);
+        }
+    }
+
+    #[test]
+    fn a_stopped_infinite_search_returns_promptly_with_a_legal_move() {
+        magic::init();
+        let board = Board::from_forsyth_edwards(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new();
+        let mut scenario = single_threaded(board.clone());
+        let stop = scenario.stop_handle();
+
+        let (result, stopped_after) = std::thread::scope(|scope| {
+            let search = scope.spawn(|| scenario.iterative_deepening(&limits, &tt, None, None));
+            std::thread::sleep(Duration::from_millis(50));
+            stop.stop();
+            let stopped_at = Instant::now();
+            let result = search.join().unwrap();
+            (result, stopped_at.elapsed())
+        });
+
+        assert!(
+            stopped_after < Duration::from_millis(200),
+            "{:?}",
+            stopped_after
+        );
+        let result = result.expect("the position has legal moves");
+        assert!(board.legal_moves().contains(&result.best_move));
+        assert!(result.depth < limits.max_depth());
+    }
+
+    #[test]
+    fn a_ponder_search_goes_on_from_its_iteration_after_the_ponderhit() {
+        magic::init();
+        let board = Board::from_forsyth_edwards(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            ponder: true,
+            movetime: Some(Duration::from_millis(100)),
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new();
+        let mut scenario = single_threaded(board.clone());
+        let ponder = scenario.ponder_handle();
+        let pondering = Duration::from_millis(300);
+
+        let (tx, rx) = mpsc::channel();
+        let (result, hit_after) = std::thread::scope(|scope| {
+            let search = scope.spawn(|| scenario.iterative_deepening(&limits, &tt, None, Some(tx)));
+            // Well past the movetime: only the ponderhit starts the clock.
+            std::thread::sleep(pondering);
+            assert!(!search.is_finished());
+            ponder.hit();
+            let hit_at = Instant::now();
+            let result = search.join().unwrap();
+            (result, hit_at.elapsed())
+        });
+        let messages: Vec<_> = rx.into_iter().collect();
+
+        assert!(hit_after < Duration::from_millis(300), "{:?}", hit_after);
+        let result = result.expect("the position has legal moves");
+        assert!(board.legal_moves().contains(&result.best_move));
+        // The iterations completed while pondering are not searched again.
+        let depths: Vec<_> = iterations(&messages).iter().map(|i| i.0).collect();
+        assert!(depths.iter().copied().eq(1..=result.depth), "{:?}", depths);
+        let before_hit = messages
+            .iter()
+            .filter(|m| matches!(m, SearchMessage::Iteration { time, .. } if *time < pondering))
+            .count();
+        assert!(before_hit > 0 && before_hit <= depths.len());
+    }
+
+    #[test]
+    fn lazy_smp_helpers_search_until_the_main_search_is_over() {
+        magic::init();
+        let config = SearchConfig {
+            threads: 4,
+            ..SearchConfig::default()
+        };
+
+        // Mate in 2: 1. Kg6 Kg8 2. Ra8#
+        let board = Board::from_forsyth_edwards("7k/8/5K2/8/8/8/8/R7 w - - 0 1").unwrap();
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new();
+        let result = Scenario::with_config(board.clone(), config)
+            .iterative_deepening(&limits, &tt, None, None)
+            .expect("the position has legal moves");
+        assert_eq!(result.best_move.to_uci(), "f6g6");
+        assert_eq!(result.score, crate::evaluator::evaluation::MATE_SCORE - 3);
+
+        // Infinite helpers only end with the main search.
+        let board = Board::from_forsyth_edwards(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new();
+        let scenario = Scenario::with_config(board.clone(), config);
+        let stop = scenario.stop_handle();
+        let (result, messages) = std::thread::scope(|scope| {
+            let search = scope.spawn(|| search_with_messages(scenario, &limits, &tt));
+            std::thread::sleep(Duration::from_millis(100));
+            stop.stop();
+            search.join().unwrap()
+        });
+        let result = result.expect("the position has legal moves");
+        assert!(board.legal_moves().contains(&result.best_move));
+        let Some(SearchMessage::Finished { stats, .. }) = messages.last() else {
+            panic!("the search did not finish: {:?}", messages.last());
+        };
+        assert!(stats.nodes >= result.nodes);
+    }
+
+    #[test]
+    fn single_and_multi_threaded_searches_agree_on_the_best_move() {
+        magic::init();
+        let positions = [
+            // Scholar's mate: Qxf7#.
+            (
+                "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+                "h5f7",
+            ),
+            // The knight forks king and queen: Nc7+.
+            ("r3k3/8/8/1N1q4/8/8/8/4K3 w - - 0 1", "b5c7"),
+        ];
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..SearchLimits::default()
+        };
+        for (fen, best) in positions {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            for threads in [1, 4] {
+                let config = SearchConfig {
+                    threads,
+                    ..SearchConfig::default()
+                };
+                let tt = TranspositionTable::new();
+                let result = Scenario::with_config(board.clone(), config)
+                    .iterative_deepening(&limits, &tt, None, None)
+                    .expect("the position has legal moves");
+                assert_eq!(
+                    result.best_move.to_uci(),
+                    best,
+                    "{} {} threads",
+                    fen,
+                    threads
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_finished_game_reports_its_result_without_searching() {
+        magic::init();
+        let limits = SearchLimits::default();
+        let tt = TranspositionTable::new();
+        for (fen, expected) in [
+            // Fool's mate.
+            (
+                "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+                -crate::evaluator::evaluation::MATE_SCORE,
+            ),
+            // Stalemate.
+            ("k7/8/1Q6/8/8/8/8/7K b - - 0 1", 0),
+        ] {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let scenario = Scenario::new(board);
+            assert_eq!(scenario.game_over_score(), Some(expected), "{}", fen);
+
+            let (result, messages) = search_with_messages(scenario, &limits, &tt);
+            assert!(result.is_none());
+            assert_eq!(
+                messages,
+                [SearchMessage::Finished {
+                    best: None,
+                    score: expected,
+                    stats: SearchStats::default(),
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn an_only_move_is_returned_after_the_first_iteration() {
+        magic::init();
+        // The king in check has a single escape.
+        let board = Board::from_forsyth_edwards("k7/8/1K6/8/8/8/8/R7 b - - 0 1").unwrap();
+        let only_move = board.parse_uci_move("a8b8").unwrap();
+        let tt = TranspositionTable::new();
+        let scenario = single_threaded(board.clone());
+        assert_eq!(scenario.game_over_score(), None);
+        assert_eq!(scenario.root_moves(), [only_move]);
+
+        let timed = SearchLimits {
+            movetime: Some(Duration::from_secs(60)),
+            ..SearchLimits::default()
+        };
+        let result = search_with_messages(scenario, &timed, &tt).0.unwrap();
+        assert_eq!((result.best_move, result.depth), (only_move, 1));
+
+        // Without a clock the search goes on as asked.
+        let deep = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let result = search_with_messages(single_threaded(board), &deep, &tt)
+            .0
+            .unwrap();
+        assert_eq!((result.best_move, result.depth), (only_move, 3));
+    }
+
+    #[test]
+    fn searchmoves_restrict_the_root_moves() {
+        magic::init();
+        let board = Board::new_game();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new();
+        let scenario = Scenario::new(board.clone())
+            .with_uci_searchmoves(&["e2e4", "d2d4", "e7e5", "e2e5"])
+            .unwrap();
+        let allowed: Vec<_> = ["e2e4", "d2d4"]
+            .map(|uci| board.parse_uci_move(uci).unwrap())
+            .into();
+        assert_eq!(scenario.searchmoves(), Some(&allowed[..]));
+
+        let (result, messages) = search_with_messages(scenario, &limits, &tt);
+        assert!(allowed.contains(&result.unwrap().best_move));
+        let mut reported = 0;
+        for message in &messages {
+            let first = match message {
+                SearchMessage::Iteration { pv, .. } => pv[0],
+                SearchMessage::NewBest { mv, .. } => *mv,
+                SearchMessage::Finished { best, .. } => best.unwrap(),
+            };
+            assert!(allowed.contains(&first), "{:?}", message);
+            reported += 1;
+        }
+        assert!(reported > 3);
+
+        // Move values work the same, and a list without a legal move is refused.
+        let knight = board.parse_uci_move("g1f3").unwrap();
+        let scenario = Scenario::new(board.clone())
+            .with_searchmoves(&[knight])
+            .unwrap();
+        let result = search_with_messages(scenario, &limits, &tt).0.unwrap();
+        assert_eq!(result.best_move, knight);
+        assert!(
+            Scenario::new(board.clone())
+                .with_uci_searchmoves(&["e2e5", "x"])
+                .is_err()
+        );
+        assert!(Scenario::new(board).with_searchmoves(&[]).is_err());
+    }
+
+    #[test]
+    fn a_fixed_depth_search_runs_with_the_default_config() {
+        magic::init();
+        let board = Board::from_forsyth_edwards(
+            "r1bqk2r/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PPP2PPP/R1BQK2R w KQkq - 0 8",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..SearchLimits::default()
+        };
+        let search = |config: SearchConfig| {
+            let tt = TranspositionTable::new();
+            let result = Scenario::with_config(board.clone(), config)
+                .iterative_deepening(&limits, &tt, None, None)
+                .expect("the position has legal moves");
+            let nodes = result.nodes;
+            (result, nodes)
+        };
+
+        let (result, nodes) = search(SearchConfig::default());
+        assert_eq!(result.depth, 4);
+        assert!(board.legal_moves().contains(&result.best_move));
+
+        let (result, nodes_without_null_move) = search(SearchConfig {
+            null_move: false,
+            ..SearchConfig::default()
+        });
+        assert_eq!(result.depth, 4);
+        assert!(nodes < nodes_without_null_move);
+    }
+
+    #[test]
+    fn a_node_limited_single_thread_search_is_reproducible() {
+        magic::init();
+        let board = Board::from_forsyth_edwards(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            nodes: Some(30_000),
+            ..SearchLimits::default()
+        };
+        let config = SearchConfig {
+            threads: 1,
+            ..SearchConfig::default()
+        };
+        let search = || {
+            let tt = TranspositionTable::new();
+            let (result, messages) =
+                search_with_messages(Scenario::with_config(board.clone(), config), &limits, &tt);
+            let reports: Vec<_> = iterations(&messages).iter().map(|i| (i.0, i.1)).collect();
+            (
+                result.expect("the position has legal moves").best_move,
+                reports,
+            )
+        };
+
+        let (best_move, reports) = search();
+        assert_eq!(search(), (best_move, reports.clone()));
+        // The nodes add up over the iterations and the limit stops the search.
+        assert!(reports.is_sorted_by(|a, b| a.1 < b.1), "{:?}", reports);
+        let (depth, nodes) = *reports.last().unwrap();
+        assert!(depth < limits.max_depth());
+        assert!(nodes < 2 * 30_000, "{}", nodes);
+    }
+
+    #[test]
+    fn stats_count_the_nodes_and_the_selective_depth() {
+        magic::init();
+        // Tactics everywhere: the quiescence search runs well past the horizon.
+        let board = Board::from_forsyth_edwards(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new();
+        // One thread: helpers would go on searching after the last iteration.
+        let (result, messages) = search_with_messages(single_threaded(board), &limits, &tt);
+        let result = result.unwrap();
+
+        let stats = &result.stats;
+        assert!(stats.nodes > 0 && stats.qnodes > 0 && stats.qnodes < stats.nodes);
+        assert!(stats.seldepth > result.depth, "{}", stats.seldepth);
+        assert!(stats.elapsed > Duration::ZERO && stats.nps() > 0);
+        let Some(SearchMessage::Finished { stats: total, .. }) = messages.last() else {
+            panic!("the search did not finish: {:?}", messages.last());
+        };
+        assert_eq!(result.nodes, total.nodes);
+        assert!(total.nodes > stats.nodes && total.seldepth >= stats.seldepth);
+        if stats::DETAILED {
+            assert!(stats.tt_probes > 0 && stats.tt_hits <= stats.tt_probes);
+            assert!(stats.first_move_cutoffs > 0);
+            assert!(stats.first_move_cutoffs <= stats.beta_cutoffs);
+        }
+    }
+
+    #[test]
+    fn depth_and_no_legal_moves_end_the_search() {
+        magic::init();
+        let board = Board::default();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new();
+        let result = Scenario::new(board).iterative_deepening(&limits, &tt, None, None);
+        assert_eq!(result.map(|r| r.depth), Some(3));
+
+        // Fool's mate.
+        let mated = Board::from_forsyth_edwards(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        )
+        .unwrap();
+        let result = Scenario::new(mated).iterative_deepening(&limits, &tt, None, None);
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn finished_is_always_the_last_message() {
+        magic::init();
+        let fens = [
+            // Mate in 2, checkmated, stalemated.
+            "7k/8/5K2/8/8/8/8/R7 w - - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+        ];
+        let limits = [
+            SearchLimits {
+                depth: Some(4),
+                ..SearchLimits::default()
+            },
+            SearchLimits {
+                nodes: Some(2_000),
+                ..SearchLimits::default()
+            },
+            SearchLimits {
+                movetime: Some(Duration::from_millis(50)),
+                ..SearchLimits::default()
+            },
+        ];
+        for fen in fens {
+            for limits in &limits {
+                let board = Board::from_forsyth_edwards(fen).unwrap();
+                let tt = TranspositionTable::new();
+                let scenario = Scenario::new(board);
+                let game_over = scenario.game_over_score();
+                let (result, messages) = search_with_messages(scenario, limits, &tt);
+
+                let finished = messages
+                    .iter()
+                    .filter(|m| matches!(m, SearchMessage::Finished { .. }))
+                    .count();
+                assert_eq!(finished, 1, "{} {:?}", fen, limits);
+                let Some(SearchMessage::Finished { best, score, .. }) = messages.last() else {
+                    panic!("{} {:?}: {:?}", fen, limits, messages.last());
+                };
+                assert_eq!(*best, result.as_ref().map(|r| r.best_move));
+                assert_eq!(Some(*score), result.as_ref().map(|r| r.score).or(game_over));
+                // The last iteration and the last new best agree with the result.
+                if let Some(result) = result {
+                    let last = iterations(&messages).last().copied();
+                    assert_eq!(last.map(|i| (i.0, i.2)), Some((result.depth, result.score)));
+                    let new_best = messages.iter().rev().find_map(|m| match m {
+                        SearchMessage::NewBest { mv, pv, .. } => Some((*mv, pv[0])),
+                        _ => None,
+                    });
+                    assert!(new_best.is_some_and(|(mv, first)| mv == first));
+                }
+            }
+        }
+    }
+}
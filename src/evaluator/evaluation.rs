@@ -1,263 +1,654 @@
-use std::cmp;
-use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::Sender;
+use std::time::Instant;
 
-use rayon::{iter::ParallelIterator, prelude::*};
-
-use crate::components::pieces::Color;
-use crate::moves::generate::RatedMove;
+use crate::components::board::Board;
+use crate::components::pieces::PieceKind;
 use crate::moves::move_type::{Move, Scenario};
+use crate::moves::packed::PackedMove;
+use crate::moves::picker::MovePicker;
 
+use super::deepening::SearchMessage;
+use super::hash_walk::HashWalk;
 use super::static_eval::StaticEval;
-use super::transposition::{Bound, TranspositionTable};
+use super::stats::{self, SearchStats};
+use super::transposition::{Bound, TERMINAL_DEPTH, TranspositionTable};
+
+/// Bound of the search window. Symmetric so that scores can always be negated.
+pub const INFINITY: i32 = i32::MAX;
+
+/// Score of a side delivering mate on the current move. A mate `n` plies away
+/// scores `MATE_SCORE - n`, so faster mates are preferred.
+pub const MATE_SCORE: i32 = INFINITY - 1;
+
+/// Scores at or beyond this magnitude are treated as mate scores.
+const MATE_BOUND: i32 = i32::MAX / 2;
+
+fn is_mate_bound(score: i32) -> bool {
+    score >= MATE_BOUND || score <= -MATE_BOUND
+}
+
+/// Plies to mate for a mate score (positive whoever is mating), None for other scores.
+pub fn mate_distance(score: i32) -> Option<i32> {
+    is_mate_bound(score).then(|| MATE_SCORE - score.saturating_abs())
+}
 
-/// Depth reduction used for null move pruning.
-const NULL_MOVE_R: i32 = 2;
+/// A score in UCI terms: `mate N` for a mate in N moves (negative when the side
+/// to move gets mated), `cp N` in centipawns otherwise.
+pub fn uci_score(score: i32) -> String {
+    match mate_distance(score) {
+        Some(plies) => {
+            let moves = (plies + 1) / 2;
+            format!("mate {}", if score > 0 { moves } else { -moves })
+        }
+        // The engine counts 1000 per pawn.
+        None => format!("cp {}", score / 10),
+    }
+}
+
+/// Score of the side to move being checkmated `ply` plies from the root.
+fn mated_score(ply: i32) -> i32 {
+    -MATE_SCORE + ply
+}
 
-/// How many additional plies the quiescence search explores beyond the main horizon.
-const QUIESCENCE_DEPTH: i32 = 4;
+/// Mate scores are stored in the TT relative to the node instead of the root,
+/// so an entry stays correct when the position is reached at another ply.
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score.saturating_add(ply)
+    } else if score <= -MATE_BOUND {
+        score.saturating_sub(ply)
+    } else {
+        score
+    }
+}
+
+pub(crate) fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score.saturating_sub(ply)
+    } else if score <= -MATE_BOUND {
+        score.saturating_add(ply)
+    } else {
+        score
+    }
+}
+
+/// Root move selection: returns true if `candidate` should replace `best`.
+/// Scores are relative to the side to move at the root.
+///
+/// The higher score wins; mate scores already prefer the shorter mate.
+/// Among equal scores a move that resets the fifty-move clock is preferred,
+/// so long winning conversions do not drift into a fifty-move draw.
+pub fn is_better_root_result(board: &Board, candidate: (&Move, i32), best: (&Move, i32)) -> bool {
+    let (candidate_move, candidate_eval) = candidate;
+    let (best_move, best_eval) = best;
+    if candidate_eval != best_eval {
+        return candidate_eval > best_eval;
+    }
+
+    board.reset_50_moves(candidate_move) && !board.reset_50_moves(best_move)
+}
+
+/// Nodes searched by a thread between two checks of the time and nodes limits.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// A node whose window is wider than a null window can still change the principal variation.
+fn is_pv_window(alpha: i32, beta: i32) -> bool {
+    beta as i64 - alpha as i64 > 1
+}
 
 impl Scenario {
-    pub fn minimax_alpha_beta(
+    /// Distance in plies from the position the search started from.
+    fn ply(&self) -> i32 {
+        (self.board.moves_count - self.root_moves_count) as i32
+    }
+
+    fn killers_at(&self, ply: i32) -> [Option<PackedMove>; 2] {
+        self.killers.get(ply as usize).copied().unwrap_or_default()
+    }
+
+    /// True once the search must stop: the deadline passed, the node limit was
+    /// reached or the search was stopped, here or in another thread. The limits
+    /// are only checked, and the nodes added to the shared count, every
+    /// `CHECK_INTERVAL` nodes. A ponder search gets its deadline at the ponderhit.
+    fn out_of_time(&mut self) -> bool {
+        if self.stats.nodes.is_multiple_of(CHECK_INTERVAL) {
+            if self.deadline.is_none()
+                && let Some(budget) = self.ponder_budget
+                && let Some(hit) = self.ponder.hit_at()
+            {
+                self.deadline = Some(hit + budget);
+            }
+            let searched = self
+                .shared_nodes
+                .fetch_add(CHECK_INTERVAL, Ordering::Relaxed)
+                + CHECK_INTERVAL;
+            if self.node_limit.is_some_and(|limit| searched >= limit)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                || (self.stop_armed && self.stop.is_stopped())
+            {
+                self.aborted.store(true, Ordering::Relaxed);
+            }
+        }
+        self.is_aborted()
+    }
+
+    /// True if the search ran out of time or nodes or was stopped: the scores it
+    /// returns are meaningless.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Relaxed)
+    }
+
+    /// True if the position is drawn by the fifty-move rule, or repeats one
+    /// played since the last capture or pawn move.
+    fn is_draw(&self) -> bool {
+        if self.board.reps_50 >= 100 {
+            // Unless the hundredth ply delivered mate.
+            return !self.board.position.is_in_check(self.board.turn)
+                || !self.board.generate_moves(false).is_empty();
+        }
+        // Only positions with the same side to move can repeat this one.
+        self.history
+            .iter()
+            .rev()
+            .take(self.board.reps_50 as usize)
+            .skip(1)
+            .step_by(2)
+            .any(|&hash| hash == self.board.hash)
+    }
+
+    /// Score of a draw for the side to move at `ply`: `contempt` below zero for
+    /// the side that started the search, above zero for its opponent.
+    fn draw_score(&self, ply: i32) -> i32 {
+        if ply % 2 == 0 {
+            -self.config.contempt
+        } else {
+            self.config.contempt
+        }
+    }
+
+    /// Remembers a quiet move that caused a beta cutoff at `ply`.
+    fn store_killer(&mut self, ply: i32, killer: PackedMove) {
+        let ply = ply as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+        let slots = &mut self.killers[ply];
+        if slots[0] != Some(killer) {
+            slots[1] = slots[0];
+            slots[0] = Some(killer);
+        }
+    }
+
+    /// Score of a position without legal moves: mated or stalemate. Terminal
+    /// nodes are exact at any depth, so they are stored as such.
+    fn terminal_score(&self, ply: i32, tt: &TranspositionTable) -> i32 {
+        let score = if self.board.position.is_in_check(self.board.turn) {
+            mated_score(ply)
+        } else {
+            self.draw_score(ply)
+        };
+        tt.store(
+            self.board.hash,
+            TERMINAL_DEPTH,
+            score_to_tt(score, ply),
+            Bound::Exact,
+            None,
+        );
+
+        score
+    }
+
+    /// Negamax alpha-beta search. Scores are relative to the side to move:
+    /// each recursion negates the child's score and swaps the window.
+    /// The window must stay within `-INFINITY..=INFINITY`.
+    pub fn negamax(
+        &mut self,
+        depth: i32,
+        alpha: i32,
+        beta: i32,
+        tt: &TranspositionTable,
+        allow_null_move: bool,
+    ) -> i32 {
+        self.search(depth, alpha, beta, tt, allow_null_move, None)
+    }
+
+    /// [`Scenario::negamax`] with a move left out, used by the singular extension
+    /// to search the alternatives of the TT move. A search with an excluded move
+    /// does not compute the value of the node, so it neither uses nor stores the
+    /// node's TT entry.
+    fn search(
         &mut self,
         depth: i32,
         mut alpha: i32,
         mut beta: i32,
         tt: &TranspositionTable,
         allow_null_move: bool,
+        excluded: Option<PackedMove>,
     ) -> i32 {
+        self.stats.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+        let original_alpha = alpha;
+        let ply = self.ply();
+        self.stats.seldepth = self.stats.seldepth.max(ply);
+        if ply > 0 && self.is_draw() {
+            return self.draw_score(ply);
+        }
+
         // Probe the transposition table. An exact hit lets us return immediately;
         // a bound hit narrows the alpha-beta window and may still cause a cutoff.
-        if let Some(result) = tt.probe(self.board.hash, depth) {
+        if excluded.is_none() {
+            stats::count(&mut self.stats.tt_probes);
+        }
+        if excluded.is_none()
+            && let Some(result) = tt.probe(self.board.hash, depth)
+        {
+            stats::count(&mut self.stats.tt_hits);
+            let score = score_from_tt(result.score, ply);
             match result.bound {
-                Bound::Exact => return result.score,
-                Bound::Lower => alpha = alpha.max(result.score),
-                Bound::Upper => beta = beta.min(result.score),
+                Bound::Exact => return score,
+                Bound::Lower => alpha = alpha.max(score),
+                Bound::Upper => beta = beta.min(score),
             }
             if alpha >= beta {
-                return result.score;
+                return score;
             }
         }
 
-        let mut available_moves = self.board.generate_moves(false);
-
-        if available_moves.is_empty() {
-            let score = if self.board.position.is_in_check(Color::White) {
-                i32::MIN
-            } else if self.board.position.is_in_check(Color::Black) {
-                i32::MAX
-            } else {
-                0
-            };
-            // Terminal nodes are exact at any depth.
-            tt.store(self.board.hash, i32::MAX, score, Bound::Exact);
-            return score;
+        if depth <= 0 {
+            // The quiescence search does not see mate and stalemate.
+            if self.board.generate_moves(false).is_empty() {
+                return self.terminal_score(ply, tt);
+            }
+            return self.quiescence_search(alpha, beta, self.config.quiescence_depth);
         }
 
-        if depth <= 0 {
-            return self.quiescence_search(alpha, beta, QUIESCENCE_DEPTH);
+        // Moves come out one at a time: quiet moves are only generated if no
+        // earlier move cuts the node off.
+        let killers = self.killers_at(ply);
+        let mut picker = MovePicker::new(tt.probe_move(self.board.hash), killers);
+        let Some(mut first_move) = picker.next(&self.board) else {
+            return self.terminal_score(ply, tt);
+        };
+
+        let in_check = self.board.position.is_in_check(self.board.turn);
+
+        // Razoring: when the static eval is hopelessly below alpha near the
+        // horizon, drop straight into quiescence. Only a fail-low is trusted:
+        // if quiescence still cannot reach alpha the node is pruned.
+        if let Some(margin) = self.config.razor_margin_at(depth)
+            && !in_check
+            && !is_mate_bound(alpha)
+            && !is_mate_bound(beta)
+            && StaticEval::relative_evaluate(&self.board) + margin <= alpha
+        {
+            let eval = self.quiescence_search(alpha, beta, self.config.quiescence_depth);
+            if eval <= alpha {
+                return eval;
+            }
         }
 
         // Null move pruning: temporarily pass the turn. If the resulting position
-        // (one free move for the opponent) still exceeds beta, the branch can be pruned.
+        // (one free move for the opponent) still reaches beta, the branch can be pruned.
         // Disabled when in check or in pawn-only positions (zugzwang risk).
-        if allow_null_move && depth > NULL_MOVE_R {
-            let in_check = self.board.position.is_in_check(self.board.turn);
-
-            if !in_check && self.board.has_non_pawn_pieces() {
-                let null_undo = self.board.make_null_move_mut();
-                let null_eval = self.minimax_alpha_beta(
-                    depth - 1 - NULL_MOVE_R,
-                    alpha,
-                    beta,
-                    tt,
-                    false, // no consecutive null moves
+        let null_move_r = self.config.null_move_r;
+        if allow_null_move
+            && self.config.null_move
+            && depth > null_move_r
+            && !in_check
+            && self.board.has_non_pawn_pieces()
+        {
+            self.history.push(self.board.hash);
+            let null_undo = self.board.make_null_move_mut();
+            let null_eval = -self.negamax(
+                depth - 1 - null_move_r,
+                -beta,
+                -alpha,
+                tt,
+                false, // no consecutive null moves
+            );
+            self.board.unmake_null_move(null_undo);
+            self.history.pop();
+            if self.is_aborted() {
+                return 0;
+            }
+
+            if null_eval >= beta {
+                tt.store(
+                    self.board.hash,
+                    depth,
+                    score_to_tt(beta, ply),
+                    Bound::Lower,
+                    None,
                 );
-                self.board.unmake_null_move(null_undo);
-
-                match self.board.turn {
-                    Color::White => {
-                        if null_eval >= beta {
-                            tt.store(self.board.hash, depth, beta, Bound::Lower);
-                            return beta;
-                        }
-                    }
-                    Color::Black => {
-                        if null_eval <= alpha {
-                            tt.store(self.board.hash, depth, alpha, Bound::Upper);
-                            return alpha;
-                        }
-                    }
-                }
+                return beta;
             }
         }
 
-        match self.board.turn {
-            Color::White => {
-                let mut max_eval = i32::MIN;
-                let mut broke_early = false;
+        // Internal iterative deepening: a PV node without a stored move would be
+        // searched with poor ordering. A reduced-depth search of the same node
+        // fills the TT with a best move to try first.
+        let mut tt_move = tt.probe_move(self.board.hash);
+        if tt_move.is_none()
+            && excluded.is_none()
+            && depth >= self.config.iid_min_depth
+            && is_pv_window(alpha, beta)
+        {
+            stats::count(&mut self.stats.iid_searches);
+            let iid_depth = depth - self.config.iid_reduction;
+            self.negamax(iid_depth, alpha, beta, tt, false);
+            if self.is_aborted() {
+                return 0;
+            }
+            tt_move = tt.probe_move(self.board.hash);
+            if tt_move.is_some() {
+                picker = MovePicker::new(tt_move, killers);
+                first_move = picker.next(&self.board).expect("the node has a legal move");
+            }
+        }
+        // A TT move that is not legal here belongs to another position.
+        let tt_move = tt_move.filter(|m| m.matches(&first_move));
 
-                for i in 0..available_moves.len() {
-                    let player_move = available_moves.get(i);
-                    let undo = self.board.make_move(&player_move);
-                    let inner_eval = self.minimax_alpha_beta(depth - 1, alpha, beta, tt, true);
-                    self.board.unmake_move(&player_move, undo);
+        // Single reply extension: a forced move costs nothing to search one ply deeper.
+        // Singular extension: neither does a TT move much better than all the others.
+        // Extensions along a line are limited by `max_extensions`.
+        // Forced moves are only looked for in check, where the evasions are few.
+        let single_reply = in_check && self.board.generate_moves(false).len() == 1;
+        let singular_move = tt_move.filter(|&tt_move| {
+            excluded.is_none()
+                && !single_reply
+                && self.extensions < self.config.max_extensions
+                && self.is_singular(depth, ply, tt, tt_move)
+        });
 
-                    if inner_eval > max_eval {
-                        max_eval = inner_eval;
-                    }
-                    alpha = cmp::max(alpha, inner_eval);
-                    if alpha >= beta {
-                        broke_early = true;
-                        break;
-                    }
-                }
+        let mut best_move: Option<Move> = None;
+        let mut best_eval = -INFINITY;
+        let mut broke_early = false;
 
-                // Beta cutoff → lower bound (real score may be even higher).
-                // All moves explored → exact value.
-                let bound = if broke_early {
-                    Bound::Lower
-                } else {
-                    Bound::Exact
-                };
-                tt.store(self.board.hash, depth, max_eval, bound);
-                max_eval
+        let mut next_move = Some(first_move);
+        while let Some(player_move) = next_move.take().or_else(|| picker.next(&self.board)) {
+            if excluded.is_some_and(|e| e.matches(&player_move)) {
+                continue;
             }
-            Color::Black => {
-                let mut min_eval = i32::MAX;
-                let mut broke_early = false;
-
-                for i in 0..available_moves.len() {
-                    let player_move = available_moves.get(i);
-                    let undo = self.board.make_move(&player_move);
-                    let inner_eval = self.minimax_alpha_beta(depth - 1, alpha, beta, tt, true);
-                    self.board.unmake_move(&player_move, undo);
-
-                    if inner_eval < min_eval {
-                        min_eval = inner_eval;
-                    }
+            let first = best_move.is_none();
 
-                    beta = cmp::min(beta, inner_eval);
-                    if alpha >= beta {
-                        broke_early = true;
-                        break;
+            let singular = singular_move.is_some_and(|s| s.matches(&player_move));
+            let extension =
+                if (single_reply || singular) && self.extensions < self.config.max_extensions {
+                    if single_reply {
+                        stats::count(&mut self.stats.single_reply_extensions);
+                    } else {
+                        stats::count(&mut self.stats.singular_extensions);
                     }
-                }
-
-                // Alpha cutoff → upper bound (real score may be even lower).
-                // All moves explored → exact value.
-                let bound = if broke_early {
-                    Bound::Upper
+                    1
                 } else {
-                    Bound::Exact
+                    0
                 };
-                tt.store(self.board.hash, depth, min_eval, bound);
-                min_eval
+            let child_depth = depth - 1 + extension;
+
+            self.history.push(self.board.hash);
+            let undo = self.board.make_move(&player_move);
+            tt.prefetch(self.board.hash);
+            self.extensions += extension;
+            let inner_eval = if best_move.is_none() || !self.config.pvs {
+                -self.negamax(child_depth, -beta, -alpha, tt, true)
+            } else {
+                // Prove the move is not better than alpha with a null window.
+                let eval = -self.negamax(child_depth, -alpha - 1, -alpha, tt, true);
+                if eval > alpha && eval < beta {
+                    stats::count(&mut self.stats.pvs_researches);
+                    -self.negamax(child_depth, -beta, -alpha, tt, true)
+                } else {
+                    eval
+                }
+            };
+            self.extensions -= extension;
+            self.board.unmake_move(&player_move, undo);
+            self.history.pop();
+            if self.is_aborted() {
+                return 0;
             }
+
+            if inner_eval > best_eval || first {
+                best_eval = inner_eval;
+                best_move = Some(player_move);
+            }
+            alpha = alpha.max(inner_eval);
+            if alpha >= beta {
+                stats::count(&mut self.stats.beta_cutoffs);
+                if first {
+                    stats::count(&mut self.stats.first_move_cutoffs);
+                }
+                if !player_move.is_capture() && !player_move.is_promotion() {
+                    self.store_killer(ply, PackedMove::new(&player_move));
+                }
+                broke_early = true;
+                break;
+            }
+        }
+
+        if excluded.is_some() {
+            return best_eval;
         }
+
+        // Beta cutoff → lower bound (real score may be even higher).
+        // No move above alpha → upper bound (real score may be even lower).
+        // Otherwise → exact value.
+        let bound = if broke_early {
+            Bound::Lower
+        } else if best_eval <= original_alpha {
+            Bound::Upper
+        } else {
+            Bound::Exact
+        };
+        tt.store(
+            self.board.hash,
+            depth,
+            score_to_tt(best_eval, ply),
+            bound,
+            best_move.as_ref().map(PackedMove::new),
+        );
+        best_eval
     }
 
-    pub fn parallel_minimax_alpha_beta(&self, depth: i32, tx: Sender<(Move, i32)>) {
+    /// Singular extension test: the TT move is singular when a reduced search of
+    /// all the other moves, with a null window `singular_margin` per ply below the
+    /// stored score, fails low. Only at deep nodes below the root, and only with a
+    /// stored score that is not an upper bound and was searched deep enough.
+    fn is_singular(
+        &mut self,
+        depth: i32,
+        ply: i32,
+        tt: &TranspositionTable,
+        tt_move: PackedMove,
+    ) -> bool {
+        if !self.config.singular_extensions || depth < self.config.singular_min_depth || ply == 0 {
+            return false;
+        }
+        let Some(entry) = tt.entry(self.board.hash) else {
+            return false;
+        };
+        if entry.bound == Bound::Upper || entry.depth < depth - 3 || is_mate_bound(entry.score) {
+            return false;
+        }
+
+        stats::count(&mut self.stats.singular_searches);
+        let singular_beta = score_from_tt(entry.score, ply) - self.config.singular_margin * depth;
+        let eval = self.search(
+            depth / 2,
+            singular_beta - 1,
+            singular_beta,
+            tt,
+            false,
+            Some(tt_move),
+        );
+
+        eval < singular_beta
+    }
+
+    /// The legal moves of the root, restricted to the searchmoves if any.
+    pub fn root_moves(&self) -> Vec<Move> {
         let available_moves = self.board.generate_moves(false);
+        available_moves
+            .iter(&self.board)
+            .filter(|m| {
+                self.searchmoves
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(m))
+            })
+            .collect()
+    }
 
-        let best_eval = AtomicI32::new(match self.board.turn {
-            Color::White => i32::MIN,
-            Color::Black => i32::MAX,
-        });
-        let main_alpha = AtomicI32::new(i32::MIN);
-        let main_beta = AtomicI32::new(i32::MAX);
-        let stop_signal = AtomicBool::new(false);
+    /// The score of the game over at the root, relative to the side to move:
+    /// mated for a checkmate, 0 for a stalemate. None while there are legal moves.
+    pub fn game_over_score(&self) -> Option<i32> {
+        if !self.board.generate_moves(false).is_empty() {
+            return None;
+        }
+        Some(if self.board.position.is_in_check(self.board.turn) {
+            mated_score(0)
+        } else {
+            0
+        })
+    }
 
-        // Single shared TT for all threads. The lockless implementation handles
-        // concurrent reads and writes safely via the XOR integrity check.
-        let tt = TranspositionTable::new();
+    /// Searches every root move and returns the best move with its evaluation
+    /// (relative to the side to move), None if there are no legal moves,
+    /// together with the statistics of the search. Only the searchmoves are
+    /// searched when the scenario has some ([`Scenario::with_searchmoves`]).
+    /// With `eval_noise` in the config, the scores of the root moves but mates
+    /// get a seeded random amount of noise.
+    /// Each time a root move beats the best so far it is sent on `tx` as a
+    /// [`SearchMessage::NewBest`].
+    ///
+    /// `tt` is kept by the caller, so it can be reused by the next iteration,
+    /// shared with the helper threads and inspected afterwards. The best root
+    /// move is stored in it as an exact entry.
+    ///
+    /// Once a limit is hit, the root moves not finished yet are dropped, no move
+    /// is returned and nothing is stored for the root: [`Scenario::is_aborted`]
+    /// tells the caller.
+    pub fn root_negamax(
+        &self,
+        depth: i32,
+        tt: &TranspositionTable,
+        tx: Option<&Sender<SearchMessage>>,
+    ) -> (Option<(Move, i32)>, SearchStats) {
+        let noise = self.config.eval_noise.max(0);
+        let mut stats = SearchStats::default();
+        let mut best = None::<(Move, i32)>;
+        // The noisy score of `best`: the noise only decides which move is
+        // played, the real score is the one reported and stored.
+        let mut best_choice = -INFINITY;
+        for player_move in self.root_moves() {
+            // Clone the board once per root move.
+            // All deeper recursive calls use make/unmake - no further clones.
+            let mut scenario = Scenario {
+                stats: SearchStats::default(),
+                killers: Vec::new(),
+                ..self.clone()
+            };
+            scenario.history.push(self.board.hash);
+            let _undo = scenario.board.make_move(&player_move);
 
-        available_moves.list[..available_moves.len()]
-            .par_iter()
-            .for_each_with(
-                tx.clone(),
-                |sender,
-                 RatedMove {
-                     piece_move: player_move,
-                     rating: _,
-                 }| {
-                    let turn = self.board.turn;
-
-                    if stop_signal.load(Ordering::Acquire) {
-                        return;
-                    }
+            // Widen alpha by one so that a move equal to the best so far
+            // gets its exact score instead of a fail-hard bound: equal root
+            // scores are then real ties. With noise, by as much as the noise
+            // can lift a move.
+            let alpha = best.map_or(-INFINITY, |_| {
+                (best_choice - 1).saturating_sub(noise).max(-INFINITY)
+            });
+            let eval = -scenario.negamax(depth - 1, -INFINITY, -alpha, tt, true);
+            // A mate is no matter of taste.
+            let choice = if is_mate_bound(eval) {
+                eval
+            } else {
+                eval + self.root_noise(&player_move)
+            };
+            stats.merge(&scenario.stats);
+            // The nodes since the last check were not counted yet.
+            self.shared_nodes
+                .fetch_add(scenario.stats.nodes % CHECK_INTERVAL, Ordering::Relaxed);
+            if scenario.is_aborted() {
+                return (None, stats);
+            }
 
-                    // Clone the board once per for thread isolation.
-                    // All deeper recursive calls use make/unmake - no further clones.
-                    let mut scenario = Scenario::new(self.board.clone());
-                    let _undo = scenario.board.make_move(player_move);
-
-                    let eval = scenario.minimax_alpha_beta(
-                        depth - 1,
-                        main_alpha.load(Ordering::Acquire),
-                        main_beta.load(Ordering::Acquire),
-                        &tt,
-                        true,
-                    );
-
-                    match turn {
-                        Color::White => {
-                            best_eval.fetch_max(eval, Ordering::AcqRel);
-                            main_alpha.fetch_max(eval, Ordering::AcqRel);
-
-                            // send evaluations while elaborating
-                            sender
-                                .send((*player_move, eval))
-                                .expect("failed to send to channel");
-
-                            if main_alpha.load(Ordering::Acquire)
-                                >= main_beta.load(Ordering::Acquire)
-                            {
-                                stop_signal.store(true, Ordering::Release);
-                            }
-                        }
-                        Color::Black => {
-                            best_eval.fetch_min(eval, Ordering::AcqRel);
-                            main_beta.fetch_min(eval, Ordering::AcqRel);
-
-                            // send evaluations while elaborating
-                            sender
-                                .send((*player_move, eval))
-                                .expect("failed to send to channel");
-
-                            if main_alpha.load(Ordering::Acquire)
-                                >= main_beta.load(Ordering::Acquire)
-                            {
-                                stop_signal.store(true, Ordering::Release);
-                            }
-                        }
-                    }
-                },
+            if best.is_none_or(|(best_move, _)| {
+                is_better_root_result(
+                    &self.board,
+                    (&player_move, choice),
+                    (&best_move, best_choice),
+                )
+            }) {
+                best = Some((player_move, eval));
+                best_choice = choice;
+                if let Some(tx) = tx {
+                    let mut pv = vec![player_move];
+                    let plies = (depth - 1).max(0) as usize;
+                    pv.extend(HashWalk::new(&scenario.board, tt, plies, 1).mainline());
+                    let _ = tx.send(SearchMessage::NewBest {
+                        mv: player_move,
+                        score: eval,
+                        pv,
+                    });
+                }
+            }
+        }
+
+        if let Some((best_move, eval)) = best {
+            tt.store(
+                self.board.hash,
+                depth,
+                eval,
+                Bound::Exact,
+                Some(PackedMove::new(&best_move)),
             );
+        }
 
-        drop(tx);
+        (best, stats)
     }
 
-    fn quiescence_search(&mut self, mut alpha: i32, mut beta: i32, qdepth: i32) -> i32 {
-        let static_eval = StaticEval::static_evaluate(&self.board);
-        let current_eval = static_eval.white - static_eval.black;
+    /// Pseudo-random amount in `-eval_noise..=eval_noise` added to the score of
+    /// the root move `m`. It only depends on the seed, the position and the
+    /// move, so a search with the same seed is reproducible.
+    fn root_noise(&self, m: &Move) -> i32 {
+        let noise = self.config.eval_noise;
+        if noise <= 0 {
+            return 0;
+        }
+        // SplitMix64 finalizer.
+        let mut x = self.board.hash
+            ^ u64::from(PackedMove::new(m).bits()) << 48
+            ^ (self.config.noise_seed as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^= x >> 31;
 
-        match self.board.turn {
-            Color::White => {
-                if current_eval >= beta {
-                    return beta;
-                }
-                if current_eval > alpha {
-                    alpha = current_eval;
-                }
+        (x % (2 * noise as u64 + 1)) as i32 - noise
+    }
+
+    fn quiescence_search(&mut self, mut alpha: i32, beta: i32, qdepth: i32) -> i32 {
+        self.stats.nodes += 1;
+        self.stats.qnodes += 1;
+        self.stats.seldepth = self.stats.seldepth.max(self.ply());
+        if self.out_of_time() {
+            return 0;
+        }
+        let current_eval = StaticEval::relative_evaluate(&self.board);
+        let in_check = self.board.position.is_in_check(self.board.turn);
+
+        // Stand pat: the side to move can usually do at least as well as the static eval.
+        // Not when in check: a check may be winning material, so the evasions decide.
+        if !in_check {
+            if current_eval >= beta {
+                return beta;
             }
-            Color::Black => {
-                if current_eval <= alpha {
-                    return alpha;
-                }
-                if current_eval < beta {
-                    beta = current_eval;
-                }
+            if current_eval > alpha {
+                alpha = current_eval;
             }
         }
 
@@ -265,52 +656,611 @@ impl Scenario {
             return current_eval;
         }
 
-        let mut available_moves = self.board.generate_moves(true);
+        // In the first plies quiet checks are tried too: a check can fork the king
+        // and a piece, which captures alone only see one ply too late.
+        let quiescence_ply = self.config.quiescence_depth - qdepth;
+        let with_checks = !in_check && quiescence_ply < self.config.quiescence_check_plies;
+
+        let mut available_moves = self.board.generate_moves(!with_checks);
         if available_moves.is_empty() {
-            if self.board.position.is_in_check(self.board.turn) {
-                return match self.board.turn {
-                    Color::White => i32::MIN,
-                    Color::Black => i32::MAX,
-                };
+            if in_check {
+                return mated_score(self.ply());
             }
             // No captures available and not in check: return the standing pat score.
-            return match self.board.turn {
-                Color::White => alpha,
-                Color::Black => beta,
+            return alpha;
+        }
+
+        // Delta pruning: skip captures that cannot raise the score to alpha even
+        // when the captured piece is won for free. Not applied in the endgame, where
+        // small material swings decide the game, nor when in check.
+        let delta_pruning = !self.board.is_endgame() && !in_check;
+
+        for i in 0..available_moves.len() {
+            let player_move = available_moves.get(i, &self.board);
+            if delta_pruning
+                && !player_move.is_promotion()
+                && let Some(victim) = player_move.captured_kind()
+                && current_eval + victim.value() + self.config.delta_margin < alpha
+            {
+                stats::count(&mut self.stats.delta_prunes);
+                continue;
+            }
+
+            // The full move list is only generated for the checks: underpromotions
+            // stay out as in the critical list, and quiet moves must give check.
+            if with_checks
+                && player_move
+                    .promotion()
+                    .is_some_and(|kind| kind != PieceKind::Queen)
+            {
+                continue;
+            }
+
+            let undo = self.board.make_move(&player_move);
+            if with_checks
+                && !player_move.is_capture()
+                && !player_move.is_promotion()
+                && !self.board.position.is_in_check(self.board.turn)
+            {
+                self.board.unmake_move(&player_move, undo);
+                continue;
+            }
+            let eval = -self.quiescence_search(-beta, -alpha, qdepth - 1);
+            self.board.unmake_move(&player_move, undo);
+            if eval >= beta {
+                return beta;
+            }
+            if eval > alpha {
+                alpha = eval;
+            }
+        }
+
+        alpha
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::board::Board;
+    use crate::components::pieces::{Color, Piece};
+    use crate::evaluator::config::SearchConfig;
+    use crate::moves::magic;
+
+    /// Scores (relative to the side to move) and best moves at depth 4 of the
+    /// full-window White/Black minimax this search replaced. Both principal
+    /// variation search and the negamax formulation must reproduce them.
+    /// Scores are updated when the static evaluation changes, or when a change of
+    /// move ordering changes what razoring prunes: the last position went from
+    /// -90 to -40 when the staged move picker came in, while with razoring off
+    /// it scores -160 under both orderings. A best move can also give way to
+    /// another one of the same score when the quiescence search changes.
+    const VERIFICATION_SUITE: &[(&str, i32, &str)] = &[
+        (
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            -600,
+            "e7e5",
+        ),
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            1041,
+            "b4c3",
+        ),
+        (
+            "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P2/8/1K6 b - - 0 1",
+            -100,
+            "f7g8",
+        ),
+        (
+            "r5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1",
+            MATE_SCORE - 1,
+            "a8a1",
+        ),
+        (
+            "r1bqk2r/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PPP2PPP/R1BQK2R w KQkq - 0 8",
+            1060,
+            "f3e5",
+        ),
+        (
+            "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P2/8/1K6 w - - 0 1",
+            100,
+            "b1c2",
+        ),
+        (
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            -550,
+            "d1e2",
+        ),
+        (
+            "r2q1rk1/ppp2ppp/2np1n2/2b1p1B1/2B1P1b1/2NP1N2/PPP2PPP/R2Q1RK1 b - - 0 8",
+            -40,
+            "c6a5",
+        ),
+    ];
+
+    #[test]
+    fn search_matches_the_verification_suite() {
+        magic::init();
+        let mut researches = 0;
+        for (fen, score, best) in VERIFICATION_SUITE {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let tt = TranspositionTable::new();
+            let mut scenario = Scenario::new(board.clone());
+
+            assert_eq!(
+                scenario.negamax(4, -INFINITY, INFINITY, &tt, true),
+                *score,
+                "{}",
+                fen
+            );
+            let best_move = board.parse_uci_move(best).unwrap();
+            assert!(
+                tt.probe_move(board.hash)
+                    .is_some_and(|m| m.matches(&best_move)),
+                "{}",
+                fen
+            );
+            researches += scenario.stats.pvs_researches;
+        }
+        if stats::DETAILED {
+            assert!(researches > 0);
+        }
+    }
+
+    #[test]
+    fn pvs_scores_match_plain_alpha_beta() {
+        magic::init();
+        // Razoring is off: what it prunes depends on the order moves are searched in.
+        let search = |board: &Board, pvs: bool| {
+            let config = SearchConfig {
+                pvs,
+                razor_depth: 0,
+                ..SearchConfig::default()
             };
+            let tt = TranspositionTable::new();
+            let mut scenario = Scenario::with_config(board.clone(), config);
+            let eval = scenario.negamax(4, -INFINITY, INFINITY, &tt, true);
+            (eval, scenario.stats.pvs_researches)
+        };
+
+        let mut researches = 0;
+        for (fen, _, _) in VERIFICATION_SUITE {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let (eval, pvs_researches) = search(&board, true);
+            let (alpha_beta_eval, alpha_beta_researches) = search(&board, false);
+            assert_eq!(eval, alpha_beta_eval, "{}", fen);
+            assert_eq!(alpha_beta_researches, 0);
+            researches += pvs_researches;
         }
+        if stats::DETAILED {
+            assert!(researches > 0);
+        }
+    }
 
-        match self.board.turn {
-            Color::White => {
-                for i in 0..available_moves.len() {
-                    let player_move = available_moves.get(i);
-                    let undo = self.board.make_move(&player_move);
-                    let eval = self.quiescence_search(alpha, beta, qdepth - 1);
-                    self.board.unmake_move(&player_move, undo);
-                    if eval >= beta {
-                        return beta;
-                    }
-                    if eval > alpha {
-                        alpha = eval;
-                    }
-                }
-                alpha
+    #[test]
+    fn razoring_alone_moves_the_last_verification_score() {
+        magic::init();
+        let (fen, _, _) = VERIFICATION_SUITE[VERIFICATION_SUITE.len() - 1];
+        let config = SearchConfig {
+            razor_depth: 0,
+            ..SearchConfig::default()
+        };
+        let tt = TranspositionTable::new();
+        let mut scenario = Scenario::with_config(Board::from_forsyth_edwards(fen).unwrap(), config);
+        assert_eq!(scenario.negamax(4, -INFINITY, INFINITY, &tt, true), -160);
+    }
+
+    #[test]
+    fn the_root_search_matches_the_full_window_search() {
+        magic::init();
+        // Razoring is off: what it prunes depends on the order moves are searched in,
+        // and the root does not order its moves. Between moves of equal score the
+        // root and the full-window search may then choose differently.
+        let config = SearchConfig {
+            razor_depth: 0,
+            ..SearchConfig::default()
+        };
+        for (fen, _, _) in VERIFICATION_SUITE {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let tt = TranspositionTable::new();
+            let mut scenario = Scenario::with_config(board.clone(), config);
+            let eval = scenario.negamax(4, -INFINITY, INFINITY, &tt, true);
+            let best_move = tt.probe_move(board.hash).unwrap();
+
+            let root = Scenario::with_config(board.clone(), config);
+            let (root_move, root_eval) = search_scenario_root(root, 4);
+            assert_eq!(root_eval, eval, "{}", fen);
+            if !best_move.matches(&root_move) {
+                // A tie: the move the full-window search chose scores the same at the root.
+                let tied = board
+                    .generate_moves(false)
+                    .iter(&board)
+                    .find(|m| best_move.matches(m))
+                    .unwrap();
+                let root = Scenario::with_config(board.clone(), config)
+                    .with_searchmoves(&[tied])
+                    .unwrap();
+                assert_eq!(search_scenario_root(root, 4).1, eval, "{}", fen);
             }
-            Color::Black => {
-                for i in 0..available_moves.len() {
-                    let player_move = available_moves.get(i);
-                    let undo = self.board.make_move(&player_move);
-                    let eval = self.quiescence_search(alpha, beta, qdepth - 1);
-                    self.board.unmake_move(&player_move, undo);
-                    if eval <= alpha {
-                        return alpha;
-                    }
-                    if eval < beta {
-                        beta = eval;
-                    }
-                }
-                beta
+        }
+    }
+
+    fn search_root(board: &Board, depth: i32) -> (Move, i32) {
+        search_scenario_root(Scenario::new(board.clone()), depth)
+    }
+
+    fn search_scenario_root(scenario: Scenario, depth: i32) -> (Move, i32) {
+        let (best, _) = scenario.root_negamax(depth, &TranspositionTable::new(), None);
+        best.expect("position has legal moves")
+    }
+
+    #[test]
+    fn the_root_searches_every_move_up_to_the_last_one_generated() {
+        magic::init();
+        // Only Kxd2 wins the rook, and king moves are generated last.
+        let board = Board::from_forsyth_edwards("4k3/8/8/8/8/8/P2r4/4K1N1 w - - 0 1").unwrap();
+        let capture = board.parse_uci_move("e1d2").unwrap();
+        let last = board.generate_moves(false).iter(&board).last();
+        assert_eq!(last, Some(capture));
+
+        for depth in 1..=3 {
+            let (best_move, eval) = search_root(&board, depth);
+            assert_eq!(best_move, capture, "depth {}", depth);
+            assert!(eval > PieceKind::Rook.value() / 2, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn the_rook_drives_the_lone_king_to_the_edge_and_mates() {
+        magic::init();
+        let mut board = Board::from_forsyth_edwards("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let lone_king = Piece::new(Color::Black, PieceKind::King);
+        let edge_distance = |board: &Board| {
+            let square = board.position.get(lone_king).lsb_index().unwrap();
+            let (file, rank) = (square % 8, square / 8);
+            file.min(7 - file).min(rank).min(7 - rank)
+        };
+        assert_eq!(edge_distance(&board), 3);
+
+        // Both sides play the engine's moves.
+        let mut distances = Vec::new();
+        while Scenario::new(board.clone()).game_over_score().is_none() && distances.len() < 40 {
+            let (best_move, _) = search_root(&board, 4);
+            board.make_move(&best_move);
+            distances.push(edge_distance(&board));
+        }
+        assert_eq!(
+            Scenario::new(board.clone()).game_over_score(),
+            Some(mated_score(0)),
+            "{:?}",
+            distances
+        );
+        assert_eq!(distances.last(), Some(&0));
+    }
+
+    #[test]
+    fn prefers_the_shortest_mate() {
+        magic::init();
+        // Mate in 2 (1. Kg6 Kg8 2. Ra8#), with slower mates also in reach at depth 5.
+        let board = Board::from_forsyth_edwards("7k/8/5K2/8/8/8/8/R7 w - - 0 1").unwrap();
+        let (_, eval) = search_root(&board, 5);
+        assert_eq!(mate_distance(eval), Some(3));
+        assert_eq!(eval, MATE_SCORE - 3);
+
+        assert_eq!(uci_score(eval), "mate 2");
+        assert_eq!(uci_score(-eval), "mate -2");
+        assert_eq!(uci_score(mated_score(2)), "mate -1");
+        assert_eq!(uci_score(-1234), "cp -123");
+    }
+
+    #[test]
+    fn equal_scores_prefer_resetting_the_fifty_move_clock() {
+        magic::init();
+        let board = Board::from_forsyth_edwards("4k3/8/8/p7/8/8/8/R3K3 w - - 90 60").unwrap();
+        let capture = board.parse_uci_move("a1a5").unwrap();
+        let quiet = board.parse_uci_move("a1a2").unwrap();
+
+        assert!(is_better_root_result(
+            &board,
+            (&capture, 9000),
+            (&quiet, 9000)
+        ));
+        assert!(!is_better_root_result(
+            &board,
+            (&quiet, 9000),
+            (&capture, 9000)
+        ));
+        // A higher score still wins over the clock.
+        assert!(is_better_root_result(
+            &board,
+            (&quiet, 9010),
+            (&capture, 9000)
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(feature = "minimal-stats", ignore = "needs the detailed search stats")]
+    fn delta_pruning_cuts_quiescence_but_not_in_the_endgame() {
+        magic::init();
+        let search = |fen: &str, delta_margin: i32| {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let config = SearchConfig {
+                delta_margin,
+                ..SearchConfig::default()
+            };
+            let tt = TranspositionTable::new();
+            let mut scenario = Scenario::with_config(board.clone(), config);
+            let eval = scenario.negamax(3, -INFINITY, INFINITY, &tt, true);
+            (eval, scenario.stats)
+        };
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let (eval, pruned) = search(fen, SearchConfig::default().delta_margin);
+        let (full_eval, full) = search(fen, INFINITY / 4);
+        assert!(pruned.delta_prunes > 0);
+        assert_eq!(full.delta_prunes, 0);
+        assert!(pruned.qnodes < full.qnodes);
+        assert_eq!(eval, full_eval);
+
+        let (_, endgame) = search("8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P2/8/1K6 w - - 0 1", 0);
+        assert_eq!(endgame.delta_prunes, 0);
+    }
+
+    #[test]
+    fn quiescence_finds_a_quiet_check_that_wins_a_rook() {
+        magic::init();
+        // Qd5+ forks the king and the rook on a8.
+        let board = Board::from_forsyth_edwards("r5k1/8/8/8/8/8/5PPP/3Q2K1 w - - 0 1").unwrap();
+        let search = |quiescence_check_plies: i32| {
+            let config = SearchConfig {
+                quiescence_check_plies,
+                ..SearchConfig::default()
+            };
+            let tt = TranspositionTable::new();
+            Scenario::with_config(board.clone(), config).negamax(0, -INFINITY, INFINITY, &tt, true)
+        };
+
+        let captures_only = search(0);
+        let with_checks = search(SearchConfig::default().quiescence_check_plies);
+        assert_eq!(captures_only, StaticEval::relative_evaluate(&board));
+        assert!(
+            with_checks - captures_only > PieceKind::Rook.value() - PieceKind::Pawn.value(),
+            "{} vs {}",
+            with_checks,
+            captures_only
+        );
+    }
+
+    #[test]
+    fn quiescence_does_not_stand_pat_in_check() {
+        magic::init();
+        // Qd5+ forks the king and the rook on a8: every evasion loses the rook.
+        let board = Board::from_forsyth_edwards("r5k1/8/8/3Q4/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        let static_eval = StaticEval::relative_evaluate(&board);
+        let mut scenario = Scenario::new(board);
+        let depth = scenario.config.quiescence_depth;
+
+        // Standing pat would fail high on a window just under the static eval.
+        let eval = scenario.quiescence_search(static_eval - 1, static_eval, depth);
+        assert!(eval < static_eval, "{} vs {}", eval, static_eval);
+
+        // The rook is lost after the evasion, net of the queen's move to a8.
+        let eval = scenario.quiescence_search(-INFINITY, INFINITY, depth);
+        assert!(
+            static_eval - eval > PieceKind::Knight.value(),
+            "{} vs {}",
+            eval,
+            static_eval
+        );
+    }
+
+    #[test]
+    fn quiescence_sees_the_promotion_refuting_a_capture() {
+        magic::init();
+        // Nxe4 wins a pawn but lets b1=Q through: only the promotion refutes it.
+        let board = Board::from_forsyth_edwards("7k/8/8/8/4p3/8/1p1N4/7K w - - 0 1").unwrap();
+        let (best, eval) = search_root(&board, 1);
+        assert_ne!(best.to_uci(), "d2e4");
+        assert!(eval > 0, "{} {}", best.to_uci(), eval);
+
+        // Only the queen promotion is critical.
+        let pawn = Board::from_forsyth_edwards("7k/8/8/8/8/8/1p6/7K b - - 0 1").unwrap();
+        let critical: Vec<_> = pawn
+            .generate_moves(true)
+            .iter(&pawn)
+            .map(|m| m.to_uci())
+            .collect();
+        assert_eq!(critical, ["b2b1q"]);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "minimal-stats", ignore = "needs the detailed search stats")]
+    fn single_replies_extend_the_search_to_a_forced_mate() {
+        magic::init();
+        // 1. Nf7+ Kg8 2. Nh6+ Kh8 3. Qg8#: both Black replies are forced.
+        let board = Board::from_forsyth_edwards("5n1k/6pp/8/4N3/2Q5/8/8/6K1 w - - 0 1").unwrap();
+        let search = |max_extensions: i32| {
+            let config = SearchConfig {
+                max_extensions,
+                ..SearchConfig::default()
+            };
+            let tt = TranspositionTable::new();
+            let mut scenario = Scenario::with_config(board.clone(), config);
+            let eval = scenario.negamax(3, -INFINITY, INFINITY, &tt, true);
+            (eval, scenario.stats.single_reply_extensions)
+        };
+
+        let (eval, extensions) = search(SearchConfig::default().max_extensions);
+        assert_eq!(eval, MATE_SCORE - 5);
+        assert!(extensions > 0);
+
+        let (eval, extensions) = search(0);
+        assert_eq!(mate_distance(eval), None);
+        assert_eq!(extensions, 0);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "minimal-stats", ignore = "needs the detailed search stats")]
+    fn singular_extensions_only_run_when_enabled_and_deep_enough() {
+        magic::init();
+        let board = Board::from_forsyth_edwards(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+        )
+        .unwrap();
+        let min_depth = 2;
+        let search = |singular_extensions: bool, depth: i32| {
+            let config = SearchConfig {
+                singular_extensions,
+                singular_min_depth: min_depth,
+                ..SearchConfig::default()
+            };
+            // Deepen iteratively so that the children have TT moves to test.
+            let tt = TranspositionTable::new();
+            let mut stats = SearchStats::default();
+            for d in 1..=depth {
+                let mut scenario = Scenario::with_config(board.clone(), config);
+                scenario.negamax(d, -INFINITY, INFINITY, &tt, true);
+                stats.merge(&scenario.stats);
             }
+            stats
+        };
+
+        // The root is never tested, so nothing runs until a child is deep enough.
+        assert_eq!(search(true, min_depth).singular_searches, 0);
+        assert!(search(true, min_depth + 1).singular_searches > 0);
+        assert_eq!(search(false, min_depth + 1).singular_searches, 0);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "minimal-stats", ignore = "needs the detailed search stats")]
+    fn iid_runs_only_at_deep_pv_nodes_and_leaves_a_move() {
+        magic::init();
+        let iid_min_depth = SearchConfig::default().iid_min_depth;
+        let board = Board::from_forsyth_edwards(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        )
+        .unwrap();
+
+        let tt = TranspositionTable::new();
+        let mut scenario = Scenario::new(board.clone());
+        scenario.negamax(iid_min_depth - 1, -INFINITY, INFINITY, &tt, true);
+        assert_eq!(scenario.stats.iid_searches, 0);
+
+        let tt = TranspositionTable::new();
+        let mut scenario = Scenario::new(board.clone());
+        scenario.negamax(iid_min_depth, 0, 1, &tt, true);
+        assert_eq!(
+            scenario.stats.iid_searches, 0,
+            "null window is not a PV node"
+        );
+
+        let tt = TranspositionTable::new();
+        let mut scenario = Scenario::new(board.clone());
+        scenario.negamax(iid_min_depth, -INFINITY, INFINITY, &tt, true);
+        assert!(scenario.stats.iid_searches > 0);
+        let best = tt
+            .probe_move(board.hash)
+            .expect("root should store a best move");
+        let moves = board.generate_moves(false);
+        assert!(
+            moves.list[..moves.len()]
+                .iter()
+                .any(|rm| rm.piece_move == best)
+        );
+    }
+
+    #[test]
+    fn contempt_avoids_a_repetition_the_engine_would_otherwise_accept() {
+        magic::init();
+        // White is a pawn down. After the rooks went back and forth, Ra2 repeats
+        // the position reached by it the first time.
+        let start = Board::from_forsyth_edwards("1r5k/6pp/8/8/8/8/6P1/R6K w - - 0 1").unwrap();
+        let (board, history, _) =
+            start.play_uci_moves_with_history(&["a1a2", "b8b7", "a2a1", "b7b8"]);
+        assert_eq!(board.hash, start.hash);
+
+        let search = |contempt: i32| {
+            let config = SearchConfig {
+                contempt,
+                ..SearchConfig::default()
+            };
+            let scenario =
+                Scenario::with_config(board.clone(), config).with_history(history.clone());
+            let (best, eval) = search_scenario_root(scenario, 3);
+            (best.to_uci(), eval)
+        };
+
+        assert_eq!(search(0), ("a1a2".to_string(), 0));
+        let (best, eval) = search(1500);
+        assert_ne!(best, "a1a2");
+        assert!(
+            eval > -1500 && eval < -PieceKind::Pawn.value() / 2,
+            "{}",
+            eval
+        );
+    }
+
+    #[test]
+    fn a_rook_ahead_the_root_does_not_repeat_into_a_draw() {
+        magic::init();
+        // White is a rook up. The rooks went back and forth twice: Ra2 would
+        // repeat the position after it a third time.
+        let start = Board::from_forsyth_edwards("1r5k/6pp/8/8/8/8/6P1/R1R4K w - - 0 1").unwrap();
+        let shuffle = ["a1a2", "b8b7", "a2a1", "b7b8"];
+        let (board, history, _) = start.play_uci_moves_with_history(&[shuffle, shuffle].concat());
+        assert_eq!(board.hash, start.hash);
+        let repeat = board.parse_uci_move("a1a2").unwrap();
+
+        let scenario = || Scenario::new(board.clone()).with_history(history.clone());
+        let (best, eval) = search_scenario_root(scenario(), 3);
+        assert_ne!(best, repeat);
+        assert!(eval > PieceKind::Rook.value() / 2, "{}", eval);
+
+        // Forced, the repetition is played and scored as the draw it is.
+        let forced = scenario().with_searchmoves(&[repeat]).unwrap();
+        assert_eq!(search_scenario_root(forced, 3), (repeat, 0));
+    }
+
+    #[test]
+    fn root_noise_varies_the_moves_only_when_enabled() {
+        magic::init();
+        let openings = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+        ];
+        let search = |board: &Board, eval_noise: i32, noise_seed: i32| {
+            let config = SearchConfig {
+                eval_noise,
+                noise_seed,
+                ..SearchConfig::default()
+            };
+            search_scenario_root(Scenario::with_config(board.clone(), config), 2)
+        };
+
+        for fen in openings {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            assert_eq!(search(&board, 0, 1), search(&board, 0, 2), "{}", fen);
+            assert_eq!(search(&board, 200, 7), search(&board, 200, 7), "{}", fen);
+        }
+
+        let mut first_moves = std::collections::HashSet::new();
+        for seed in 0..8 {
+            let board = Board::from_forsyth_edwards(openings[0]).unwrap();
+            first_moves.insert(search(&board, 200, seed).0);
         }
+        assert!(first_moves.len() > 1, "{:?}", first_moves);
+
+        // The move is chosen with the noise, its score is reported without.
+        for seed in 0..8 {
+            let board = Board::from_forsyth_edwards(openings[0]).unwrap();
+            let (m, eval) = search(&board, 200, seed);
+            let alone = Scenario::new(board).with_searchmoves(&[m]).unwrap();
+            assert_eq!(search_scenario_root(alone, 2), (m, eval), "{}", seed);
+        }
+
+        // Mates are left alone.
+        let board = Board::from_forsyth_edwards("r5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        assert_eq!(search(&board, 1000, 3), search(&board, 0, 0));
     }
 }
@@ -0,0 +1,380 @@
+use std::fmt::Write;
+
+use crate::components::{board::Board, pieces::Color};
+use crate::moves::move_type::Move;
+
+use super::evaluation::{mate_distance, score_from_tt};
+use super::transposition::{Bound, TranspositionTable, TtEntry};
+
+/// A move reached while walking the transposition table.
+#[derive(Debug, Clone)]
+pub struct WalkNode {
+    pub played: Move,
+    /// Entry of the position after `played`, seen from the side that played it.
+    /// None when the table has nothing for that position (e.g. the search ended there).
+    pub eval: Option<WalkEval>,
+    /// Continuations, the stored best move first.
+    pub children: Vec<WalkNode>,
+}
+
+/// Score, depth and bound of a stored entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalkEval {
+    pub score: i32,
+    pub depth: i32,
+    pub bound: Bound,
+}
+
+impl WalkEval {
+    /// Converts the entry of the position after a move to the point of view of the
+    /// side that played the move: the score is negated, the bound flipped, and a
+    /// mate score counts the move itself.
+    fn after_move(entry: TtEntry) -> Self {
+        let bound = match entry.bound {
+            Bound::Exact => Bound::Exact,
+            Bound::Lower => Bound::Upper,
+            Bound::Upper => Bound::Lower,
+        };
+
+        WalkEval {
+            score: score_from_tt(-entry.score, 1),
+            depth: entry.depth,
+            bound,
+        }
+    }
+}
+
+/// The analysis tree stored in the transposition table after a search.
+#[derive(Debug, Clone)]
+pub struct HashWalk {
+    /// FEN of the position the walk started from.
+    pub root_fen: String,
+    /// The root position, replayed to write the moves in SAN.
+    root: Board,
+    /// Root moves, the stored best move first.
+    pub variations: Vec<WalkNode>,
+}
+
+impl HashWalk {
+    /// Walks the table from `board`, following the stored best move of each position
+    /// for at most `max_plies` plies.
+    ///
+    /// With `branch` > 1 up to `branch - 1` other moves are listed at every node too,
+    /// ordered by score, as long as the table has an entry for the position they lead to.
+    /// Those alternatives are then walked the same way.
+    ///
+    /// Entries only store squares and may come from a hash collision, so a stored move
+    /// is only followed if it matches a legal move of the position. The walk also stops
+    /// at positions already seen on the current line, since the table can contain cycles.
+    pub fn new(board: &Board, tt: &TranspositionTable, max_plies: usize, branch: usize) -> Self {
+        let mut board = board.clone();
+        let mut line = Vec::new();
+        let variations = walk(&mut board, tt, max_plies, branch.max(1), &mut line);
+
+        HashWalk {
+            root_fen: board.to_forsyth_edwards(),
+            root: board,
+            variations,
+        }
+    }
+
+    /// The principal line: the stored best move of every position.
+    pub fn mainline(&self) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut nodes = &self.variations;
+        while let Some(node) = nodes.first() {
+            line.push(node.played);
+            nodes = &node.children;
+        }
+
+        line
+    }
+
+    /// Exports the tree as PGN movetext with the alternatives as variations,
+    /// preceded by the tags needed to start from the root position.
+    /// Moves are written in SAN and annotated with
+    /// `{score/depth}` comments, score in pawns or `#n` for mates,
+    /// prefixed by `>=` or `<=` when the entry is only a bound.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = format!("[SetUp \"1\"]\n[FEN \"{}\"]\n\n", self.root_fen);
+        write_pgn_line(&mut pgn, &self.root, &self.variations, true);
+        pgn.push_str(" *\n");
+
+        // The first move number is written with a leading space like every other.
+        pgn.replacen("\n\n ", "\n\n", 1)
+    }
+
+    /// Exports the tree as JSON:
+    /// `{"fen": ..., "variations": [{"move", "score", "depth", "bound", "children"}]}`.
+    /// `score`, `depth` and `bound` are null for moves without an entry.
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"fen\":\"{}\",\"variations\":", self.root_fen);
+        write_json_nodes(&mut json, &self.variations);
+        json.push('}');
+
+        json
+    }
+}
+
+fn walk(
+    board: &mut Board,
+    tt: &TranspositionTable,
+    plies_left: usize,
+    branch: usize,
+    line: &mut Vec<u64>,
+) -> Vec<WalkNode> {
+    if plies_left == 0 || line.contains(&board.hash) {
+        return Vec::new();
+    }
+    let Some(best) = tt.entry(board.hash).and_then(|entry| entry.best_move) else {
+        return Vec::new();
+    };
+
+    let moves: Vec<Move> = board.generate_moves(false).iter(board).collect();
+    let Some(best_move) = moves.iter().copied().find(|m| best.matches(m)) else {
+        return Vec::new();
+    };
+
+    // Alternatives are only known through the entries of the positions they lead to.
+    let mut alternatives = Vec::new();
+    if branch > 1 {
+        for m in moves {
+            if best.matches(&m) {
+                continue;
+            }
+            let undo = board.make_move(&m);
+            let entry = tt.entry(board.hash);
+            board.unmake_move(&m, undo);
+            if let Some(entry) = entry {
+                alternatives.push((m, WalkEval::after_move(entry)));
+            }
+        }
+        alternatives.sort_by_key(|(_, eval)| std::cmp::Reverse(eval.score));
+        alternatives.truncate(branch - 1);
+    }
+
+    line.push(board.hash);
+    let mut nodes = Vec::with_capacity(1 + alternatives.len());
+    let candidates = std::iter::once(best_move).chain(alternatives.into_iter().map(|(m, _)| m));
+    for m in candidates {
+        let undo = board.make_move(&m);
+        let eval = tt.entry(board.hash).map(WalkEval::after_move);
+        let children = walk(board, tt, plies_left - 1, branch, line);
+        board.unmake_move(&m, undo);
+
+        nodes.push(WalkNode {
+            played: m,
+            eval,
+            children,
+        });
+    }
+    line.pop();
+
+    nodes
+}
+
+fn write_pgn_line(pgn: &mut String, board: &Board, nodes: &[WalkNode], numbered: bool) {
+    let Some((main, alternatives)) = nodes.split_first() else {
+        return;
+    };
+
+    let number = board.fullmove_number();
+    match board.turn {
+        Color::White => {
+            let _ = write!(pgn, " {}.", number);
+        }
+        Color::Black if numbered => {
+            let _ = write!(pgn, " {}...", number);
+        }
+        Color::Black => {}
+    }
+    let _ = write!(pgn, " {}", board.move_to_san(&main.played));
+    if let Some(eval) = main.eval {
+        let bound = match eval.bound {
+            Bound::Exact => "",
+            Bound::Lower => ">=",
+            Bound::Upper => "<=",
+        };
+        let _ = write!(
+            pgn,
+            " {{{}{}/{}}}",
+            bound,
+            format_score(eval.score),
+            eval.depth
+        );
+    }
+
+    for alternative in alternatives {
+        pgn.push_str(" (");
+        let start = pgn.len();
+        write_pgn_line(pgn, board, std::slice::from_ref(alternative), true);
+        // No space right after the opening parenthesis.
+        pgn.remove(start);
+        pgn.push(')');
+    }
+
+    let next = board.make_unchecked_move(&main.played);
+    write_pgn_line(pgn, &next, &main.children, !alternatives.is_empty());
+}
+
+fn format_score(score: i32) -> String {
+    match mate_distance(score) {
+        // Mate distance in moves of the mating side, negative when being mated.
+        Some(plies) if score > 0 => format!("#{}", (plies + 1) / 2),
+        Some(plies) => format!("#-{}", (plies + 1) / 2),
+        None => format!("{:+.2}", score as f64 / 1000.0),
+    }
+}
+
+fn write_json_nodes(json: &mut String, nodes: &[WalkNode]) {
+    json.push('[');
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        let _ = write!(json, "{{\"move\":\"{}\",", node.played.to_uci());
+        match node.eval {
+            Some(eval) => {
+                let bound = match eval.bound {
+                    Bound::Exact => "exact",
+                    Bound::Lower => "lower",
+                    Bound::Upper => "upper",
+                };
+                let _ = write!(
+                    json,
+                    "\"score\":{},\"depth\":{},\"bound\":\"{}\",",
+                    eval.score, eval.depth, bound
+                );
+            }
+            None => json.push_str("\"score\":null,\"depth\":null,\"bound\":null,"),
+        }
+        json.push_str("\"children\":");
+        write_json_nodes(json, &node.children);
+        json.push('}');
+    }
+    json.push(']');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::evaluation::MATE_SCORE;
+    use crate::moves::packed::PackedMove;
+    use crate::moves::{magic, move_type::Scenario};
+
+    fn search(board: &Board, depth: i32, tt: &TranspositionTable) -> (Move, i32) {
+        let (best, _) = Scenario::new(board.clone()).root_negamax(depth, tt, None);
+        best.expect("position has legal moves")
+    }
+
+    /// Replays every line of the tree, checking each move is legal where it is played.
+    fn assert_replays_legally(board: &Board, nodes: &[WalkNode]) -> usize {
+        let legal = board.generate_moves(false);
+        let mut count = 0;
+        for node in nodes {
+            assert!(
+                legal
+                    .iter(board)
+                    .any(|m| m.to_uci() == node.played.to_uci()),
+                "{} is not legal in {}",
+                node.played.to_uci(),
+                board.to_forsyth_edwards()
+            );
+            let next = board.make_unchecked_move(&node.played);
+            count += 1 + assert_replays_legally(&next, &node.children);
+        }
+
+        count
+    }
+
+    #[test]
+    fn mainline_follows_the_search_and_replays_legally() {
+        magic::init();
+        let board = Board::from_forsyth_edwards(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let tt = TranspositionTable::new();
+        let (best, eval) = search(&board, 4, &tt);
+
+        let walk = HashWalk::new(&board, &tt, 6, 1);
+        let mainline = walk.mainline();
+        assert!(mainline.len() >= 3, "{}", walk.to_pgn());
+        assert_eq!(mainline[0].to_uci(), best.to_uci());
+        assert_eq!(walk.variations[0].eval.map(|e| e.score), Some(eval));
+        assert_eq!(
+            assert_replays_legally(&board, &walk.variations),
+            mainline.len()
+        );
+
+        let tree = HashWalk::new(&board, &tt, 3, 3);
+        let uci = |line: &[Move]| line.iter().map(Move::to_uci).collect::<Vec<_>>();
+        assert_eq!(uci(&tree.mainline()[..3]), uci(&mainline[..3]));
+        assert!(tree.variations.len() > 1);
+        assert!(assert_replays_legally(&board, &tree.variations) > 3);
+    }
+
+    #[test]
+    fn forced_mate_is_walked_to_the_end_and_exported() {
+        magic::init();
+        let board = Board::from_forsyth_edwards("7k/8/5K2/8/8/8/8/R7 w - - 0 1").unwrap();
+        let tt = TranspositionTable::new();
+        search(&board, 5, &tt);
+
+        let walk = HashWalk::new(&board, &tt, 10, 1);
+        let mut end = board.clone();
+        for m in walk.mainline() {
+            end = end.make_unchecked_move(&m);
+        }
+        assert_eq!(walk.mainline().len(), 3);
+        assert!(end.generate_moves(false).is_empty());
+        assert!(end.position.is_in_check(end.turn));
+
+        let last = walk.variations[0].children[0].children[0].eval.unwrap();
+        assert_eq!(last.score, MATE_SCORE - 1);
+
+        let pgn = walk.to_pgn();
+        assert!(pgn.starts_with("[SetUp \"1\"]\n[FEN \"7k/8/5K2/8/8/8/8/R7 w - - 0 1\"]\n\n1. "));
+        assert!(pgn.contains("2. Ra8# {#1/"), "{}", pgn);
+        assert!(pgn.ends_with(" *\n"));
+
+        let json = walk.to_json();
+        assert!(json.starts_with(
+            "{\"fen\":\"7k/8/5K2/8/8/8/8/R7 w - - 0 1\",\"variations\":[{\"move\":\""
+        ));
+        assert!(json.contains(&format!("\"move\":\"a1a8\",\"score\":{}", MATE_SCORE - 1)));
+    }
+
+    #[test]
+    fn pgn_moves_are_numbered_from_the_root_fullmove() {
+        magic::init();
+        let board = Board::from_forsyth_edwards("7K/8/5k2/8/8/8/8/r7 b - - 4 30").unwrap();
+        let tt = TranspositionTable::new();
+        search(&board, 5, &tt);
+
+        let pgn = HashWalk::new(&board, &tt, 10, 1).to_pgn();
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.starts_with("30... K"), "{}", pgn);
+        assert!(movetext.contains(" 31. K"), "{}", pgn);
+        assert!(movetext.contains(" Ra8# {#1/"), "{}", pgn);
+    }
+
+    #[test]
+    fn collided_entries_are_not_followed() {
+        magic::init();
+        let board = Board::new_game();
+        let tt = TranspositionTable::new();
+        // A "best move" that is not legal in the position, as left by a hash collision.
+        let other = Board::from_forsyth_edwards("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let bogus = other.parse_uci_move("a1a8").unwrap();
+        tt.store(
+            board.hash,
+            3,
+            0,
+            Bound::Exact,
+            Some(PackedMove::new(&bogus)),
+        );
+
+        assert!(HashWalk::new(&board, &tt, 5, 2).variations.is_empty());
+    }
+}
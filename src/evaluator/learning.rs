@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::moves::move_type::Move;
+
+const HEADER: &str = "# corman learning file v1";
+
+/// A root evaluation remembered between sessions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LearnedEntry {
+    pub depth: i32,
+    /// Score relative to the side to move.
+    pub score: i32,
+    /// Best move in UCI notation. It is re-validated against the legal moves
+    /// of the position before being played, which also guards against hash collisions.
+    pub best_move: String,
+}
+
+/// Persistent store of root search results keyed by Zobrist hash.
+///
+/// The file is plain text, one entry per line sorted by key:
+///   `<hash as 16 hex digits> <depth> <score> <uci move>`
+/// Malformed lines are skipped on load. Saving writes a temporary file and
+/// renames it over the old one, so a crash mid-write never leaves a truncated store.
+/// When the store grows over `max_entries`, the shallowest entries are evicted first.
+#[derive(Debug)]
+pub struct LearningStore {
+    path: PathBuf,
+    entries: BTreeMap<u64, LearnedEntry>,
+    max_entries: usize,
+    dirty: bool,
+}
+
+impl LearningStore {
+    pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+    /// Loads the store at `path`. A missing file yields an empty store.
+    pub fn open(path: impl AsRef<Path>, max_entries: usize) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = BTreeMap::new();
+
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read learning file {}", path.display()))?;
+            for line in content.lines() {
+                if line.starts_with('#') || line.trim().is_empty() {
+                    continue;
+                }
+                if let Some((hash, entry)) = parse_line(line) {
+                    entries.insert(hash, entry);
+                }
+            }
+        }
+
+        let mut store = Self {
+            path,
+            entries,
+            max_entries: max_entries.max(1),
+            dirty: false,
+        };
+        store.evict();
+
+        Ok(store)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the stored entry if it was searched at least as deep as `depth`.
+    pub fn probe(&self, hash: u64, depth: i32) -> Option<&LearnedEntry> {
+        self.entries.get(&hash).filter(|e| e.depth >= depth)
+    }
+
+    /// Returns the stored entry for `hash` regardless of its depth.
+    pub fn get(&self, hash: u64) -> Option<&LearnedEntry> {
+        self.entries.get(&hash)
+    }
+
+    /// Records a search result. An existing entry is only replaced by a result
+    /// that is at least as deep.
+    pub fn record(&mut self, hash: u64, depth: i32, score: i32, best_move: &Move) {
+        if self.entries.get(&hash).is_some_and(|e| e.depth > depth) {
+            return;
+        }
+        self.entries.insert(
+            hash,
+            LearnedEntry {
+                depth,
+                score,
+                best_move: best_move.to_uci(),
+            },
+        );
+        self.dirty = true;
+        self.evict();
+    }
+
+    /// Writes the store back to disk if anything changed since it was loaded.
+    pub fn save(&mut self) -> Result<(), anyhow::Error> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut file = fs::File::create(&tmp_path)
+                .with_context(|| format!("failed to create {}", tmp_path.display()))?;
+            writeln!(file, "{}", HEADER)?;
+            for (hash, e) in &self.entries {
+                writeln!(
+                    file,
+                    "{:016x} {} {} {}",
+                    hash, e.depth, e.score, e.best_move
+                )?;
+            }
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("failed to replace {}", self.path.display()))?;
+        self.dirty = false;
+
+        Ok(())
+    }
+
+    /// Drops the shallowest entries until the store fits in `max_entries`.
+    fn evict(&mut self) {
+        while self.entries.len() > self.max_entries {
+            let Some(shallowest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.depth)
+                .map(|(hash, _)| *hash)
+            else {
+                break;
+            };
+            self.entries.remove(&shallowest);
+            self.dirty = true;
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(u64, LearnedEntry)> {
+    let mut fields = line.split_whitespace();
+    let hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let depth = fields.next()?.parse().ok()?;
+    let score = fields.next()?.parse().ok()?;
+    let best_move = fields.next()?.to_string();
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Some((
+        hash,
+        LearnedEntry {
+            depth,
+            score,
+            best_move,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::board::Board;
+    use crate::evaluator::transposition::TranspositionTable;
+    use crate::moves::{magic, move_type::Scenario};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("corman-{}-{}.learn", name, std::process::id()))
+    }
+
+    fn search(board: &Board, depth: i32) -> (Move, i32) {
+        let scenario = Scenario::new(board.clone());
+        let (best, _) = scenario.root_negamax(depth, &TranspositionTable::new(), None);
+        best.expect("position has legal moves")
+    }
+
+    #[test]
+    fn stored_result_survives_restart_and_deeper_request_updates_it() {
+        magic::init();
+        let path = temp_path("restart");
+        let _ = fs::remove_file(&path);
+        let board = Board::from_forsyth_edwards(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        )
+        .unwrap();
+
+        let mut store = LearningStore::open(&path, LearningStore::DEFAULT_MAX_ENTRIES).unwrap();
+        let (best, score) = search(&board, 2);
+        store.record(board.hash, 2, score, &best);
+        store.save().unwrap();
+
+        // Restart: a shallower request is answered from the file.
+        let mut store = LearningStore::open(&path, LearningStore::DEFAULT_MAX_ENTRIES).unwrap();
+        let entry = store.probe(board.hash, 1).expect("entry should be stored");
+        assert_eq!(entry.best_move, best.to_uci());
+        assert!(board.parse_uci_move(&entry.best_move).is_some());
+
+        // A deeper request is not satisfied and its result replaces the entry.
+        assert!(store.probe(board.hash, 3).is_none());
+        let (deeper_best, deeper_score) = search(&board, 3);
+        store.record(board.hash, 3, deeper_score, &deeper_best);
+        store.save().unwrap();
+
+        let store = LearningStore::open(&path, LearningStore::DEFAULT_MAX_ENTRIES).unwrap();
+        let entry = store
+            .probe(board.hash, 3)
+            .expect("deeper entry should be stored");
+        assert_eq!(entry.depth, 3);
+        assert_eq!(entry.best_move, deeper_best.to_uci());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn evicts_shallowest_and_skips_corrupted_lines() {
+        magic::init();
+        let path = temp_path("evict");
+        fs::write(
+            &path,
+            format!(
+                "{}\n{:016x} 5 120 e2e4\n{:016x} 1 -30 d2d4\ngarbage line\n{:016x} 3 10\n",
+                HEADER, 1, 2, 3
+            ),
+        )
+        .unwrap();
+
+        let mut store = LearningStore::open(&path, 2).unwrap();
+        assert_eq!(store.len(), 2);
+
+        let board = Board::new_game();
+        let m = board.parse_uci_move("g1f3").unwrap();
+        store.record(4, 4, 0, &m);
+        assert_eq!(store.len(), 2);
+        assert!(
+            store.get(2).is_none(),
+            "depth 1 entry should be evicted first"
+        );
+        assert!(store.probe(1, 5).is_some());
+        assert!(store.probe(4, 4).is_some());
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+/// Deepest iteration run when no depth limit is given.
+pub const MAX_DEPTH: i32 = 20;
+
+/// Deepest iteration of an infinite search, far beyond what it reaches before
+/// it is stopped.
+const INFINITE_DEPTH: i32 = 64;
+
+/// When a search must stop, as given by the UCI `go` command. The time given
+/// to a move under a clock is up to the
+/// [`TimeManager`](super::time_manager::TimeManager).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Deepest iteration to complete.
+    pub depth: Option<i32>,
+    /// Fixed time for the move.
+    pub movetime: Option<Duration>,
+    /// Time left on the clocks.
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    /// Increments added to the clocks after each move.
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    /// Moves to play until the next time control.
+    pub movestogo: Option<u32>,
+    /// No new iteration starts once this many nodes were searched.
+    pub nodes: Option<u64>,
+    /// Search until stopped, whatever the other limits.
+    pub infinite: bool,
+    /// Search on the opponent's time until the ponderhit, after which the
+    /// other limits apply.
+    pub ponder: bool,
+}
+
+impl SearchLimits {
+    /// Parses the arguments of `go`, ignoring unknown tokens and invalid values.
+    pub fn from_go_tokens(tokens: &[&str]) -> Self {
+        let mut limits = SearchLimits::default();
+        let mut i = 0;
+        while i < tokens.len() {
+            let value = tokens.get(i + 1);
+            let millis = || {
+                value
+                    .and_then(|s| s.parse().ok())
+                    .map(Duration::from_millis)
+            };
+            match tokens[i] {
+                "depth" => limits.depth = value.and_then(|s| s.parse().ok()),
+                "nodes" => limits.nodes = value.and_then(|s| s.parse().ok()),
+                "movestogo" => limits.movestogo = value.and_then(|s| s.parse().ok()),
+                "movetime" => limits.movetime = millis(),
+                "wtime" => limits.wtime = millis(),
+                "btime" => limits.btime = millis(),
+                "winc" => limits.winc = millis(),
+                "binc" => limits.binc = millis(),
+                "infinite" => limits.infinite = true,
+                "ponder" => limits.ponder = true,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            // Every keyword but `infinite` and `ponder` is followed by its value.
+            i += if matches!(tokens[i], "infinite" | "ponder") {
+                1
+            } else {
+                2
+            };
+        }
+
+        limits
+    }
+
+    /// Deepest iteration allowed.
+    pub fn max_depth(&self) -> i32 {
+        if self.infinite {
+            return INFINITE_DEPTH;
+        }
+        self.depth.unwrap_or(MAX_DEPTH)
+    }
+}
+
+/// The moves listed after `searchmoves` in the arguments of `go`, up to the
+/// next keyword. None without `searchmoves`.
+pub fn searchmoves_from_go_tokens<'a>(tokens: &[&'a str]) -> Option<Vec<&'a str>> {
+    const KEYWORDS: &[&str] = &[
+        "ponder",
+        "wtime",
+        "btime",
+        "winc",
+        "binc",
+        "movestogo",
+        "depth",
+        "nodes",
+        "mate",
+        "movetime",
+        "infinite",
+    ];
+    let start = tokens.iter().position(|&t| t == "searchmoves")? + 1;
+    Some(
+        tokens[start..]
+            .iter()
+            .take_while(|t| !KEYWORDS.contains(t))
+            .copied()
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(go: &str) -> SearchLimits {
+        SearchLimits::from_go_tokens(&go.split_whitespace().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn go_arguments_set_the_limits() {
+        let limits =
+            parse("wtime 60000 btime 30000 winc 1000 binc 2000 movestogo 20 nodes 5000 depth x");
+        assert_eq!(limits.wtime, Some(Duration::from_secs(60)));
+        assert_eq!(limits.binc, Some(Duration::from_secs(2)));
+        assert_eq!(limits.movestogo, Some(20));
+        assert_eq!(limits.nodes, Some(5000));
+        assert_eq!(limits.depth, None);
+        assert_eq!(limits.max_depth(), MAX_DEPTH);
+
+        let limits = parse("ponder infinite movetime 250");
+        assert!(limits.infinite && limits.ponder);
+        assert_eq!(limits.max_depth(), INFINITE_DEPTH);
+        assert_eq!(limits.movetime, Some(Duration::from_millis(250)));
+
+        let tokens = ["searchmoves", "e2e4", "d2d4", "depth", "6"];
+        assert_eq!(
+            searchmoves_from_go_tokens(&tokens),
+            Some(vec!["e2e4", "d2d4"])
+        );
+        assert_eq!(SearchLimits::from_go_tokens(&tokens).depth, Some(6));
+        assert_eq!(searchmoves_from_go_tokens(&["depth", "6"]), None);
+
+        assert_eq!(parse("depth 6").max_depth(), 6);
+    }
+}
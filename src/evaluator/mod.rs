@@ -1,4 +1,14 @@
+pub mod book;
+pub mod config;
+pub mod deepening;
 pub mod evaluation;
+pub mod hash_walk;
+pub mod learning;
+pub mod limits;
+pub mod polyglot;
 pub mod static_eval;
+pub mod stats;
+pub mod stop;
+pub mod time_manager;
 pub mod transposition;
 pub mod utils;
@@ -1,7 +1,7 @@
 use crate::components::{
     board::Board,
     constants,
-    pieces::{Bitboard, Color, PieceKind},
+    pieces::{Bitboard, Color, Piece, PieceKind},
 };
 
 use super::utils;
@@ -23,9 +23,22 @@ impl StaticEval {
             Color::Black => self.black += value,
         }
     }
+
+    /// Score from `side`'s point of view: > 0 means `side` is better.
+    pub fn relative_to(&self, side: Color) -> i32 {
+        match side {
+            Color::White => self.white - self.black,
+            Color::Black => self.black - self.white,
+        }
+    }
 }
 
 impl StaticEval {
+    /// Evaluates the position from the side to move's point of view, as the search needs it.
+    pub fn relative_evaluate(board: &Board) -> i32 {
+        Self::static_evaluate(board).relative_to(board.turn)
+    }
+
     pub fn static_evaluate(board: &Board) -> Self {
         let mut eval = Self::new();
 
@@ -60,6 +73,218 @@ impl StaticEval {
             }
         }
 
+        let (side, bonus) = Self::exchange_bonus(board);
+        eval.add(side, bonus);
+        if let Some((side, bonus)) = Self::mop_up_bonus(board) {
+            eval.add(side, bonus);
+        }
+
         eval
     }
+
+    /// "Trade when winning": the side ahead in material gets a bonus for every
+    /// enemy piece that is no longer on the board, proportional to its lead.
+    /// Trading pieces then looks good for the side ahead and bad for the side
+    /// behind, while pawn trades are left alone.
+    ///
+    /// The bonus is measured against the starting set of pieces and scaled by
+    /// the share of non-pawn material (both sides) already off the board, so
+    /// it is close to zero in the opening and middlegame and only grows as the
+    /// game heads into the endgame. Pawns count for the lead, as being a pawn
+    /// up is reason enough to trade, but not for the phase.
+    /// It is capped below a pawn so it never outweighs material.
+    ///
+    /// Returns the side getting the bonus and its value.
+    fn exchange_bonus(board: &Board) -> (Color, i32) {
+        let mut material = [0, 0];
+        let mut pieces = [0, 0];
+        let mut non_pawn_material = 0;
+        for (piece, bitboard) in &board.position {
+            let side = match piece.color {
+                Color::White => 0,
+                Color::Black => 1,
+            };
+            match piece.kind {
+                PieceKind::King => {}
+                PieceKind::Pawn => material[side] += bitboard.count_bits() * piece.kind.value(),
+                _ => {
+                    material[side] += bitboard.count_bits() * piece.kind.value();
+                    pieces[side] += bitboard.count_bits();
+                    non_pawn_material += bitboard.count_bits() * piece.kind.value();
+                }
+            }
+        }
+
+        let (ahead, lead, enemy_pieces) = if material[0] >= material[1] {
+            (Color::White, material[0] - material[1], pieces[1])
+        } else {
+            (Color::Black, material[1] - material[0], pieces[0])
+        };
+        let removed = (constants::STARTING_PIECES - enemy_pieces).max(0);
+        let traded = (constants::STARTING_NON_PAWN_MATERIAL - non_pawn_material).max(0);
+        let bonus = lead.min(constants::EXCHANGE_BONUS_MAX_LEAD)
+            * removed
+            * constants::EXCHANGE_BONUS_PER_PIECE
+            / PieceKind::Pawn.value()
+            * traded
+            / constants::STARTING_NON_PAWN_MATERIAL;
+
+        (ahead, bonus.min(constants::EXCHANGE_BONUS_CAP))
+    }
+
+    /// Mop-up in the basic mates: when one side is down to a bare king and the
+    /// other has a rook or a queen, the strong side gets a bonus for pushing the
+    /// lone king away from the centre and for bringing its own king close.
+    /// Material alone does not change while the mate is prepared, so without
+    /// it the search has no way to make progress.
+    ///
+    /// Returns the side getting the bonus and its value, None outside these endgames.
+    fn mop_up_bonus(board: &Board) -> Option<(Color, i32)> {
+        let pieces = |color: Color, kinds: &[PieceKind]| {
+            kinds
+                .iter()
+                .map(|&kind| board.position.get(Piece::new(color, kind)).count_bits())
+                .sum::<i32>()
+        };
+        let all = [
+            PieceKind::Pawn,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+        ];
+        let strong = [Color::White, Color::Black].into_iter().find(|&color| {
+            pieces(color.other(), &all) == 0
+                && pieces(color, &[PieceKind::Rook, PieceKind::Queen]) > 0
+        })?;
+
+        let king = |color: Color| {
+            board
+                .position
+                .get(Piece::new(color, PieceKind::King))
+                .lsb_index()
+        };
+        let (strong_king, lone_king) = (king(strong)?, king(strong.other())?);
+        let bonus = center_distance(lone_king) * constants::MOP_UP_EDGE_VALUE
+            + (7 - king_distance(strong_king, lone_king)) * constants::MOP_UP_PROXIMITY_VALUE;
+
+        Some((strong, bonus))
+    }
+}
+
+/// Manhattan distance of `square` to the four central squares (0 - 6).
+fn center_distance(square: u8) -> i32 {
+    let (file, rank) = (i32::from(square % 8), i32::from(square / 8));
+    (3 - file).max(file - 4) + (3 - rank).max(rank - 4)
+}
+
+/// Number of king moves between two squares (0 - 7).
+fn king_distance(a: u8, b: u8) -> i32 {
+    let file = (i32::from(a % 8) - i32::from(b % 8)).abs();
+    let rank = (i32::from(a / 8) - i32::from(b / 8)).abs();
+    file.max(rank)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::magic;
+
+    fn exchange_bonus_for_white(fen: &str) -> i32 {
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        let (side, bonus) = StaticEval::exchange_bonus(&board);
+        match side {
+            Color::White => bonus,
+            Color::Black => -bonus,
+        }
+    }
+
+    #[test]
+    fn trading_queens_is_good_when_ahead_and_bad_when_behind() {
+        magic::init();
+        // White is up a knight (+3) in both pairs; the only difference is the queens.
+        let queens_on = "r1bqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let queens_off = "r1b1kb1r/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1";
+        assert!(exchange_bonus_for_white(queens_off) > exchange_bonus_for_white(queens_on));
+        assert!(exchange_bonus_for_white(queens_on) > 0);
+
+        // Colors swapped: White is down a knight and wants to keep the queens.
+        let queens_on = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKB1R w KQkq - 0 1";
+        let queens_off = "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/R1B1KB1R w KQkq - 0 1";
+        assert!(exchange_bonus_for_white(queens_off) < exchange_bonus_for_white(queens_on));
+
+        // Pawn trades do not count, and nothing applies to an even position.
+        let fewer_pawns = "r1bqkb1r/pppppp2/8/8/8/8/PPPPPP2/RNBQKBNR w KQkq - 0 1";
+        let more_pawns = "r1bqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(
+            exchange_bonus_for_white(fewer_pawns),
+            exchange_bonus_for_white(more_pawns)
+        );
+        assert_eq!(exchange_bonus_for_white("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), 0);
+    }
+
+    #[test]
+    fn exchange_bonus_grows_towards_the_endgame() {
+        magic::init();
+        // White is up a knight in both pairs and trades queens; the endgame
+        // pair has the rooks and minor pieces already off the board.
+        let middlegame_queens_on = "r1bqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let middlegame_queens_off = "r1b1kb1r/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1";
+        let endgame_queens_on = "3qk3/pppppppp/8/8/8/8/PPPPPPPP/1N1QK3 w - - 0 1";
+        let endgame_queens_off = "4k3/pppppppp/8/8/8/8/PPPPPPPP/1N2K3 w - - 0 1";
+
+        let middlegame = exchange_bonus_for_white(middlegame_queens_on);
+        let endgame = exchange_bonus_for_white(endgame_queens_on);
+        assert!(middlegame < endgame);
+
+        let middlegame_trade = exchange_bonus_for_white(middlegame_queens_off) - middlegame;
+        let endgame_trade = exchange_bonus_for_white(endgame_queens_off) - endgame;
+        assert!(middlegame_trade > 0);
+        assert!(middlegame_trade < endgame_trade);
+    }
+
+    #[test]
+    fn exchange_bonus_is_capped() {
+        magic::init();
+        assert_eq!(
+            exchange_bonus_for_white("4k3/8/8/8/8/8/8/QR2K3 w - - 0 1"),
+            constants::EXCHANGE_BONUS_CAP
+        );
+    }
+
+    fn mop_up_bonus_for_white(fen: &str) -> Option<i32> {
+        let board = Board::from_forsyth_edwards(fen).unwrap();
+        StaticEval::mop_up_bonus(&board).map(|(side, bonus)| match side {
+            Color::White => bonus,
+            Color::Black => -bonus,
+        })
+    }
+
+    #[test]
+    fn mop_up_drives_the_lone_king_to_the_edge() {
+        magic::init();
+        let center = mop_up_bonus_for_white("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let edge = mop_up_bonus_for_white("3k4/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let corner = mop_up_bonus_for_white("k7/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(0 < center && center < edge && edge < corner);
+        // The attacking king is rewarded for coming close.
+        let close = mop_up_bonus_for_white("3k4/8/3K4/8/8/8/8/R7 w - - 0 1").unwrap();
+        assert!(close > edge);
+        // Black can be the strong side too.
+        assert!(mop_up_bonus_for_white("q3k3/8/8/8/8/8/8/K7 w - - 0 1").unwrap() < 0);
+
+        // Only against a bare king, and only with a rook or a queen.
+        assert_eq!(
+            mop_up_bonus_for_white("3k4/p7/8/8/8/8/8/R3K3 w - - 0 1"),
+            None
+        );
+        assert_eq!(
+            mop_up_bonus_for_white("3k4/8/8/8/8/8/8/B3K3 w - - 0 1"),
+            None
+        );
+        assert_eq!(
+            mop_up_bonus_for_white("3k4/8/8/8/8/8/8/4K3 w - - 0 1"),
+            None
+        );
+    }
 }
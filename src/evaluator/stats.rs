@@ -0,0 +1,106 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Whether the detailed counters are kept. The `minimal-stats` feature compiles
+/// them out and leaves only `nodes`, `qnodes`, `seldepth` and `elapsed`.
+pub const DETAILED: bool = cfg!(not(feature = "minimal-stats"));
+
+/// Increments a detailed counter; a no-op with the `minimal-stats` feature.
+#[inline(always)]
+pub fn count(counter: &mut u64) {
+    if DETAILED {
+        *counter += 1;
+    }
+}
+
+/// Counters collected while searching, used to see how often the
+/// search heuristics fire.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Nodes visited by the main search and the quiescence search.
+    pub nodes: u64,
+    /// Nodes visited by the quiescence search (included in `nodes`).
+    pub qnodes: u64,
+    /// Deepest ply reached, quiescence search included.
+    pub seldepth: i32,
+    /// Time the search took.
+    pub elapsed: Duration,
+    /// Transposition table lookups of the main search.
+    pub tt_probes: u64,
+    /// Lookups that found an entry searched deep enough to use its score.
+    pub tt_hits: u64,
+    /// Main search nodes cut off by a move reaching beta.
+    pub beta_cutoffs: u64,
+    /// Cutoffs by the first move searched: the higher the share, the better the ordering.
+    pub first_move_cutoffs: u64,
+    /// Nodes where internal iterative deepening ran a reduced search to find a move to try first.
+    pub iid_searches: u64,
+    /// Null-window searches that landed inside the window and had to be repeated with the full window.
+    pub pvs_researches: u64,
+    /// Quiescence captures skipped by delta pruning.
+    pub delta_prunes: u64,
+    /// Nodes searched one ply deeper because they have a single legal move.
+    pub single_reply_extensions: u64,
+    /// Reduced searches run to find out whether the TT move is singular.
+    pub singular_searches: u64,
+    /// TT moves searched one ply deeper because they were singular.
+    pub singular_extensions: u64,
+}
+
+impl SearchStats {
+    /// Nodes per second over `elapsed`, 0 before any time has passed.
+    pub fn nps(&self) -> u64 {
+        let micros = self.elapsed.as_micros();
+        if micros == 0 {
+            return 0;
+        }
+        (self.nodes as u128 * 1_000_000 / micros) as u64
+    }
+
+    /// Adds the counters of another (per-thread) accumulator into this one.
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.qnodes += other.qnodes;
+        self.seldepth = self.seldepth.max(other.seldepth);
+        self.elapsed = self.elapsed.max(other.elapsed);
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+        self.beta_cutoffs += other.beta_cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.iid_searches += other.iid_searches;
+        self.pvs_researches += other.pvs_researches;
+        self.delta_prunes += other.delta_prunes;
+        self.single_reply_extensions += other.single_reply_extensions;
+        self.singular_searches += other.singular_searches;
+        self.singular_extensions += other.singular_extensions;
+    }
+}
+
+/// The counters as "name value" pairs; the detailed ones only when they are kept.
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "nodes {} qnodes {} seldepth {}",
+            self.nodes, self.qnodes, self.seldepth
+        )?;
+        if DETAILED {
+            write!(
+                f,
+                " tt hits {}/{} cutoffs {} first move {} iid searches {} pvs re-searches {} delta prunes {} single reply extensions {} singular extensions {}/{}",
+                self.tt_hits,
+                self.tt_probes,
+                self.beta_cutoffs,
+                self.first_move_cutoffs,
+                self.iid_searches,
+                self.pvs_researches,
+                self.delta_prunes,
+                self.single_reply_extensions,
+                self.singular_extensions,
+                self.singular_searches
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::{
+    Arc, OnceLock,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::Instant;
+
+/// Asks a running search to stop, from any thread. Clones share the same flag.
+///
+/// The search checks it every thousand nodes or so, once its first iteration
+/// completed, and then returns the best move of the last completed iteration.
+/// A handle stays stopped: use a new one for each search.
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Turns a ponder search into the real one, from any thread: the opponent
+/// played the expected move. Clones share the same state.
+///
+/// The time budget of the search runs from the hit on, and the search goes on
+/// from the iteration it was at. Only the first hit counts: use a new handle
+/// for each search.
+#[derive(Debug, Clone, Default)]
+pub struct PonderHandle(Arc<OnceLock<Instant>>);
+
+impl PonderHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hit(&self) {
+        let _ = self.0.set(Instant::now());
+    }
+
+    /// When the ponderhit came, None while still pondering.
+    pub fn hit_at(&self) -> Option<Instant> {
+        self.0.get().copied()
+    }
+}
@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use crate::components::pieces::Color;
+use crate::moves::move_type::Move;
+
+use super::config::SearchConfig;
+use super::limits::SearchLimits;
+
+/// Least time aimed at for a move under a clock, as long as the hard cap allows it.
+const MIN_MOVE_TIME: Duration = Duration::from_millis(100);
+
+/// Clock assumed when `go` gives neither a clock, a depth nor a nodes limit.
+const DEFAULT_CLOCK: Duration = Duration::from_secs(30);
+
+/// A move never gets more than this share of the clock left, so the engine
+/// cannot lose on time however the search goes.
+const HARD_CAP_DIVISOR: u32 = 5;
+
+/// The hard limit is at most this many times the time aimed at.
+const MAX_OPTIMUM_FACTOR: u32 = 5;
+
+/// Percent of the time aimed at that is saved per iteration the best move
+/// did not change, up to `MAX_STABLE_ITERATIONS`.
+const STABILITY_STEP: u32 = 10;
+const MAX_STABLE_ITERATIONS: u32 = 5;
+
+/// A score this much below the previous iteration's (1 pawn = 1000) is a fail low:
+/// the position turned out worse than thought and deserves more time.
+const FAIL_LOW_MARGIN: i32 = 300;
+/// Percent of the time aimed at added after a fail low.
+const FAIL_LOW_EXTENSION: u32 = 100;
+
+/// Time allocation of a search under a clock.
+///
+/// A move aims at `optimum`: the clock divided by the moves expected until
+/// the end of the game, plus half the increment. After each iteration,
+/// [`TimeManager::update`] shortens that time while the best move stays the
+/// same and lengthens it when the score drops. Nothing ever runs past
+/// `maximum`, a small share of the clock left minus the move overhead.
+///
+/// A fixed `movetime` is used as it is, without adapting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeManager {
+    optimum: Duration,
+    maximum: Duration,
+    adaptive: bool,
+    best_move: Option<Move>,
+    stable_iterations: u32,
+    score: Option<i32>,
+    fail_low: bool,
+}
+
+impl TimeManager {
+    /// The allocation for the side to move `turn`, None if no clock limits the
+    /// search: a depth or nodes limit alone, or an infinite search.
+    pub fn new(limits: &SearchLimits, turn: Color, config: &SearchConfig) -> Option<Self> {
+        if limits.infinite {
+            return None;
+        }
+        let overhead = Duration::from_millis(config.move_overhead.max(0) as u64);
+        if let Some(movetime) = limits.movetime {
+            let time = movetime.saturating_sub(overhead);
+            return Some(Self::with_limits(time, time, false));
+        }
+
+        let (time, inc) = match turn {
+            Color::White => (limits.wtime, limits.winc),
+            Color::Black => (limits.btime, limits.binc),
+        };
+        let time = match (time, limits.depth, limits.nodes) {
+            (Some(time), _, _) => time,
+            (None, None, None) => DEFAULT_CLOCK,
+            (None, _, _) => return None,
+        };
+        let moves_left = limits
+            .movestogo
+            .unwrap_or(u32::MAX)
+            .min(config.expected_moves.max(1) as u32)
+            .max(1);
+
+        let optimum = (time / moves_left).max(MIN_MOVE_TIME) + inc.unwrap_or_default() / 2;
+        let maximum =
+            (optimum * MAX_OPTIMUM_FACTOR).min(time.saturating_sub(overhead) / HARD_CAP_DIVISOR);
+
+        Some(Self::with_limits(optimum.min(maximum), maximum, true))
+    }
+
+    fn with_limits(optimum: Duration, maximum: Duration, adaptive: bool) -> Self {
+        Self {
+            optimum,
+            maximum,
+            adaptive,
+            best_move: None,
+            stable_iterations: 0,
+            score: None,
+            fail_low: false,
+        }
+    }
+
+    /// Time aimed at before any iteration completed.
+    pub fn optimum(&self) -> Duration {
+        self.optimum
+    }
+
+    /// Time the search never runs past.
+    pub fn maximum(&self) -> Duration {
+        self.maximum
+    }
+
+    /// Records the result of a completed iteration.
+    pub fn update(&mut self, best_move: Move, score: i32) {
+        if self.best_move == Some(best_move) {
+            self.stable_iterations += 1;
+        } else {
+            self.stable_iterations = 0;
+        }
+        self.fail_low = self
+            .score
+            .is_some_and(|previous| score < previous - FAIL_LOW_MARGIN);
+        self.best_move = Some(best_move);
+        self.score = Some(score);
+    }
+
+    /// Time aimed at after the iterations so far.
+    pub fn soft_limit(&self) -> Duration {
+        if !self.adaptive {
+            return self.optimum;
+        }
+        let mut percent = 100 - STABILITY_STEP * self.stable_iterations.min(MAX_STABLE_ITERATIONS);
+        if self.fail_low {
+            percent += FAIL_LOW_EXTENSION;
+        }
+
+        (self.optimum * percent / 100).min(self.maximum)
+    }
+
+    /// True if no iteration should start once `elapsed` passed: it would
+    /// hardly complete within the time aimed at.
+    pub fn should_stop(&self, elapsed: Duration) -> bool {
+        elapsed * 2 > self.soft_limit()
+    }
+
+    /// Time after which the running iteration is dropped. The next iteration
+    /// usually takes a few times longer than the last one, so it gets some
+    /// slack past the time aimed at, never past the hard limit.
+    pub fn iteration_limit(&self) -> Duration {
+        if !self.adaptive {
+            return self.maximum;
+        }
+        (self.soft_limit() * 2).min(self.maximum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::board::Board;
+    use crate::moves::magic;
+
+    fn time_manager(go: &str, turn: Color) -> Option<TimeManager> {
+        let tokens: Vec<_> = go.split_whitespace().collect();
+        TimeManager::new(
+            &SearchLimits::from_go_tokens(&tokens),
+            turn,
+            &SearchConfig::default(),
+        )
+    }
+
+    #[test]
+    fn the_clock_is_shared_among_the_moves_left() {
+        let go = "wtime 60000 btime 30000 winc 1000 binc 2000";
+        // A thirtieth of the clock plus half the increment.
+        let white = time_manager(go, Color::White).unwrap();
+        assert_eq!(white.optimum(), Duration::from_millis(2500));
+        assert_eq!(white.maximum(), Duration::from_millis(11_994));
+        let black = time_manager(go, Color::Black).unwrap();
+        assert_eq!(black.optimum(), Duration::from_millis(2000));
+
+        let last_moves = time_manager("wtime 60000 movestogo 4", Color::White).unwrap();
+        assert_eq!(last_moves.optimum(), Duration::from_millis(11_994));
+
+        let fixed = time_manager("movetime 250", Color::White).unwrap();
+        assert_eq!(fixed.optimum(), Duration::from_millis(220));
+        assert_eq!(fixed.iteration_limit(), fixed.optimum());
+        assert_eq!(
+            time_manager("", Color::White).unwrap().optimum(),
+            Duration::from_secs(1)
+        );
+
+        assert_eq!(time_manager("depth 6", Color::White), None);
+        assert_eq!(time_manager("nodes 1000", Color::White), None);
+        assert_eq!(time_manager("infinite wtime 1000", Color::White), None);
+    }
+
+    #[test]
+    fn the_hard_cap_stays_well_under_the_clock_left() {
+        for wtime in [10, 40, 200, 1000, 5000, 600_000] {
+            let go = format!("wtime {} winc 100000 movestogo 1", wtime);
+            let time = time_manager(&go, Color::White).unwrap();
+            let clock = Duration::from_millis(wtime);
+            assert!(time.maximum() <= clock / 5, "{}", wtime);
+            assert!(time.optimum() <= time.maximum(), "{}", wtime);
+            assert!(time.iteration_limit() <= time.maximum(), "{}", wtime);
+        }
+    }
+
+    #[test]
+    fn a_stable_best_move_stops_the_search_early() {
+        magic::init();
+        let board = Board::new_game();
+        let e4 = board.parse_uci_move("e2e4").unwrap();
+        let d4 = board.parse_uci_move("d2d4").unwrap();
+        let mut time = time_manager("wtime 60000", Color::White).unwrap();
+        let optimum = time.optimum();
+        // Simulated clock: 40% of the time aimed at is gone.
+        let elapsed = optimum * 4 / 10;
+
+        time.update(e4, 100);
+        assert!(!time.should_stop(elapsed));
+        for _ in 0..3 {
+            time.update(e4, 100);
+        }
+        assert_eq!(time.soft_limit(), optimum * 7 / 10);
+        assert!(time.should_stop(elapsed));
+
+        // A new best move starts counting again.
+        time.update(d4, 100);
+        assert_eq!(time.soft_limit(), optimum);
+        assert!(!time.should_stop(elapsed));
+    }
+
+    #[test]
+    fn a_fail_low_extends_the_time() {
+        magic::init();
+        let board = Board::new_game();
+        let e4 = board.parse_uci_move("e2e4").unwrap();
+        let mut time = time_manager("wtime 60000", Color::White).unwrap();
+        let optimum = time.optimum();
+
+        time.update(e4, 100);
+        assert!(time.should_stop(optimum * 6 / 10));
+        time.update(e4, 100 - FAIL_LOW_MARGIN - 1);
+        assert_eq!(time.soft_limit(), optimum * 19 / 10);
+        assert!(!time.should_stop(optimum * 6 / 10));
+        assert!(time.iteration_limit() <= time.maximum());
+
+        // The extension ends once the score stops falling.
+        time.update(e4, 100 - FAIL_LOW_MARGIN - 1);
+        assert_eq!(time.soft_limit(), optimum * 8 / 10);
+    }
+}
@@ -1,7 +1,9 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use crate::moves::packed::PackedMove;
+
 /// Describes the reliability of a stored score relative to the true minimax value.
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Bound {
     /// The stored score is the exact minimax value.
     Exact,
@@ -11,9 +13,23 @@ pub enum Bound {
     Upper,
 }
 
+/// Depth stored for terminal positions (mate / stalemate): their score is exact at any depth.
+/// The depth is packed into 14 bits, so this is the deepest storable value.
+pub const TERMINAL_DEPTH: i32 = (1 << 13) - 1;
+
 pub struct ProbeResult {
     pub score: i32,
     pub bound: Bound,
+    pub best_move: Option<PackedMove>,
+}
+
+/// A stored entry as a whole, whatever its depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub depth: i32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<PackedMove>,
 }
 
 /// One slot in the transposition table.
@@ -27,8 +43,8 @@ pub struct ProbeResult {
 /// Data layout (64 bits):
 ///   bits 0-1 ->  Bound 2 bits for 3 variants
 ///   bits 2-33 -> score reinterpreted as u32
-///   bits 34-49 -> depth reinterpreted as u16
-///   bits 50-63 -> unused
+///   bits 34-47 -> depth as a 14 bits two's complement
+///   bits 48-63 -> best move (see `PackedMove`), 0 if none
 #[derive(Debug, Default)]
 struct TtSlot {
     key: AtomicU64,
@@ -44,28 +60,36 @@ impl TtSlot {
     }
 }
 
-fn compress_to_u64(depth: i32, score: i32, bound: Bound) -> u64 {
+const DEPTH_MASK: u64 = (1 << 14) - 1;
+
+fn compress_to_u64(depth: i32, score: i32, bound: Bound, best_move: Option<PackedMove>) -> u64 {
     let bound_bits = match bound {
         Bound::Exact => 0u64,
         Bound::Lower => 1u64,
         Bound::Upper => 2u64,
     };
     let score_bits = (score as u32) as u64;
-    let depth_bits = (depth as u16) as u64;
+    let depth_bits = (depth as u64) & DEPTH_MASK;
+    let move_bits = best_move.map_or(0, |m| m.bits() as u64);
 
-    bound_bits | (score_bits << 2) | (depth_bits << 34)
+    bound_bits | (score_bits << 2) | (depth_bits << 34) | (move_bits << 48)
 }
 
-fn unpack(data: u64) -> (i32, i32, Bound) {
+fn unpack(data: u64) -> (i32, i32, Bound, Option<PackedMove>) {
     let bound = match data & 0b11 {
         0 => Bound::Exact,
         1 => Bound::Lower,
         _ => Bound::Upper,
     };
     let score = (data >> 2) as u32 as i32;
-    let depth = (data >> 34) as u16 as i16 as i32;
+    // Shift the 14 depth bits to the top of an i16 and back to extend the sign.
+    let depth = ((((data >> 34) & DEPTH_MASK) as u16) << 2) as i16 >> 2;
+    let best_move = match (data >> 48) as u16 {
+        0 => None,
+        bits => Some(PackedMove::from_bits(bits)),
+    };
 
-    (depth, score, bound)
+    (depth as i32, score, bound, best_move)
 }
 
 /// A lockless transposition table that can be shared across threads without
@@ -97,6 +121,37 @@ impl TranspositionTable {
     /// Returns the stored result if the entry matches `hash` and was computed
     /// at least as deep as the requested `depth`.
     pub fn probe(&self, hash: u64, depth: i32) -> Option<ProbeResult> {
+        let (entry_depth, score, bound, best_move) = self.read(hash)?;
+        if entry_depth < depth {
+            return None;
+        }
+
+        Some(ProbeResult {
+            score,
+            bound,
+            best_move,
+        })
+    }
+
+    /// Returns the best move stored for `hash` at any depth, used for move ordering.
+    pub fn probe_move(&self, hash: u64) -> Option<PackedMove> {
+        self.read(hash)?.3
+    }
+
+    /// Returns the entry stored for `hash` at any depth, used to inspect the table
+    /// after a search.
+    pub fn entry(&self, hash: u64) -> Option<TtEntry> {
+        let (depth, score, bound, best_move) = self.read(hash)?;
+
+        Some(TtEntry {
+            depth,
+            score,
+            bound,
+            best_move,
+        })
+    }
+
+    fn read(&self, hash: u64) -> Option<(i32, i32, Bound, Option<PackedMove>)> {
         let slot = &self.table[hash as usize & self.mask];
         let key = slot.key.load(Ordering::Relaxed);
         let data = slot.data.load(Ordering::Relaxed);
@@ -104,35 +159,145 @@ impl TranspositionTable {
         if key ^ data != hash {
             return None;
         }
-        let (entry_depth, score, bound) = unpack(data);
-        if entry_depth < depth {
-            return None;
-        }
 
-        Some(ProbeResult { score, bound })
+        Some(unpack(data))
     }
 
     /// Stores a result.  Uses depth-preferred replacement: an existing entry for
     /// the same hash is only overwritten if the new depth is >= the stored depth.
+    /// A result without a best move keeps the move already stored for the same hash.
     /// The store itself is lock-free: data is written before key so a concurrent
     /// reader will fail the XOR check during the brief inconsistent window.
-    pub fn store(&self, hash: u64, depth: i32, score: i32, bound: Bound) {
+    pub fn store(
+        &self,
+        hash: u64,
+        depth: i32,
+        score: i32,
+        bound: Bound,
+        mut best_move: Option<PackedMove>,
+    ) {
         let slot = &self.table[hash as usize & self.mask];
 
         // Depth-preferred replacement: only overwrite if new depth is at least as deep.
         let existing_key = slot.key.load(Ordering::Relaxed);
         let existing_data = slot.data.load(Ordering::Relaxed);
         if existing_key ^ existing_data == hash {
-            let (existing_depth, _, _) = unpack(existing_data);
+            let (existing_depth, _, _, existing_move) = unpack(existing_data);
             if depth < existing_depth {
                 return;
             }
+            best_move = best_move.or(existing_move);
         }
 
-        let data = compress_to_u64(depth, score, bound);
+        let data = compress_to_u64(depth, score, bound, best_move);
         // Write data before key so a concurrent reader sees key ^ data != hash
         // during the brief window between the two stores.
         slot.data.store(data, Ordering::Relaxed);
         slot.key.store(hash ^ data, Ordering::Relaxed);
     }
+
+    /// Hints the CPU to start loading the slot of `hash` into the cache.
+    /// Called right after a move is made, so the probe of the child position
+    /// does not wait on memory. Has no effect on the table contents.
+    #[inline]
+    pub fn prefetch(&self, hash: u64) {
+        let slot = &self.table[hash as usize & self.mask];
+        prefetch::read(slot as *const TtSlot as *const i8);
+    }
+
+    /// Empties every slot, e.g. when a new game starts.
+    pub fn clear(&self) {
+        for slot in &self.table {
+            slot.data.store(0, Ordering::Relaxed);
+            slot.key.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+mod prefetch {
+    #[cfg(target_arch = "x86_64")]
+    #[inline]
+    pub fn read(ptr: *const i8) {
+        use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
+        // SAFETY: prefetching is only a hint; it never faults, even on an invalid address.
+        unsafe { _mm_prefetch::<_MM_HINT_T0>(ptr) }
+    }
+
+    /// No prefetch instruction is used on other architectures.
+    /// Also compiled in tests so the fallback is checked on every target.
+    #[cfg(any(not(target_arch = "x86_64"), test))]
+    #[inline]
+    pub fn read_fallback(_ptr: *const i8) {}
+
+    #[cfg(not(target_arch = "x86_64"))]
+    pub use read_fallback as read;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::board::Board;
+    use crate::moves::magic;
+
+    #[test]
+    fn best_move_round_trips_and_survives_stores_without_one() {
+        magic::init();
+        let board = Board::from_forsyth_edwards("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let promotion = board.parse_uci_move("b7b8n").unwrap();
+        let queen_promotion = board.parse_uci_move("b7b8q").unwrap();
+        let castle = board.parse_uci_move("e1g1").unwrap();
+
+        let tt = TranspositionTable::new();
+        tt.store(
+            board.hash,
+            3,
+            -25,
+            Bound::Lower,
+            Some(PackedMove::new(&promotion)),
+        );
+        tt.store(board.hash, 4, 40, Bound::Exact, None);
+
+        let result = tt.probe(board.hash, 4).unwrap();
+        assert_eq!(result.score, 40);
+        let stored = result.best_move.expect("move should be kept");
+        assert!(stored.matches(&promotion));
+        assert!(!stored.matches(&queen_promotion));
+        assert!(!stored.matches(&castle));
+        assert_eq!(tt.probe_move(board.hash), Some(stored));
+        assert!(PackedMove::new(&castle).matches(&castle));
+    }
+
+    #[test]
+    fn depth_and_move_fields_do_not_overlap() {
+        magic::init();
+        // A castle sets both flag bits, the highest ones of the entry.
+        let board = Board::from_forsyth_edwards("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let castle = PackedMove::new(&board.parse_uci_move("e1c1").unwrap());
+
+        for depth in [-5, 0, 1, 63, TERMINAL_DEPTH] {
+            for score in [i32::MIN + 1, -1, 0, i32::MAX - 1] {
+                let data = compress_to_u64(depth, score, Bound::Upper, Some(castle));
+                assert_eq!(
+                    unpack(data),
+                    (depth, score, Bound::Upper, Some(castle)),
+                    "depth {} score {}",
+                    depth,
+                    score
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn prefetch_leaves_the_table_unchanged() {
+        let tt = TranspositionTable::new();
+        tt.store(42, 3, 100, Bound::Exact, None);
+        for hash in [7, 42, u64::MAX] {
+            tt.prefetch(hash);
+        }
+        prefetch::read_fallback(&tt as *const TranspositionTable as *const i8);
+
+        assert_eq!(tt.probe(42, 3).map(|r| r.score), Some(100));
+        assert!(tt.entry(7).is_none());
+    }
 }
@@ -31,9 +31,24 @@ pub fn attacked_squares_score(
     score
 }
 
+/// Piece-square delta of a quiet move, read from the same tables the static eval uses.
+/// > 0 it means the piece is improving its square. < 0 the piece is going in a worse position.
+///
+/// Cheap on purpose: rating by the squares attacked from `to` would regenerate
+/// the piece's moves for every candidate.
+fn quiet_move_psqt_delta(piece: Piece, from: u8, to: u8) -> i32 {
+    let table = if piece.kind == PieceKind::King {
+        &constants::KING_MIDDLEGAME_TABLE
+    } else {
+        &constants::SQUARES_VALUE
+    };
+
+    table[(63 - to) as usize] - table[(63 - from) as usize]
+}
+
 fn inner_move_score_no_captures(m: &Move, board_position: &BBPosition) -> i32 {
     match m.action {
-        MoveKind::Castle(_) => constants::CASTLING_VALUE,
+        MoveKind::Castle { .. } => constants::CASTLING_VALUE,
         MoveKind::EnPassant { to, .. } => {
             if board_position.square_is_defended_by(to, m.piece.color.other()) {
                 0
@@ -41,22 +56,14 @@ fn inner_move_score_no_captures(m: &Move, board_position: &BBPosition) -> i32 {
                 PieceKind::Pawn.value()
             }
         }
-        MoveKind::Standard { from, to, .. } => {
-            let attacked_before =
-                attacked_squares_score(board_position, m.piece, Bitboard::new(1 << from));
-            let attacked_after =
-                attacked_squares_score(board_position, m.piece, Bitboard::new(1 << to));
-
-            // > 0 it means the position is improving. < 0 the piece is going in a worse position
-            attacked_after - attacked_before
-        }
+        MoveKind::Standard { from, to, .. } => quiet_move_psqt_delta(m.piece, from, to),
         MoveKind::Promote { .. } => constants::PROMOTION_VALUE,
     }
 }
 
 pub fn move_score_with_mvv_lva(m: &Move, board_position: &BBPosition) -> i32 {
     match m.action {
-        MoveKind::Castle(_) => constants::CASTLING_VALUE,
+        MoveKind::Castle { .. } => constants::CASTLING_VALUE,
         // En passant always captures a pawn of equal value (pawn for pawn).
         // The target square is empty, but it can still be defended by another piece.
         MoveKind::EnPassant { to, .. } => {
@@ -1,14 +1,22 @@
 use std::io::{self, BufRead, Write};
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
 use std::thread;
-use std::time::{Duration, Instant};
 
-use corman::components::{
-    board::Board,
-    castle::CastleSide,
-    pieces::{Color, PieceKind},
+use corman::components::{board::Board, pieces::Color};
+use corman::evaluator::{
+    book::Book,
+    config::{self, SearchConfig},
+    deepening::{SearchMessage, SearchResult},
+    evaluation,
+    hash_walk::HashWalk,
+    learning::LearningStore,
+    limits::{self, SearchLimits},
+    stats::SearchStats,
+    stop::{PonderHandle, StopHandle},
+    transposition::TranspositionTable,
 };
-use corman::moves::move_type::{Move, MoveKind, Scenario};
+use corman::moves::move_type::{Move, Scenario};
 
 const ENGINE_NAME: &str = "corman"; // my cats: Cornelia and Norman
 const ENGINE_AUTHOR: &str = "Damiano Scarpellini";
@@ -22,195 +30,123 @@ macro_rules! uci_send {
 }
 
 // ---------------------------------------------------------------------------
-// Square index <-> UCI notation
-// ---------------------------------------------------------------------------
-
-/// Square index (0 - 63) -> UCI square string (e.g. 3 -> e1).
-fn sq_to_uci(sq: u8) -> String {
-    let file = 7 - (sq % 8);
-    let rank = sq / 8;
-
-    format!("{}{}", (b'a' + file) as char, (b'1' + rank) as char)
-}
-
-/// UCI square string -> square index (e.g. "e1" → 3). Returns None on invalid input.
-fn uci_to_sq(s: &str) -> Option<u8> {
-    let b = s.as_bytes();
-    if b.len() < 2 {
-        return None;
-    }
-    let file = b[0].checked_sub(b'a').filter(|&f| f < 8)?;
-    let rank = b[1].checked_sub(b'1').filter(|&r| r < 8)?;
-
-    Some(rank * 8 + (7 - file))
-}
-
-// ---------------------------------------------------------------------------
-// Move <-> UCI notation
+// Search helpers
 // ---------------------------------------------------------------------------
 
-/// Converts a Move to its UCI string (e.g. "e2e4", "e7e8q", "e1g1").
-fn move_to_uci(m: &Move) -> String {
-    match &m.action {
-        MoveKind::Standard { from, to, .. } | MoveKind::EnPassant { from, to } => {
-            format!("{}{}", sq_to_uci(*from), sq_to_uci(*to))
-        }
-        MoveKind::Promote {
-            from, to, to_piece, ..
-        } => {
-            let promo = match to_piece {
-                PieceKind::Queen => 'q',
-                PieceKind::Rook => 'r',
-                PieceKind::Bishop => 'b',
-                PieceKind::Knight => 'n',
-                _ => 'q',
-            };
-            format!("{}{}{}", sq_to_uci(*from), sq_to_uci(*to), promo)
-        }
-        MoveKind::Castle(side) => match (m.piece.color, side) {
-            (Color::White, CastleSide::King) => "e1g1",
-            (Color::White, CastleSide::Queen) => "e1c1",
-            (Color::Black, CastleSide::King) => "e8g8",
-            (Color::Black, CastleSide::Queen) => "e8c8",
-        }
-        .to_string(),
-    }
+/// The handles reaching a running search from the command reader.
+#[derive(Debug, Clone, Default)]
+struct SearchHandles {
+    stop: StopHandle,
+    ponder: PonderHandle,
 }
 
-/// Expected from/to square indices for each castling move (used when matching
-/// a UCI string like "e1g1" back to a Castle move).
-fn castle_squares(color: Color, side: &CastleSide) -> (u8, u8) {
-    match (color, side) {
-        (Color::White, CastleSide::King) => (3, 1),    // e1 -> g1
-        (Color::White, CastleSide::Queen) => (3, 5),   // e1 -> c1
-        (Color::Black, CastleSide::King) => (59, 57),  // e8 -> g8
-        (Color::Black, CastleSide::Queen) => (59, 61), // e8 -> c8
+/// The scenario searched by `go`: `board` with `history`, the hashes of the
+/// positions played before it, reached by `handles` and restricted to the
+/// searchmoves of the `go` arguments if any. None, once reported, if none of
+/// the searchmoves is legal.
+fn go_scenario(
+    board: &Board,
+    history: &[u64],
+    config: SearchConfig,
+    tokens: &[&str],
+    handles: SearchHandles,
+) -> Option<Scenario> {
+    let scenario = Scenario::with_config(board.clone(), config)
+        .with_history(history.to_vec())
+        .with_stop_handle(handles.stop)
+        .with_ponder_handle(handles.ponder);
+
+    match limits::searchmoves_from_go_tokens(tokens) {
+        Some(moves) => match scenario.with_uci_searchmoves(&moves) {
+            Ok(scenario) => Some(scenario),
+            Err(e) => {
+                uci_send!("info string {:#}", e);
+                None
+            }
+        },
+        None => Some(scenario),
     }
 }
 
-/// Parses a UCI move string into a legal Move for the given board position.
-/// Returns None if the move string is malformed or the move is not legal.
-fn parse_uci_move(board: &Board, uci: &str) -> Option<Move> {
-    if uci.len() < 4 {
-        return None;
-    }
-    let from = uci_to_sq(&uci[0..2])?;
-    let to = uci_to_sq(&uci[2..4])?;
-    let promo = uci.as_bytes().get(4).and_then(|&b| match b {
-        b'q' => Some(PieceKind::Queen),
-        b'r' => Some(PieceKind::Rook),
-        b'b' => Some(PieceKind::Bishop),
-        b'n' => Some(PieceKind::Knight),
-        _ => None,
+/// Iterative-deepening search within `limits` or until stopped, sending an `info` line for each
+/// completed depth. Returns the best move of the last completed depth with that
+/// depth and its score, or None if there are no legal moves.
+///
+/// A `learned` result (move, depth, score) from a previous session is used as the
+/// starting point: its depth is not searched again.
+fn iterative_deepening(
+    mut scenario: Scenario,
+    tt: &TranspositionTable,
+    limits: &SearchLimits,
+    learned: Option<(Move, i32, i32)>,
+) -> Option<(Move, i32, i32)> {
+    let previous = learned.map(|(best_move, depth, score)| SearchResult {
+        best_move,
+        depth,
+        score,
+        stats: SearchStats::default(),
+        nodes: 0,
     });
 
-    let moves = board.generate_moves(false);
-    moves.list[..moves.len()]
-        .iter()
-        .find(|rm| {
-            let m = &rm.piece_move;
-            match &m.action {
-                MoveKind::Standard { from: f, to: t, .. } => {
-                    *f == from && *t == to && promo.is_none()
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        scope.spawn(move || scenario.iterative_deepening(limits, tt, previous, Some(tx)));
+
+        let mut depth = learned.map_or(0, |(_, depth, _)| depth);
+        // The search closes the channel after Finished.
+        for message in rx {
+            match message {
+                SearchMessage::Iteration {
+                    depth: iteration_depth,
+                    seldepth,
+                    score,
+                    pv,
+                    nodes,
+                    time,
+                } => {
+                    depth = iteration_depth;
+                    let nps = nodes as u128 * 1000 / time.as_millis().max(1);
+                    let pv: Vec<_> = pv.iter().map(Move::to_uci).collect();
+                    uci_send!(
+                        "info depth {} seldepth {} score {} nodes {} nps {} time {} pv {}",
+                        depth,
+                        seldepth,
+                        evaluation::uci_score(score),
+                        nodes,
+                        nps,
+                        time.as_millis(),
+                        pv.join(" ")
+                    );
                 }
-                MoveKind::EnPassant { from: f, to: t } => *f == from && *t == to,
-                MoveKind::Promote {
-                    from: f,
-                    to: t,
-                    to_piece,
-                    ..
-                } => *f == from && *t == to && promo.is_none_or(|p| p == *to_piece),
-                MoveKind::Castle(side) => {
-                    let (cf, ct) = castle_squares(m.piece.color, side);
-                    from == cf && to == ct
+                SearchMessage::NewBest { .. } => {}
+                SearchMessage::Finished { best, score, stats } => {
+                    if best.is_none() {
+                        // Checkmate ("mate 0") or stalemate ("cp 0"): the game is over.
+                        uci_send!("info depth 0 score {}", evaluation::uci_score(score));
+                    } else if cfg!(feature = "engine-diagnostics") {
+                        uci_send!("info string {}", stats);
+                    }
+                    return best.map(|m| (m, depth, score));
                 }
             }
-        })
-        .map(|rm| rm.piece_move)
-}
-
-// ---------------------------------------------------------------------------
-// Search helpers
-// ---------------------------------------------------------------------------
-
-/// Runs the engine search at the given depth and returns the best (Move, score)
-/// pair, or None if there are no legal moves (checkmate / stalemate).
-fn search_at_depth(board: &Board, depth: i32) -> Option<(Move, i32)> {
-    let scenario = Scenario::new(board.clone());
-    let (tx, rx) = mpsc::channel::<(Move, i32)>();
-    let is_white = board.turn == Color::White;
-
-    thread::spawn(move || {
-        scenario.parallel_minimax_alpha_beta(depth, tx);
-    });
-
-    let mut best: Option<(Move, i32)> = None;
-    for (m, eval) in rx {
-        let better = match best {
-            None => true,
-            Some((_, prev)) => {
-                if is_white {
-                    eval > prev
-                } else {
-                    eval < prev
-                }
-            }
-        };
-        if better {
-            best = Some((m, eval));
-        }
-    }
-
-    best
-}
-
-/// Iterative-deepening search within `budget`.
-/// Starts from depth 1, increases depth as long as time allows, and returns
-/// the best move found in the last completed iteration.
-///
-/// The engine has no mid-search stop mechanism, so each iteration runs to
-/// completion. The heuristic is: skip the next iteration if we have already
-/// used more than half the budget (the next depth usually takes ~5× longer).
-fn iterative_deepening(board: &Board, budget: Duration) -> Option<Move> {
-    let start = Instant::now();
-    let mut best_move: Option<Move> = None;
-
-    for depth in 1i32..=20 {
-        let elapsed = start.elapsed();
-        if elapsed >= budget {
-            break;
         }
-        // If over half the budget is gone, the next depth will likely exceed it.
-        if best_move.is_some() && elapsed.as_millis() * 2 > budget.as_millis() {
-            break;
-        }
-
-        match search_at_depth(board, depth) {
-            Some((m, eval)) => {
-                // Engine uses 1000 per pawn; UCI expects centipawns (100/pawn).
-                let cp = eval / 10;
-                uci_send!(
-                    "info depth {} score cp {} time {}",
-                    depth,
-                    cp,
-                    start.elapsed().as_millis()
-                );
-                best_move = Some(m);
-            }
-            None => break, // no legal moves
-        }
-    }
-
-    best_move
+        None
+    })
 }
 
 // ---------------------------------------------------------------------------
 // Command handlers
 // ---------------------------------------------------------------------------
 
-fn handle_position(tokens: &[&str], current_board: &mut Board) {
+/// Handles `position [startpos | fen <fen>] [moves <m1> ...]`.
+///
+/// An invalid FEN keeps the previous position. An illegal move keeps the position
+/// reached before it, and that move's index is reported. Returns false in both
+/// cases, so that strict mode can refuse to search.
+/// `history` receives the hashes of the positions the move list went through.
+fn handle_position(tokens: &[&str], current_board: &mut Board, history: &mut Vec<u64>) -> bool {
     if tokens.len() < 2 {
-        return;
+        return false;
     }
 
     let moves_idx = tokens.iter().position(|&t| t == "moves");
@@ -220,106 +156,228 @@ fn handle_position(tokens: &[&str], current_board: &mut Board) {
         "fen" => {
             let fen_end = moves_idx.unwrap_or(tokens.len());
             if fen_end <= 2 {
-                return;
+                return false;
             }
             Board::from_forsyth_edwards(&tokens[2..fen_end].join(" "))
         }
-        _ => return,
+        _ => return false,
     };
 
-    let mut board = match board_result {
+    let board = match board_result {
         Ok(b) => b,
-        Err(_) => return,
+        Err(e) => {
+            uci_send!("info string invalid position: {:#}", e);
+            return false;
+        }
     };
 
-    if let Some(mi) = moves_idx {
-        for uci_move in &tokens[mi + 1..] {
-            match parse_uci_move(&board, uci_move) {
-                Some(m) => board = board.make_unchecked_move(&m),
-                None => break, // malformed move list, stop applying
-            }
+    let moves = moves_idx.map_or(&[][..], |mi| &tokens[mi + 1..]);
+    let (board, played, failed) = board.play_uci_moves_with_history(moves);
+    *current_board = board;
+    *history = played;
+
+    match failed {
+        Some(i) => {
+            uci_send!(
+                "info string illegal move {} at index {} of the move list, keeping the position before it",
+                moves[i],
+                i
+            );
+            false
         }
+        None => true,
     }
+}
 
-    *current_board = board;
+/// Non-standard extension for board-recognition tools:
+/// `setpieces <w|b> <castling> <ep|-> <piece list>`, e.g. `setpieces w KQkq - Ke1 Pe2 ke8`.
+fn handle_setpieces(tokens: &[&str], current_board: &mut Board) -> bool {
+    if tokens.len() < 5 {
+        uci_send!("info string usage: setpieces <w|b> <castling> <ep|-> <pieces>");
+        return false;
+    }
+    let board_result = Color::try_from(tokens[1]).and_then(|turn| {
+        Board::from_piece_list(&tokens[4..].join(" "), turn, tokens[2], Some(tokens[3]))
+    });
+
+    match board_result {
+        Ok(board) => {
+            *current_board = board;
+            true
+        }
+        Err(e) => {
+            uci_send!("info string invalid piece list: {:#}", e);
+            false
+        }
+    }
+}
+
+/// Handles `go` on `scenario`, from [`go_scenario`]. Returns the best move,
+/// None if there are no legal moves.
+fn handle_go(
+    scenario: Scenario,
+    tt: &TranspositionTable,
+    limits: &SearchLimits,
+    book: Option<&Book>,
+    learning: Option<&mut LearningStore>,
+) -> Option<Move> {
+    let board = scenario.board.clone();
+    // The book move with the highest weight is played without a search, except
+    // when analysing or when the searchmoves restrict the choice.
+    let book_move = book
+        .filter(|_| !limits.infinite && scenario.searchmoves().is_none())
+        .and_then(|book| book.best_move(&board));
+    if let Some(m) = book_move {
+        uci_send!("info string book move {}", m.to_uci());
+        return Some(m);
+    }
+    // A result restricted to the searchmoves is not the position's: it is
+    // neither answered from nor recorded in the learning file.
+    let learning = learning.filter(|_| scenario.searchmoves().is_none());
+    // A stored result from a previous session, re-validated against the legal moves.
+    let learned = learning
+        .as_deref()
+        .and_then(|store| store.get(board.hash))
+        .and_then(|e| Some((board.parse_uci_move(&e.best_move)?, e.depth, e.score)));
+
+    let best = match learned {
+        // Already analysed at least this deep: answer from the learning file.
+        Some((m, depth, eval)) if limits.depth.is_some_and(|d| depth >= d) => {
+            uci_send!("info depth {} score {}", depth, evaluation::uci_score(eval));
+            Some((m, depth, eval))
+        }
+        _ => iterative_deepening(scenario, tt, limits, learned),
+    };
+
+    if let (Some(store), Some((m, depth, eval))) = (learning, best) {
+        store.record(board.hash, depth, eval, &m);
+    }
+
+    best.map(|(m, _, _)| m)
+}
+
+/// The `bestmove` line for `best`. With `ponder`, the expected reply found in
+/// the transposition table is suggested for pondering.
+fn bestmove_line(
+    board: &Board,
+    tt: &TranspositionTable,
+    best: Option<Move>,
+    ponder: bool,
+) -> String {
+    let Some(best) = best else {
+        return "bestmove 0000".to_string(); // no legal moves (checkmate / stalemate)
+    };
+    let reply = HashWalk::new(board, tt, 2, 1)
+        .mainline()
+        .get(..2)
+        .filter(|line| ponder && line[0] == best)
+        .map(|line| line[1]);
+
+    match reply {
+        Some(reply) => format!("bestmove {} ponder {}", best.to_uci(), reply.to_uci()),
+        None => format!("bestmove {}", best.to_uci()),
+    }
 }
 
-fn handle_go(board: &Board, tokens: &[&str]) {
-    let mut fixed_depth: Option<i32> = None;
-    let mut movetime_ms: Option<u64> = None;
-    let mut wtime: Option<u64> = None; // white clock time left
-    let mut btime: Option<u64> = None; // black clock time left
-    let mut winc: Option<u64> = None; // white clock increment
-    let mut binc: Option<u64> = None; // black clock increment
-    let mut infinite = false;
+/// Handles `hashwalk [plies <n>] [branch <n>] [json]`, a debugging command that
+/// prints the analysis tree left in the transposition table by the last search,
+/// as PGN or as JSON. Defaults: 12 plies, main line only.
+fn handle_hashwalk(tokens: &[&str], board: &Board, tt: &TranspositionTable) {
+    let mut plies = 12;
+    let mut branch = 1;
+    let mut json = false;
 
     let mut i = 0;
     while i < tokens.len() {
         match tokens[i] {
-            "depth" => {
-                fixed_depth = tokens.get(i + 1).and_then(|s| s.parse().ok());
-                i += 1;
-            }
-            "movetime" => {
-                movetime_ms = tokens.get(i + 1).and_then(|s| s.parse().ok());
-                i += 1;
-            }
-            "wtime" => {
-                wtime = tokens.get(i + 1).and_then(|s| s.parse().ok());
-                i += 1;
-            }
-            "btime" => {
-                btime = tokens.get(i + 1).and_then(|s| s.parse().ok());
+            "plies" => {
+                plies = tokens
+                    .get(i + 1)
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(plies);
                 i += 1;
             }
-            "winc" => {
-                winc = tokens.get(i + 1).and_then(|s| s.parse().ok());
+            "branch" => {
+                branch = tokens
+                    .get(i + 1)
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(branch);
                 i += 1;
             }
-            "binc" => {
-                binc = tokens.get(i + 1).and_then(|s| s.parse().ok());
-                i += 1;
-            }
-            "infinite" => {
-                infinite = true;
-            }
+            "json" => json = true,
             _ => {}
         }
         i += 1;
     }
 
-    let best_move = if let Some(d) = fixed_depth {
-        // Fixed-depth search: run once, no time management.
-        search_at_depth(board, d).map(|(m, eval)| {
-            let cp = eval / 10;
-            uci_send!("info depth {} score cp {}", d, cp);
-            m
-        })
+    let walk = HashWalk::new(board, tt, plies, branch);
+    if json {
+        uci_send!("{}", walk.to_json());
     } else {
-        let budget_ms = if infinite {
-            // No stop support yet: cap at 30 s so we don't hang forever.
-            30_000
-        } else if let Some(mt) = movetime_ms {
-            mt
+        uci_send!("{}", walk.to_pgn());
+    }
+}
+
+/// Handles `setoption name <id> [value <x>]`.
+/// Search tunables are copied into each search when it starts, so a change
+/// applies from the next `go` on.
+fn handle_setoption(
+    tokens: &[&str],
+    config: &mut SearchConfig,
+    book: &mut Option<Book>,
+    learning: &mut Option<LearningStore>,
+    strict: &mut bool,
+    ponder: &mut bool,
+) {
+    let (name, value) = config::parse_setoption(tokens);
+
+    if name.eq_ignore_ascii_case("Strict") {
+        match value.to_ascii_lowercase().as_str() {
+            "true" => *strict = true,
+            "false" => *strict = false,
+            _ => uci_send!("info string invalid value for Strict: {}", value),
+        }
+    } else if name.eq_ignore_ascii_case("Ponder") {
+        match value.to_ascii_lowercase().as_str() {
+            "true" => *ponder = true,
+            "false" => *ponder = false,
+            _ => uci_send!("info string invalid value for Ponder: {}", value),
+        }
+    } else if name.eq_ignore_ascii_case("BookFile") {
+        *book = if value.is_empty() || value == "<empty>" {
+            None
         } else {
-            // Time-control mode: use ~1/30 of remaining time + half increment.
-            let my_time = match board.turn {
-                Color::White => wtime.unwrap_or(30_000),
-                Color::Black => btime.unwrap_or(30_000),
-            };
-            let my_inc = match board.turn {
-                Color::White => winc.unwrap_or(0),
-                Color::Black => binc.unwrap_or(0),
-            };
-            (my_time / 30).max(100) + my_inc / 2
+            match Book::open(&value) {
+                Ok(book) => Some(book),
+                Err(e) => {
+                    uci_send!("info string cannot open opening book: {:#}", e);
+                    None
+                }
+            }
         };
+    } else if name.eq_ignore_ascii_case("LearningFile") {
+        save_learning(learning);
+        *learning = if value.is_empty() || value == "<empty>" {
+            None
+        } else {
+            match LearningStore::open(&value, LearningStore::DEFAULT_MAX_ENTRIES) {
+                Ok(store) => Some(store),
+                Err(e) => {
+                    uci_send!("info string cannot open learning file: {:#}", e);
+                    None
+                }
+            }
+        };
+    } else if let Err(e) = config.set_option(&name, &value) {
+        uci_send!("info string {:#}", e);
+    }
+}
 
-        iterative_deepening(board, Duration::from_millis(budget_ms))
-    };
-
-    match best_move {
-        Some(m) => uci_send!("bestmove {}", move_to_uci(&m)),
-        None => uci_send!("bestmove 0000"), // no legal moves (checkmate / stalemate)
+fn save_learning(learning: &mut Option<LearningStore>) {
+    if let Some(store) = learning
+        && let Err(e) = store.save()
+    {
+        uci_send!("info string cannot save learning file: {:#}", e);
     }
 }
 
@@ -327,19 +385,71 @@ fn handle_go(board: &Board, tokens: &[&str]) {
 // Main loop
 // ---------------------------------------------------------------------------
 
+/// Reads the commands on their own thread, so that `stop`, `ponderhit` and
+/// `quit` reach a running search while the other commands wait for it to end.
+/// Each `go` comes with the handles reaching its search.
+///
+/// `searching` is set on `go` and cleared by the main loop once the search is
+/// over. Meanwhile `isready` is answered right away.
+fn spawn_reader(searching: Arc<AtomicBool>) -> mpsc::Receiver<(String, Option<SearchHandles>)> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut search = SearchHandles::default();
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            let line = line.trim().to_string();
+            let handle = match line.split_whitespace().next() {
+                Some("go") => {
+                    searching.store(true, Ordering::Relaxed);
+                    search = SearchHandles::default();
+                    Some(search.clone())
+                }
+                Some("isready") if searching.load(Ordering::Relaxed) => {
+                    uci_send!("readyok");
+                    continue;
+                }
+                Some("ponderhit") => {
+                    search.ponder.hit();
+                    None
+                }
+                Some("stop" | "quit") => {
+                    search.stop.stop();
+                    None
+                }
+                _ => None,
+            };
+            if tx.send((line, handle)).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
 fn main() {
     // Pre-compute magic bitboard tables;
     corman::moves::magic::init();
 
-    let stdin = io::stdin();
+    let searching = Arc::new(AtomicBool::new(false));
+    let commands = spawn_reader(searching.clone());
     let mut current_board = Board::new_game();
-
-    for line in stdin.lock().lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => break,
-        };
-        let line = line.trim().to_string();
+    // Hashes of the positions played before `current_board`, for repetitions.
+    let mut history: Vec<u64> = Vec::new();
+    let mut book: Option<Book> = None;
+    let mut learning: Option<LearningStore> = None;
+    let mut config = SearchConfig::default();
+    // In strict mode `go` is refused until a position is set without errors.
+    let mut strict = false;
+    let mut position_ok = true;
+    // With the Ponder option the best move comes with the reply to ponder on.
+    let mut ponder = false;
+    // A ponder or infinite search that ended before the ponderhit or the stop keeps its
+    // best move until then.
+    let mut pending_bestmove: Option<String> = None;
+    // Kept between searches: the next search starts from what the previous one found.
+    let tt = TranspositionTable::new();
+
+    for (line, handles) in commands {
         if line.is_empty() {
             continue;
         }
@@ -349,6 +459,13 @@ fn main() {
             "uci" => {
                 uci_send!("id name {}", ENGINE_NAME);
                 uci_send!("id author {}", ENGINE_AUTHOR);
+                uci_send!("option name BookFile type string default <empty>");
+                uci_send!("option name LearningFile type string default <empty>");
+                uci_send!("option name Strict type check default false");
+                uci_send!("option name Ponder type check default false");
+                for option in config.uci_options() {
+                    uci_send!("{}", option);
+                }
                 uci_send!("uciok");
             }
             "isready" => {
@@ -356,18 +473,75 @@ fn main() {
             }
             "ucinewgame" => {
                 current_board = Board::new_game();
+                history.clear();
+                position_ok = true;
+                tt.clear();
             }
             "position" => {
-                handle_position(&tokens, &mut current_board);
+                position_ok = handle_position(&tokens, &mut current_board, &mut history);
+            }
+            "setpieces" => {
+                position_ok = handle_setpieces(&tokens, &mut current_board);
+                if position_ok {
+                    history.clear();
+                }
+            }
+            "setoption" => {
+                handle_setoption(
+                    &tokens,
+                    &mut config,
+                    &mut book,
+                    &mut learning,
+                    &mut strict,
+                    &mut ponder,
+                );
+            }
+            "go" if strict && !position_ok => {
+                uci_send!("info string strict mode: no valid position set, not searching");
+                uci_send!("bestmove 0000");
+                searching.store(false, Ordering::Relaxed);
             }
             "go" => {
-                handle_go(&current_board, &tokens[1..]);
+                let limits = SearchLimits::from_go_tokens(&tokens[1..]);
+                let handles = handles.unwrap_or_default();
+                let best = go_scenario(
+                    &current_board,
+                    &history,
+                    config,
+                    &tokens[1..],
+                    handles.clone(),
+                )
+                .and_then(|scenario| {
+                    handle_go(scenario, &tt, &limits, book.as_ref(), learning.as_mut())
+                });
+                let line = bestmove_line(&current_board, &tt, best, ponder);
+                // UCI forbids answering a ponder or infinite search before the
+                // ponderhit or the stop, even if it ended on its own.
+                if (limits.ponder || limits.infinite)
+                    && handles.ponder.hit_at().is_none()
+                    && !handles.stop.is_stopped()
+                {
+                    pending_bestmove = Some(line);
+                } else {
+                    uci_send!("{}", line);
+                }
+                searching.store(false, Ordering::Relaxed);
+            }
+            "hashwalk" => {
+                handle_hashwalk(&tokens[1..], &current_board, &tt);
+            }
+            // Already handled by the reader: once the search is over, only the
+            // best move of a ponder or infinite search can still be waiting.
+            "stop" | "ponderhit" => {
+                if let Some(line) = pending_bestmove.take() {
+                    uci_send!("{}", line);
+                }
             }
-            // "stop" is not supported yet: the search runs to completion.
-            // Ignore it silently so the GUI does not hang.
-            "stop" => {}
             "quit" => break,
             _ => {}
         }
     }
+
+    // Persist what was learned during this session.
+    save_learning(&mut learning);
 }
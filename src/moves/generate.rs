@@ -1,54 +1,56 @@
 use crate::{
-    components::{board::Board, castle, en_passant, pieces::PieceKind},
+    components::{
+        board::Board,
+        castle, constants, en_passant,
+        pieces::{Bitboard, Piece, PieceKind},
+    },
     evaluator,
 };
 use strum::IntoEnumIterator;
 
-use super::move_type::{Move, MoveKind};
+use super::{
+    lines,
+    move_type::{Move, MoveKind},
+    packed::PackedMove,
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct RatedMove {
-    pub piece_move: Move,
+    pub piece_move: PackedMove,
     pub rating: i32,
 }
 
 impl RatedMove {
-    pub fn new(piece_move: Move, rating: i32) -> Self {
+    pub fn new(piece_move: PackedMove, rating: i32) -> Self {
         RatedMove { piece_move, rating }
     }
 }
 
-impl Default for RatedMove {
-    fn default() -> Self {
-        Self {
-            piece_move: Move {
-                piece: 'K'.try_into().expect("hardcoded piece should not fail"),
-                action: MoveKind::Standard {
-                    from: 0,
-                    to: 0,
-                    captured: None,
-                },
-            },
-            rating: i32::MIN,
-        }
-    }
-}
+/// Capacity of [`Moves`]. No legal chess position has more than 218 legal moves.
+/// Only a position no game can reach, e.g. a FEN with a dozen queens, may have more.
+pub const MAX_MOVES: usize = 255;
 
+/// Moves generated on a board, stored packed. Unpacking needs the same board.
 pub struct Moves {
-    pub list: [RatedMove; 255],
+    pub list: [RatedMove; MAX_MOVES],
     pub len: u8,
 }
 
 impl Moves {
     fn new() -> Self {
         Moves {
-            list: [RatedMove::default(); 255],
+            list: [RatedMove::default(); MAX_MOVES],
             len: 0,
         }
     }
 
     fn push(&mut self, current_move: Move, rating: i32) {
-        self.list[self.len as usize] = RatedMove::new(current_move, rating);
+        assert!(
+            self.len() < MAX_MOVES,
+            "more than {} legal moves: the position cannot occur in a game",
+            MAX_MOVES
+        );
+        self.list[self.len as usize] = RatedMove::new(PackedMove::new(&current_move), rating);
         self.len += 1;
     }
 
@@ -56,7 +58,14 @@ impl Moves {
     /// Finds the highest-rated move in list[index..len], swaps it to position `index`,
     /// and returns it. This means only the moves actually examined by the search get sorted,
     /// which is faster than sorting all moves upfront when alpha-beta prunes early.
-    pub fn get(&mut self, index: usize) -> Move {
+    ///
+    /// `board` must be the position the moves were generated on.
+    pub fn get(&mut self, index: usize, board: &Board) -> Move {
+        unpack(self.select(index).piece_move, board)
+    }
+
+    /// The selection step of [`Moves::get`], without unpacking the move.
+    pub fn select(&mut self, index: usize) -> RatedMove {
         let len = self.len as usize;
         let mut best_idx = index;
         for j in (index + 1)..len {
@@ -66,7 +75,37 @@ impl Moves {
         }
         self.list.swap(index, best_idx);
 
-        self.list[index].piece_move
+        self.list[index]
+    }
+
+    /// Returns the move at `index` in generation order, without sorting.
+    /// `board` must be the position the moves were generated on.
+    pub fn get_unsorted(&self, index: usize, board: &Board) -> Move {
+        unpack(self.list[index].piece_move, board)
+    }
+
+    /// Unpacks the moves in generation order, without sorting them.
+    /// `board` must be the position the moves were generated on.
+    pub fn iter<'a>(&'a self, board: &'a Board) -> impl Iterator<Item = Move> + 'a {
+        self.list[..self.len()]
+            .iter()
+            .map(move |rm| unpack(rm.piece_move, board))
+    }
+
+    /// Gives the first move matching `is_preferred` the highest rating so that
+    /// `get(0)` returns it. Returns false if no move matches.
+    pub fn prioritize(&mut self, is_preferred: impl Fn(PackedMove) -> bool) -> bool {
+        let len = self.len as usize;
+        match self.list[..len]
+            .iter_mut()
+            .find(|rm| is_preferred(rm.piece_move))
+        {
+            Some(rm) => {
+                rm.rating = i32::MAX;
+                true
+            }
+            None => false,
+        }
     }
 
     pub fn is_empty(&self) -> bool {
@@ -78,25 +117,93 @@ impl Moves {
     }
 }
 
+fn unpack(packed: PackedMove, board: &Board) -> Move {
+    packed
+        .to_move(board)
+        .expect("generated moves unpack on the board they were generated on")
+}
+
+/// Which of the legal moves [`Board::generate`] keeps.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    All,
+    /// Captures and promotions to a queen, and every move when in check
+    /// except the underpromotions.
+    Critical,
+    /// Captures, en passant and every promotion.
+    Captures,
+    /// Everything [`Selection::Captures`] leaves out, castling included.
+    Quiets,
+}
+
 impl Board {
     /// returns all the possible legal moves order by the rating given to them.
     /// the rating is given according to MVV LVA:
     /// Most Valuable Victim Less Valuable Attacker.
     ///
-    /// When only_critical is true only captures and stop-checks get generated.
+    /// When only_critical is true only captures, promotions to a queen and
+    /// stop-checks get generated.
     /// Discards the moves that leaves the moving side king in check (illegal).
     pub fn generate_moves(&self, only_critical: bool) -> Moves {
+        self.generate(if only_critical {
+            Selection::Critical
+        } else {
+            Selection::All
+        })
+    }
+
+    /// The legal captures, en passant captures and promotions, rated like
+    /// [`Board::generate_moves`].
+    pub fn generate_captures(&self) -> Moves {
+        self.generate(Selection::Captures)
+    }
+
+    /// The legal moves [`Board::generate_captures`] leaves out.
+    pub fn generate_quiets(&self) -> Moves {
+        self.generate(Selection::Quiets)
+    }
+
+    fn generate(&self, selection: Selection) -> Moves {
         let mut moves = Moves::new();
         let in_check = self.position.is_in_check(self.turn);
 
         let enemy_squares = self.position.occupied_by(self.turn.other()).bits;
+        let pinned = self.position.pinned_pieces(self.turn).bits;
+        // Squares where a piece other than the king stops a check: anywhere when
+        // not in check, on the checker or between it and the king against a
+        // single checker, and nowhere against two.
+        let king = self.position.get(Piece::new(self.turn, PieceKind::King));
+        let evasion_mask = match king.lsb_index() {
+            Some(king_sq) if in_check => {
+                let checkers = self
+                    .position
+                    .attackers_to(king_sq, self.position.occupied_cells())
+                    & Bitboard::new(enemy_squares);
+                match (checkers.count_bits(), checkers.lsb_index()) {
+                    (1, Some(checker)) => lines::between(king_sq, checker) | checkers.bits,
+                    _ => 0,
+                }
+            }
+            _ => u64::MAX,
+        };
         for (piece, bitboard) in self.position.into_iter() {
             if piece.color != self.turn {
                 continue;
             }
+            // A piece other than the king that stays on its pin ray and stops
+            // any check cannot expose its king, so only king moves need the test.
+            let needs_check_test = piece.kind == PieceKind::King;
 
             for piece_position in bitboard.single_squares() {
-                let available_moves = self.position.available_moves(*piece, piece_position);
+                let mut available_moves = self.position.available_moves(*piece, piece_position);
+                if !needs_check_test {
+                    available_moves.bits &= evasion_mask;
+                }
+                if pinned & (1u64 << piece_position) != 0
+                    && let Some(ray) = self.position.pin_ray(self.turn, piece_position)
+                {
+                    available_moves = available_moves & ray;
+                }
 
                 for to_square in available_moves.single_squares() {
                     let captured = if (1u64 << to_square) & enemy_squares != 0 {
@@ -113,22 +220,36 @@ impl Board {
                         },
                     };
 
-                    if self.position.is_in_check_after_standard_move(
-                        piece_position,
-                        to_square,
-                        *piece,
-                    ) {
+                    let is_promotion = current_move.is_promotion();
+                    let wanted = match selection {
+                        Selection::All => true,
+                        Selection::Critical => is_promotion || captured.is_some() || in_check,
+                        Selection::Captures => captured.is_some() || is_promotion,
+                        Selection::Quiets => captured.is_none() && !is_promotion,
+                    };
+                    if !wanted {
+                        continue;
+                    }
+
+                    if needs_check_test
+                        && self.position.is_in_check_after_standard_move(
+                            piece_position,
+                            to_square,
+                            *piece,
+                        )
+                    {
                         // the move the player made left the king in check -> not valid
                         continue;
                     }
 
-                    // save promotions only if it's not required to generate only critical moves.
-                    // only_critical == true => save only captures and stop-checks
-                    if current_move.is_promotion() && !only_critical {
+                    if is_promotion {
                         for piece_kind in PieceKind::iter() {
                             if piece_kind == PieceKind::Pawn || piece_kind == PieceKind::King {
                                 continue;
                             }
+                            if selection == Selection::Critical && piece_kind != PieceKind::Queen {
+                                continue;
+                            }
                             let promotion = Move {
                                 piece: *piece,
                                 action: MoveKind::Promote {
@@ -144,30 +265,22 @@ impl Board {
                             );
                             moves.push(promotion, eval);
                         }
-                    } else if current_move.is_capture() || in_check {
-                        // if i'm there it means the move is a capture or the player is in check.
-                        // if the player is in check, the move that reached this part is a move that stops the check
-                        // or it would have been discarded from the condition at line 39.
-                        let eval = evaluator::utils::move_score_with_mvv_lva(
-                            &current_move,
-                            &self.position,
-                        );
-                        moves.push(current_move, eval);
-                    } else if !only_critical {
-                        // if i'm here it means the move is not a promotion, a capture, or a stop-check.
-                        // so i add it to the moves Vec only if only_critical is not required
-                        let eval = evaluator::utils::move_score_with_mvv_lva(
+                    } else {
+                        let mut eval = evaluator::utils::move_score_with_mvv_lva(
                             &current_move,
                             &self.position,
                         );
+                        if captured.is_none() && self.gives_check(&current_move) {
+                            eval += constants::QUIET_CHECK_VALUE;
+                        }
                         moves.push(current_move, eval);
                     }
                 }
             }
         }
 
-        // generate castling moves only if the player is not in check and only_critical is not required
-        if !in_check && !only_critical {
+        // castling is a quiet move, never available when in check
+        if !in_check && matches!(selection, Selection::All | Selection::Quiets) {
             let castling_moves = castle::available_castling_moves(
                 self,
                 self.white_can_castle,
@@ -185,7 +298,10 @@ impl Board {
             }
         }
 
-        // En passant is always a capture, so generate it in both full and critical mode.
+        // En passant is always a capture.
+        if selection == Selection::Quiets {
+            return moves;
+        }
         let ep_moves = en_passant::available_en_passant_moves(self);
         for ep_move in [ep_moves.0, ep_moves.1].into_iter().flatten() {
             if let MoveKind::EnPassant { from, to } = ep_move.action
@@ -201,4 +317,119 @@ impl Board {
 
         moves
     }
+
+    /// Unpacks `packed` if it is a legal move in this position, e.g. a move
+    /// read from the transposition table that may come from another position.
+    pub fn legal_move(&self, packed: PackedMove) -> Option<Move> {
+        let m = packed.to_move(self)?;
+        if m.piece.color != self.turn {
+            return None;
+        }
+        let legal = match m.action {
+            MoveKind::Standard { from, to, .. } | MoveKind::Promote { from, to, .. } => {
+                // A pawn reaching the last rank must promote, and only then.
+                let must_promote = m.piece.kind == PieceKind::Pawn && matches!(to / 8, 0 | 7);
+                must_promote == matches!(m.action, MoveKind::Promote { .. })
+                    && self.position.available_moves(m.piece, from).bits & (1u64 << to) != 0
+                    && !self
+                        .position
+                        .is_in_check_after_standard_move(from, to, m.piece)
+            }
+            MoveKind::EnPassant { from, to } => {
+                let (a, b) = en_passant::available_en_passant_moves(self);
+                [a, b].into_iter().flatten().any(|ep| packed.matches(&ep))
+                    && !self
+                        .position
+                        .is_in_check_after_en_passant(from, to, m.piece.color)
+            }
+            MoveKind::Castle { .. } => {
+                let (a, b) = castle::available_castling_moves(
+                    self,
+                    self.white_can_castle,
+                    self.black_can_castle,
+                );
+                !self.position.is_in_check(self.turn)
+                    && [a, b].into_iter().flatten().any(|c| packed.matches(&c))
+            }
+        };
+        legal.then_some(m)
+    }
+
+    /// All the legal moves of the side to move, castling, promotions and en passant
+    /// included. Meant for library users: unlike [`Board::generate_moves`], the
+    /// moves come unpacked and are not ordered for the search.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.generate_moves(false).iter(self).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{components::board::Board, moves::magic};
+
+    #[test]
+    fn the_position_with_the_most_legal_moves_fits_and_sorts() {
+        magic::init();
+        let board =
+            Board::from_forsyth_edwards("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1")
+                .unwrap();
+        let mut moves = board.generate_moves(false);
+        assert_eq!(moves.len(), 218);
+
+        let mut ratings = Vec::new();
+        for i in 0..moves.len() {
+            moves.get(i, &board);
+            ratings.push(moves.list[i].rating);
+        }
+        assert!(ratings.is_sorted_by(|a, b| a >= b));
+    }
+
+    #[test]
+    fn legal_moves_match_the_reference_perft_1_counts() {
+        magic::init();
+        for (fen, count) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                20,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                48,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 14),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                6,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                44,
+            ),
+            // Black can take on d3 en passant.
+            ("4k3/8/8/8/2Pp4/8/8/4K3 b - c3 0 1", 7),
+        ] {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let moves = board.legal_moves();
+            assert_eq!(moves.len(), count, "{}", fen);
+        }
+
+        let board = Board::from_forsyth_edwards("4k3/8/8/8/2Pp4/8/8/4K3 b - c3 0 1").unwrap();
+        let uci: Vec<_> = board.legal_moves().iter().map(|m| m.to_uci()).collect();
+        assert!(uci.contains(&"d4c3".to_string()), "{:?}", uci);
+    }
+
+    #[test]
+    fn quiet_checks_are_rated_above_the_other_quiet_moves() {
+        magic::init();
+        // Ra8, Nc7 and Nd6 check the black king, the other quiet moves do not.
+        let board = Board::from_forsyth_edwards("4k3/8/8/1N6/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut moves = board.generate_quiets();
+        let ordered: Vec<_> = (0..moves.len()).map(|i| moves.get(i, &board)).collect();
+
+        let checks = ordered.iter().take_while(|m| board.gives_check(m));
+        let mut checks: Vec<_> = checks.map(|m| m.to_uci()).collect();
+        checks.sort();
+        assert_eq!(checks, ["a1a8", "b5c7", "b5d6"]);
+        assert!(ordered[3..].iter().all(|m| !board.gives_check(m)));
+    }
 }
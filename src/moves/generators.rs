@@ -21,8 +21,8 @@ pub fn white_pawn_attack(
     _blockers: Bitboard,
     black_pieces: Bitboard,
 ) -> Bitboard {
-    ((starting_position << 7 & Bitboard::new(constants::NOT_A_RANK))
-        | (starting_position << 9 & Bitboard::new(constants::NOT_H_RANK)))
+    ((starting_position << 7 & Bitboard::new(constants::NOT_A_FILE))
+        | (starting_position << 9 & Bitboard::new(constants::NOT_H_FILE)))
         & black_pieces
 }
 
@@ -34,29 +34,30 @@ pub fn black_pawn_attack(
     _blockers: Bitboard,
     white_pieces: Bitboard,
 ) -> Bitboard {
-    ((starting_position >> 7 & Bitboard::new(constants::NOT_H_RANK))
-        | (starting_position >> 9 & Bitboard::new(constants::NOT_A_RANK)))
+    ((starting_position >> 7 & Bitboard::new(constants::NOT_H_FILE))
+        | (starting_position >> 9 & Bitboard::new(constants::NOT_A_FILE)))
         & white_pieces
 }
 
 /// Returns all possible pawns advancing moves considering other pieces positioned on the board.
 /// Running the function with blockers == 0 returns all possbile advancing move, without any blocking pieces in front of the pawn.
+///
+/// Works on any number of pawns at once: a double push is a single push that
+/// lands on the third row and can advance once more, so only the pawns that
+/// started on the second row with both squares in front empty get one.
 pub fn white_pawn_quiet_moves(starting_position: Bitboard, blockers: Bitboard) -> Bitboard {
-    if starting_position.bits & constants::SECOND_ROW != 0 {
-        (starting_position << 8 & !blockers)
-            | (starting_position << 16 & !blockers & !(blockers << 8))
-    } else {
-        starting_position << 8 & !blockers
-    }
+    let single = starting_position << 8 & !blockers;
+    let double = Bitboard::new(single.bits & constants::THIRD_ROW) << 8 & !blockers;
+
+    single | double
 }
 
+/// Same as [`white_pawn_quiet_moves`], advancing towards the first row.
 pub fn black_pawn_quiet_moves(starting_position: Bitboard, blockers: Bitboard) -> Bitboard {
-    if starting_position.bits & constants::SEVENTH_ROW != 0 {
-        (starting_position >> 8 & !blockers)
-            | (starting_position >> 16 & !blockers & !(blockers >> 8))
-    } else {
-        starting_position >> 8 & !blockers
-    }
+    let single = starting_position >> 8 & !blockers;
+    let double = Bitboard::new(single.bits & constants::SIXTH_ROW) >> 8 & !blockers;
+
+    single | double
 }
 
 /// blockers = pieces of the same and opposite color
@@ -82,14 +83,14 @@ pub fn black_pawn(starting_position: Bitboard, blockers: Bitboard, enemies: Bitb
 /// blockers = pieces of the same color
 pub fn knight(starting_position: Bitboard, blockers: Bitboard, _enemies: Bitboard) -> Bitboard {
     Bitboard::new(
-        ((starting_position.bits << 15 & constants::NOT_A_RANK)
-            | (starting_position.bits >> 15 & constants::NOT_H_RANK)
-            | (starting_position.bits << 17 & constants::NOT_H_RANK)
-            | (starting_position.bits >> 17 & constants::NOT_A_RANK)
-            | (starting_position.bits >> 6 & constants::NOT_H_RANK & constants::NOT_G_RANK)
-            | (starting_position.bits << 6 & constants::NOT_A_RANK & constants::NOT_B_RANK)
-            | (starting_position.bits << 10 & constants::NOT_H_RANK & constants::NOT_G_RANK)
-            | (starting_position.bits >> 10 & constants::NOT_A_RANK & constants::NOT_B_RANK))
+        ((starting_position.bits << 15 & constants::NOT_A_FILE)
+            | (starting_position.bits >> 15 & constants::NOT_H_FILE)
+            | (starting_position.bits << 17 & constants::NOT_H_FILE)
+            | (starting_position.bits >> 17 & constants::NOT_A_FILE)
+            | (starting_position.bits >> 6 & constants::NOT_H_FILE & constants::NOT_G_FILE)
+            | (starting_position.bits << 6 & constants::NOT_A_FILE & constants::NOT_B_FILE)
+            | (starting_position.bits << 10 & constants::NOT_H_FILE & constants::NOT_G_FILE)
+            | (starting_position.bits >> 10 & constants::NOT_A_FILE & constants::NOT_B_FILE))
             & !blockers.bits,
     )
 }
@@ -138,14 +139,413 @@ pub fn queen(starting_position: Bitboard, blockers: Bitboard, enemies: Bitboard)
 /// blockers = pieces of the same color
 pub fn king(starting_position: Bitboard, blockers: Bitboard, _enemies: Bitboard) -> Bitboard {
     Bitboard::new(
-        ((starting_position.bits << 1 & constants::NOT_H_RANK)
-            | (starting_position.bits << 9 & constants::NOT_H_RANK)
-            | (starting_position.bits >> 7 & constants::NOT_H_RANK)
+        ((starting_position.bits << 1 & constants::NOT_H_FILE)
+            | (starting_position.bits << 9 & constants::NOT_H_FILE)
+            | (starting_position.bits >> 7 & constants::NOT_H_FILE)
             | (starting_position.bits << 8)
-            | (starting_position.bits << 7 & constants::NOT_A_RANK)
-            | (starting_position.bits >> 1 & constants::NOT_A_RANK)
-            | (starting_position.bits >> 9 & constants::NOT_A_RANK)
+            | (starting_position.bits << 7 & constants::NOT_A_FILE)
+            | (starting_position.bits >> 1 & constants::NOT_A_FILE)
+            | (starting_position.bits >> 9 & constants::NOT_A_FILE)
             | (starting_position.bits >> 8))
             & !blockers.bits,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{board::Board, pieces::Color};
+
+    #[test]
+    fn sliders_without_pieces_attack_nothing() {
+        magic::init();
+        let empty = Bitboard::new(0);
+        let blockers = Bitboard::new(0xFF00);
+        let enemies = Bitboard::new(0x00FF_0000_0000_0000);
+
+        assert_eq!(bishop(empty, blockers, enemies).bits, 0);
+        assert_eq!(rook(empty, blockers, enemies).bits, 0);
+        assert_eq!(queen(empty, blockers, enemies).bits, 0);
+    }
+
+    /// Bit of a square given by name, e.g. "e4".
+    fn sq(name: &str) -> u64 {
+        let file = name.as_bytes()[0] - b'a';
+        let rank = name.as_bytes()[1] - b'1';
+        1 << (rank * 8 + 7 - file)
+    }
+
+    #[test]
+    fn pawn_pushes_respect_blockers_one_and_two_squares_ahead() {
+        let white = Bitboard::new(sq("e2"));
+        let black = Bitboard::new(sq("d7"));
+
+        assert_eq!(
+            white_pawn_quiet_moves(white, Bitboard::new(0)).bits,
+            sq("e3") | sq("e4")
+        );
+        // A piece directly in front blocks both pushes.
+        assert_eq!(
+            white_pawn_quiet_moves(white, Bitboard::new(sq("e3"))).bits,
+            0
+        );
+        assert_eq!(
+            black_pawn_quiet_moves(black, Bitboard::new(sq("d6"))).bits,
+            0
+        );
+        // A piece two squares ahead only blocks the double push.
+        assert_eq!(
+            white_pawn_quiet_moves(white, Bitboard::new(sq("e4"))).bits,
+            sq("e3")
+        );
+        assert_eq!(
+            black_pawn_quiet_moves(black, Bitboard::new(sq("d5"))).bits,
+            sq("d6")
+        );
+        // An enemy piece in front is not a capture target either.
+        let blocked = white_pawn(white, Bitboard::new(sq("e3")), Bitboard::new(sq("e3")));
+        assert_eq!(blocked.bits, 0);
+    }
+
+    #[test]
+    fn mixed_pawns_push_independently_of_each_other() {
+        // Pawns on the 2nd, 3rd and 4th rows: only a2 and d2 may double push,
+        // and d2 cannot because d3 is occupied, even though d4 is free.
+        let white = Bitboard::new(squares("a2 b3 c4 d2 e2"));
+        let blockers = Bitboard::new(squares("d3 e4"));
+        assert_eq!(
+            white_pawn_quiet_moves(white, blockers).bits,
+            squares("a3 a4 b4 c5 e3")
+        );
+
+        let black = Bitboard::new(squares("a7 b6 c5 d7 e7"));
+        let blockers = Bitboard::new(squares("d6 e5"));
+        assert_eq!(
+            black_pawn_quiet_moves(black, blockers).bits,
+            squares("a6 a5 b5 c4 e6")
+        );
+    }
+
+    #[test]
+    fn only_pawns_on_their_starting_row_double_push() {
+        let white = Bitboard::new(sq("a2") | sq("c3"));
+        assert_eq!(
+            white_pawn_quiet_moves(white, Bitboard::new(0)).bits,
+            sq("a3") | sq("a4") | sq("c4")
+        );
+
+        let black = Bitboard::new(sq("h7") | sq("f6"));
+        assert_eq!(
+            black_pawn_quiet_moves(black, Bitboard::new(0)).bits,
+            sq("h6") | sq("h5") | sq("f5")
+        );
+    }
+
+    fn file(sq: u8) -> i32 {
+        7 - (sq % 8) as i32
+    }
+
+    fn rank(sq: u8) -> i32 {
+        (sq / 8) as i32
+    }
+
+    #[test]
+    fn pawn_captures_never_wrap_around_the_edge_files() {
+        let everything = Bitboard::new(u64::MAX);
+        for from in 8..56u8 {
+            let pawn = Bitboard::new(1 << from);
+            for (attacks, direction) in [
+                (white_pawn_attack(pawn, Bitboard::new(0), everything), 1),
+                (black_pawn_attack(pawn, Bitboard::new(0), everything), -1),
+            ] {
+                let expected = match file(from) {
+                    0 | 7 => 1,
+                    _ => 2,
+                };
+                assert_eq!(attacks.count_bits(), expected, "pawn on {}", from);
+                for to in attacks.single_squares() {
+                    assert_eq!((file(to) - file(from)).abs(), 1, "{} -> {}", from, to);
+                    assert_eq!(rank(to) - rank(from), direction, "{} -> {}", from, to);
+                }
+            }
+        }
+
+        // a2 only takes on b3, h2 only on g3.
+        assert_eq!(
+            white_pawn_attack(Bitboard::new(sq("a2")), Bitboard::new(0), everything).bits,
+            sq("b3")
+        );
+        assert_eq!(
+            white_pawn_attack(Bitboard::new(sq("h2")), Bitboard::new(0), everything).bits,
+            sq("g3")
+        );
+        assert_eq!(
+            black_pawn_attack(Bitboard::new(sq("a7")), Bitboard::new(0), everything).bits,
+            sq("b6")
+        );
+        assert_eq!(
+            black_pawn_attack(Bitboard::new(sq("h7")), Bitboard::new(0), everything).bits,
+            sq("g6")
+        );
+    }
+
+    #[test]
+    fn knight_moves_never_wrap_around_the_edge_files() {
+        for from in 0..64u8 {
+            let moves = knight(Bitboard::new(1 << from), Bitboard::new(0), Bitboard::new(0));
+            let mut expected = 0;
+            for (df, dr) in [
+                (1, 2),
+                (2, 1),
+                (2, -1),
+                (1, -2),
+                (-1, -2),
+                (-2, -1),
+                (-2, 1),
+                (-1, 2),
+            ] {
+                if (0..8).contains(&(file(from) + df)) && (0..8).contains(&(rank(from) + dr)) {
+                    expected += 1;
+                }
+            }
+            assert_eq!(moves.count_bits(), expected, "knight on {}", from);
+            for to in moves.single_squares() {
+                let (df, dr) = ((file(to) - file(from)).abs(), (rank(to) - rank(from)).abs());
+                assert!(df * dr == 2, "{} -> {}", from, to);
+            }
+        }
+
+        assert_eq!(
+            knight(Bitboard::new(sq("a1")), Bitboard::new(0), Bitboard::new(0)).bits,
+            sq("b3") | sq("c2")
+        );
+        assert_eq!(
+            knight(Bitboard::new(sq("h4")), Bitboard::new(0), Bitboard::new(0)).bits,
+            sq("g6") | sq("f5") | sq("f3") | sq("g2")
+        );
+    }
+
+    #[test]
+    fn sliders_union_the_rays_of_every_piece() {
+        magic::init();
+        const A_FILE_ABOVE_FIRST_RANK: u64 = 0x8080_8080_8080_8000;
+        const H_FILE_ABOVE_FIRST_RANK: u64 = 0x0101_0101_0101_0100;
+        let rooks = Bitboard::new(0x81); // a1 and h1
+        let attacks = rook(rooks, Bitboard::new(0), Bitboard::new(0)).bits;
+        assert_eq!(
+            attacks & (A_FILE_ABOVE_FIRST_RANK | H_FILE_ABOVE_FIRST_RANK),
+            A_FILE_ABOVE_FIRST_RANK | H_FILE_ABOVE_FIRST_RANK
+        );
+
+        let board = Board::from_forsyth_edwards("4k3/8/8/8/8/8/3K4/R6R w - - 0 1").unwrap();
+        let attacked = board.attacked_squares(Color::White).bits;
+        assert_eq!(
+            attacked & (A_FILE_ABOVE_FIRST_RANK | H_FILE_ABOVE_FIRST_RANK),
+            A_FILE_ABOVE_FIRST_RANK | H_FILE_ABOVE_FIRST_RANK
+        );
+    }
+
+    fn squares(names: &str) -> u64 {
+        names.split_whitespace().map(sq).fold(0, |acc, b| acc | b)
+    }
+
+    #[test]
+    fn stacked_rooks_and_crossing_bishops_attack_every_ray() {
+        magic::init();
+        // Two rooks on the a-file: the lower one stops at the upper one.
+        let rooks = Bitboard::new(squares("a1 a5"));
+        assert_eq!(
+            rook(rooks, rooks, Bitboard::new(0)).bits,
+            squares("a2 a3 a4 a6 a7 a8 b1 c1 d1 e1 f1 g1 h1 b5 c5 d5 e5 f5 g5 h5")
+        );
+
+        // Bishops on c1 and g1, their diagonals crossing on e3.
+        let bishops = Bitboard::new(squares("c1 g1"));
+        assert_eq!(
+            bishop(bishops, bishops, Bitboard::new(0)).bits,
+            squares("b2 a3 d2 e3 f4 g5 h6 h2 f2 d4 c5 b6 a7")
+        );
+
+        // The check comes from the lower of the two rooks.
+        let board = Board::from_forsyth_edwards("8/8/8/R7/8/8/8/R2k3K b - - 0 1").unwrap();
+        assert!(board.position.is_in_check(Color::Black));
+    }
+
+    /// Slow reference generator: walks an 8x8 array of squares one step at a
+    /// time, so no shift can wrap around an edge.
+    mod mailbox {
+        use super::{file, rank};
+
+        #[derive(Clone, Copy, PartialEq)]
+        enum Cell {
+            Empty,
+            Own,
+            Enemy,
+        }
+
+        pub struct Mailbox {
+            cells: [[Cell; 8]; 8],
+        }
+
+        pub const KNIGHT: &[(i32, i32)] = &[
+            (1, 2),
+            (2, 1),
+            (2, -1),
+            (1, -2),
+            (-1, -2),
+            (-2, -1),
+            (-2, 1),
+            (-1, 2),
+        ];
+        pub const KING: &[(i32, i32)] = &[
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+        ];
+        pub const BISHOP: &[(i32, i32)] = &[(1, 1), (-1, 1), (-1, -1), (1, -1)];
+        pub const ROOK: &[(i32, i32)] = &[(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+        fn bit(file: i32, rank: i32) -> u64 {
+            1 << (rank * 8 + 7 - file)
+        }
+
+        impl Mailbox {
+            pub fn new(own: u64, enemies: u64) -> Self {
+                let mut cells = [[Cell::Empty; 8]; 8];
+                for (r, row) in cells.iter_mut().enumerate() {
+                    for (f, cell) in row.iter_mut().enumerate() {
+                        let b = bit(f as i32, r as i32);
+                        if own & b != 0 {
+                            *cell = Cell::Own;
+                        } else if enemies & b != 0 {
+                            *cell = Cell::Enemy;
+                        }
+                    }
+                }
+
+                Self { cells }
+            }
+
+            fn at(&self, file: i32, rank: i32) -> Option<Cell> {
+                if (0..8).contains(&file) && (0..8).contains(&rank) {
+                    Some(self.cells[rank as usize][file as usize])
+                } else {
+                    None
+                }
+            }
+
+            /// Squares reached from every piece in `pieces`. Sliders keep going
+            /// until they leave the board or hit a piece, which they may take
+            /// only if it is an enemy.
+            pub fn moves(&self, pieces: u64, steps: &[(i32, i32)], slides: bool) -> u64 {
+                let mut result = 0;
+                for sq in (0..64u8).filter(|sq| pieces & 1 << sq != 0) {
+                    for (df, dr) in steps {
+                        let (mut f, mut r) = (file(sq) + df, rank(sq) + dr);
+                        while let Some(cell) = self.at(f, r) {
+                            if cell != Cell::Own {
+                                result |= bit(f, r);
+                            }
+                            if cell != Cell::Empty || !slides {
+                                break;
+                            }
+                            f += df;
+                            r += dr;
+                        }
+                    }
+                }
+
+                result
+            }
+
+            /// Pushes and captures of pawns advancing by `dir` ranks.
+            pub fn pawn_moves(&self, pawns: u64, dir: i32) -> u64 {
+                let start_rank = if dir > 0 { 1 } else { 6 };
+                let mut result = 0;
+                for sq in (0..64u8).filter(|sq| pawns & 1 << sq != 0) {
+                    let (f, r) = (file(sq), rank(sq));
+                    if self.at(f, r + dir) == Some(Cell::Empty) {
+                        result |= bit(f, r + dir);
+                        if r == start_rank && self.at(f, r + 2 * dir) == Some(Cell::Empty) {
+                            result |= bit(f, r + 2 * dir);
+                        }
+                    }
+                    for df in [-1, 1] {
+                        if self.at(f + df, r + dir) == Some(Cell::Enemy) {
+                            result |= bit(f + df, r + dir);
+                        }
+                    }
+                }
+
+                result
+            }
+        }
+    }
+
+    mod properties {
+        use super::mailbox::{self, Mailbox};
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Random bitboards with about a quarter of the squares set.
+        fn sparse() -> impl Strategy<Value = u64> {
+            (any::<u64>(), any::<u64>()).prop_map(|(a, b)| a & b)
+        }
+
+        proptest! {
+            #[test]
+            fn generators_match_the_mailbox_reference(
+                pieces in sparse(),
+                own in sparse(),
+                enemies in sparse(),
+            ) {
+                magic::init();
+                let own = own | pieces;
+                let enemies = enemies & !own;
+                let occupied = own | enemies;
+                let (p, o, e) = (Bitboard::new(pieces), Bitboard::new(own), Bitboard::new(enemies));
+
+                // Moves: own pieces block, enemy pieces can be taken.
+                // Attacks: every piece on the board is a target.
+                let board = Mailbox::new(own, enemies);
+                let targets = Mailbox::new(0, occupied);
+                let leapers_and_sliders = [
+                    (knight as fn(Bitboard, Bitboard, Bitboard) -> Bitboard, mailbox::KNIGHT, false),
+                    (king, mailbox::KING, false),
+                    (bishop, mailbox::BISHOP, true),
+                    (rook, mailbox::ROOK, true),
+                ];
+                for (generator, steps, slides) in leapers_and_sliders {
+                    prop_assert_eq!(generator(p, o, e).bits, board.moves(pieces, steps, slides));
+                    prop_assert_eq!(
+                        generator(p, Bitboard::new(0), Bitboard::new(occupied)).bits,
+                        targets.moves(pieces, steps, slides)
+                    );
+                }
+                let queen_steps = [mailbox::BISHOP, mailbox::ROOK].concat();
+                prop_assert_eq!(queen(p, o, e).bits, board.moves(pieces, &queen_steps, true));
+
+                let all = Bitboard::new(occupied);
+                prop_assert_eq!(white_pawn(p, all, e).bits, board.pawn_moves(pieces, 1));
+                prop_assert_eq!(black_pawn(p, all, e).bits, board.pawn_moves(pieces, -1));
+
+                // With every square taken pawns cannot push: only captures are left.
+                let everything = Bitboard::new(u64::MAX);
+                let full = Mailbox::new(0, u64::MAX);
+                prop_assert_eq!(
+                    white_pawn_attack(p, Bitboard::new(0), everything).bits,
+                    full.pawn_moves(pieces, 1)
+                );
+                prop_assert_eq!(
+                    black_pawn_attack(p, Bitboard::new(0), everything).bits,
+                    full.pawn_moves(pieces, -1)
+                );
+            }
+        }
+    }
+}
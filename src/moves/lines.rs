@@ -0,0 +1,122 @@
+//! Squares between and through pairs of aligned squares.
+//!
+//! Both tables are indexed by two square indices (0 = h1, 63 = a8) and are
+//! computed at compile time, so they need no initialization.
+
+/// Squares strictly between two squares on a common line, 0 otherwise.
+pub static BETWEEN: [[u64; 64]; 64] = build(false);
+
+/// The full line through two squares, both included, 0 if they are not aligned.
+pub static LINE: [[u64; 64]; 64] = build(true);
+
+pub fn between(a: u8, b: u8) -> u64 {
+    BETWEEN[a as usize][b as usize]
+}
+
+pub fn line(a: u8, b: u8) -> u64 {
+    LINE[a as usize][b as usize]
+}
+
+/// Returns true if the three squares stand on one rank, file or diagonal.
+pub fn aligned(a: u8, b: u8, c: u8) -> bool {
+    line(a, b) & (1u64 << c) != 0
+}
+
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, 1),
+    (0, -1),
+    (1, 0),
+    (-1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+/// Every square reached from `sq` walking in one direction, `sq` excluded.
+const fn ray(sq: usize, (rank_step, file_step): (i32, i32)) -> u64 {
+    let mut result = 0;
+    let mut rank = (sq / 8) as i32 + rank_step;
+    let mut file = (sq % 8) as i32 + file_step;
+    while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+        result |= 1u64 << (rank * 8 + file);
+        rank += rank_step;
+        file += file_step;
+    }
+    result
+}
+
+const fn build(full_line: bool) -> [[u64; 64]; 64] {
+    let mut table = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut d = 0;
+        while d < DIRECTIONS.len() {
+            let (rank_step, file_step) = DIRECTIONS[d];
+            let line =
+                ray(a, (rank_step, file_step)) | ray(a, (-rank_step, -file_step)) | 1u64 << a;
+            // Walk the ray, collecting the squares passed so far.
+            let mut passed = 0;
+            let mut rank = (a / 8) as i32 + rank_step;
+            let mut file = (a % 8) as i32 + file_step;
+            while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+                let b = (rank * 8 + file) as usize;
+                table[a][b] = if full_line { line } else { passed };
+                passed |= 1u64 << b;
+                rank += rank_step;
+                file += file_step;
+            }
+            d += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::notation::parse_square;
+
+    fn sq(name: &str) -> u8 {
+        parse_square(name).unwrap()
+    }
+
+    fn squares(names: &str) -> u64 {
+        names
+            .split_whitespace()
+            .fold(0, |acc, name| acc | 1 << sq(name))
+    }
+
+    #[test]
+    fn between_and_line_on_known_pairs() {
+        let cases = [
+            // rank
+            ("a1", "e1", "b1 c1 d1", "a1 b1 c1 d1 e1 f1 g1 h1"),
+            // file
+            ("d8", "d3", "d7 d6 d5 d4", "d1 d2 d3 d4 d5 d6 d7 d8"),
+            // diagonal
+            ("b2", "f6", "c3 d4 e5", "a1 b2 c3 d4 e5 f6 g7 h8"),
+            // anti-diagonal
+            ("h3", "c8", "g4 f5 e6 d7", "c8 d7 e6 f5 g4 h3"),
+            // neighbours
+            ("e4", "f5", "", "b1 c2 d3 e4 f5 g6 h7"),
+        ];
+        for (a, b, between_squares, line_squares) in cases {
+            assert_eq!(between(sq(a), sq(b)), squares(between_squares), "{a}-{b}");
+            assert_eq!(between(sq(b), sq(a)), squares(between_squares), "{b}-{a}");
+            assert_eq!(line(sq(a), sq(b)), squares(line_squares), "{a}-{b}");
+            assert_eq!(line(sq(b), sq(a)), squares(line_squares), "{b}-{a}");
+        }
+
+        for (a, b) in [("a1", "b3"), ("e4", "e4"), ("h1", "a2"), ("c1", "h7")] {
+            assert_eq!(between(sq(a), sq(b)), 0, "{a}-{b}");
+            assert_eq!(line(sq(a), sq(b)), 0, "{a}-{b}");
+        }
+
+        assert!(aligned(sq("a1"), sq("h8"), sq("d4")));
+        assert!(aligned(sq("e1"), sq("e8"), sq("e1")));
+        assert!(!aligned(sq("a1"), sq("h8"), sq("d5")));
+        assert!(!aligned(sq("a1"), sq("b3"), sq("c5")));
+    }
+}
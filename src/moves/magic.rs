@@ -297,4 +297,57 @@ mod tests {
             }
         }
     }
+
+    /// Every subset of every square's relevant mask, i.e. every distinct
+    /// table entry, matches the slow ray casting.
+    #[test]
+    fn magic_matches_slow_on_every_relevant_occupancy() {
+        init();
+        for sq in 0u8..64 {
+            for (mask, slow, fast) in [
+                (
+                    rook_mask(sq),
+                    rook_attacks_slow as fn(u8, u64) -> u64,
+                    rook_attacks as fn(u8, u64) -> u64,
+                ),
+                (bishop_mask(sq), bishop_attacks_slow, bishop_attacks),
+            ] {
+                let mut occ = 0u64;
+                loop {
+                    assert_eq!(fast(sq, occ), slow(sq, occ), "sq={sq} occ={occ:#018x}");
+                    occ = occ.wrapping_sub(mask) & mask;
+                    if occ == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Same check over many random occupancies per square, from sparse
+    /// (late endgame) to dense (opening) boards.
+    #[test]
+    fn magic_matches_slow_on_random_occupancies() {
+        init();
+        let mut state = 0x9E3779B97F4A7C15u64;
+        for sq in 0u8..64 {
+            for i in 0..2000 {
+                let occ = match i % 3 {
+                    0 => xorshift64(&mut state) & xorshift64(&mut state),
+                    1 => xorshift64(&mut state),
+                    _ => xorshift64(&mut state) | xorshift64(&mut state),
+                };
+                assert_eq!(
+                    rook_attacks(sq, occ),
+                    rook_attacks_slow(sq, occ),
+                    "rook sq={sq} occ={occ:#018x}"
+                );
+                assert_eq!(
+                    bishop_attacks(sq, occ),
+                    bishop_attacks_slow(sq, occ),
+                    "bishop sq={sq} occ={occ:#018x}"
+                );
+            }
+        }
+    }
 }
@@ -1,4 +1,9 @@
 pub mod generate;
 pub mod generators;
+pub mod lines;
 pub mod magic;
 pub mod move_type;
+pub mod notation;
+pub mod packed;
+pub mod pgn;
+pub mod picker;
@@ -1,18 +1,39 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64},
+};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+
 use crate::components::{
     board::Board,
-    castle::CastleSide,
+    castle::{self, CastleSide},
     constants,
     pieces::{Piece, PieceKind},
 };
+use crate::evaluator::{
+    config::SearchConfig,
+    stats::SearchStats,
+    stop::{PonderHandle, StopHandle},
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+use super::packed::PackedMove;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum MoveKind {
     Standard {
         from: u8,
         to: u8,
         captured: Option<Piece>,
     },
-    Castle(CastleSide),
+    /// Castling with the rook on `rook_from`. The king and rook always land on
+    /// the standard squares; where they start differs in Chess960.
+    Castle {
+        side: CastleSide,
+        king_from: u8,
+        rook_from: u8,
+    },
     Promote {
         from: u8,
         to: u8,
@@ -21,19 +42,43 @@ pub enum MoveKind {
     },
     /// En passant capture: the capturing pawn moves from `from` to `to`,
     /// and the captured pawn (sitting one rank behind `to`) is removed.
-    EnPassant {
-        from: u8,
-        to: u8,
-    },
+    EnPassant { from: u8, to: u8 },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Move {
     pub piece: Piece,
     pub action: MoveKind,
 }
 
 impl Move {
+    /// Square the moving piece leaves. For castling this is the king's square.
+    pub fn from_square(&self) -> u8 {
+        match self.action {
+            MoveKind::Standard { from, .. }
+            | MoveKind::Promote { from, .. }
+            | MoveKind::EnPassant { from, .. } => from,
+            MoveKind::Castle { king_from, .. } => king_from,
+        }
+    }
+
+    /// Square the moving piece lands on. For castling this is the king's square.
+    pub fn to_square(&self) -> u8 {
+        match self.action {
+            MoveKind::Standard { to, .. }
+            | MoveKind::Promote { to, .. }
+            | MoveKind::EnPassant { to, .. } => to,
+            MoveKind::Castle { side, .. } => castle::castle_destinations(self.piece.color, side).0,
+        }
+    }
+
+    pub fn promotion(&self) -> Option<PieceKind> {
+        match self.action {
+            MoveKind::Promote { to_piece, .. } => Some(to_piece),
+            _ => None,
+        }
+    }
+
     pub fn is_promotion(&self) -> bool {
         match self.action {
             MoveKind::Standard { to, .. } => {
@@ -41,14 +86,25 @@ impl Move {
                     && ((1 << to) & constants::EIGHT_ROW != 0
                         || (1 << to) & constants::FIRST_ROW != 0)
             }
-            MoveKind::Castle(_) | MoveKind::EnPassant { .. } => false,
+            MoveKind::Castle { .. } | MoveKind::EnPassant { .. } => false,
             MoveKind::Promote { .. } => true,
         }
     }
 
+    /// Kind of the piece this move captures, if any. En passant captures a pawn.
+    pub fn captured_kind(&self) -> Option<PieceKind> {
+        match self.action {
+            MoveKind::Castle { .. } => None,
+            MoveKind::EnPassant { .. } => Some(PieceKind::Pawn),
+            MoveKind::Standard { captured, .. } | MoveKind::Promote { captured, .. } => {
+                captured.map(|p| p.kind)
+            }
+        }
+    }
+
     pub fn is_capture(&self) -> bool {
         match self.action {
-            MoveKind::Castle(_) => false,
+            MoveKind::Castle { .. } => false,
             MoveKind::EnPassant { .. } => true,
             MoveKind::Standard { captured, .. } | MoveKind::Promote { captured, .. } => {
                 captured.is_some()
@@ -60,10 +116,155 @@ impl Move {
 #[derive(Debug, Clone)]
 pub struct Scenario {
     pub board: Board,
+    pub config: SearchConfig,
+    pub stats: SearchStats,
+    /// Move counter of the position the search started from, used to know the ply.
+    pub(crate) root_moves_count: u32,
+    /// Plies of extension applied on the line currently searched.
+    pub(crate) extensions: i32,
+    /// Two quiet moves per ply that recently caused a beta cutoff.
+    pub(crate) killers: Vec<[Option<PackedMove>; 2]>,
+    /// Hashes of the positions played before `board`, oldest first: the game
+    /// leading to the search and then the line being searched. A position
+    /// found in it again is scored as a draw.
+    pub history: Vec<u64>,
+    /// Time at which the search gives up. Checked every thousand nodes or so.
+    pub(crate) deadline: Option<Instant>,
+    /// Nodes after which the search gives up.
+    pub(crate) node_limit: Option<u64>,
+    /// Nodes searched by all the threads, counted a thousand or so at a time.
+    pub(crate) shared_nodes: Arc<AtomicU64>,
+    /// Set when the search ran out of time or nodes or was stopped, shared by
+    /// the copies searching the root moves. Once set, the scores returned are
+    /// meaningless.
+    pub(crate) aborted: Arc<AtomicBool>,
+    /// Stops the search from another thread.
+    pub(crate) stop: StopHandle,
+    /// Whether `stop` is honoured yet: not before a first move is found.
+    pub(crate) stop_armed: bool,
+    /// Turns a ponder search into the real one from another thread.
+    pub(crate) ponder: PonderHandle,
+    /// Time the search may take from the ponderhit on, set while pondering:
+    /// the deadline is only known once the hit comes.
+    pub(crate) ponder_budget: Option<Duration>,
+    /// Root moves the search is restricted to, every legal move when None.
+    pub(crate) searchmoves: Option<Vec<Move>>,
 }
 
 impl Scenario {
     pub fn new(board: Board) -> Self {
-        Self { board }
+        Self::with_config(board, SearchConfig::default())
+    }
+
+    pub fn with_config(board: Board, config: SearchConfig) -> Self {
+        Self {
+            root_moves_count: board.moves_count,
+            board,
+            config,
+            stats: SearchStats::default(),
+            extensions: 0,
+            killers: Vec::new(),
+            history: Vec::new(),
+            deadline: None,
+            node_limit: None,
+            shared_nodes: Arc::new(AtomicU64::new(0)),
+            aborted: Arc::new(AtomicBool::new(false)),
+            stop: StopHandle::new(),
+            stop_armed: false,
+            ponder: PonderHandle::new(),
+            ponder_budget: None,
+            searchmoves: None,
+        }
+    }
+
+    /// Sets the hashes of the positions the game went through before `board`.
+    pub fn with_history(mut self, history: Vec<u64>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Lets `stop` end the search from another thread.
+    pub fn with_stop_handle(mut self, stop: StopHandle) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// A handle stopping the search of this scenario and of its clones.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// Lets `ponder` turn a ponder search into the real one from another thread.
+    pub fn with_ponder_handle(mut self, ponder: PonderHandle) -> Self {
+        self.ponder = ponder;
+        self
+    }
+
+    /// Restricts the search to the root moves in `moves`, as for UCI
+    /// `go searchmoves`. The moves that are not legal are left out; fails if
+    /// none of them is.
+    pub fn with_searchmoves(mut self, moves: &[Move]) -> Result<Self, anyhow::Error> {
+        let legal: Vec<Move> = self
+            .board
+            .legal_moves()
+            .into_iter()
+            .filter(|m| moves.contains(m))
+            .collect();
+        if legal.is_empty() {
+            return Err(anyhow!("none of the searchmoves is legal"));
+        }
+        self.searchmoves = Some(legal);
+        Ok(self)
+    }
+
+    /// The root moves the search is restricted to, None if it is not.
+    pub fn searchmoves(&self) -> Option<&[Move]> {
+        self.searchmoves.as_deref()
+    }
+
+    /// [`Scenario::with_searchmoves`] with the moves in UCI long algebraic notation.
+    pub fn with_uci_searchmoves(self, moves: &[&str]) -> Result<Self, anyhow::Error> {
+        let moves: Vec<Move> = moves
+            .iter()
+            .filter_map(|uci| self.board.parse_uci_move(uci))
+            .collect();
+        self.with_searchmoves(&moves)
+    }
+
+    /// A handle sending the ponderhit to the search of this scenario and of its clones.
+    pub fn ponder_handle(&self) -> PonderHandle {
+        self.ponder.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{components::board::Board, moves::magic};
+
+    #[test]
+    fn moves_compare_and_hash_by_value() {
+        magic::init();
+        let board = Board::from_forsyth_edwards(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let moves = board.legal_moves();
+        let again = board.legal_moves();
+
+        let set: HashSet<_> = moves.iter().chain(&again).copied().collect();
+        assert_eq!(set.len(), moves.len());
+        assert!(again.iter().all(|m| moves.contains(m) && set.contains(m)));
+
+        // The same squares with another captured piece or action differ.
+        let castle = moves.iter().find(|m| m.to_uci() == "e1g1").unwrap();
+        let king_step = moves.iter().find(|m| m.to_uci() == "e1f1").unwrap();
+        assert_ne!(castle, king_step);
+        let mut capture = *moves.iter().find(|m| m.is_capture()).unwrap();
+        if let super::MoveKind::Standard { captured, .. } = &mut capture.action {
+            *captured = None;
+        }
+        assert!(!set.contains(&capture));
     }
 }
@@ -0,0 +1,390 @@
+use anyhow::anyhow;
+
+use crate::components::{
+    board::Board,
+    castle::{self, CastleSide},
+    pieces::{Color, Piece, PieceKind},
+};
+
+use super::move_type::{Move, MoveKind};
+
+/// Square index (0 - 63) -> UCI square string (e.g. 3 -> e1).
+pub fn square_name(sq: u8) -> String {
+    let file = 7 - (sq % 8);
+    let rank = sq / 8;
+
+    format!("{}{}", (b'a' + file) as char, (b'1' + rank) as char)
+}
+
+/// UCI square string -> square index (e.g. "e1" → 3). Returns None on invalid input.
+pub fn parse_square(s: &str) -> Option<u8> {
+    let b = s.as_bytes();
+    if b.len() < 2 {
+        return None;
+    }
+    let file = b[0].checked_sub(b'a').filter(|&f| f < 8)?;
+    let rank = b[1].checked_sub(b'1').filter(|&r| r < 8)?;
+
+    Some(rank * 8 + (7 - file))
+}
+
+impl Move {
+    /// Converts the move to its UCI string (e.g. "e2e4", "e7e8q", "e1g1").
+    /// Chess960 castling is written as the king taking its own rook (e.g. "f1h1").
+    pub fn to_uci(&self) -> String {
+        match &self.action {
+            MoveKind::Standard { from, to, .. } | MoveKind::EnPassant { from, to } => {
+                format!("{}{}", square_name(*from), square_name(*to))
+            }
+            MoveKind::Promote {
+                from, to, to_piece, ..
+            } => {
+                let promo = match to_piece {
+                    PieceKind::Queen => 'q',
+                    PieceKind::Rook => 'r',
+                    PieceKind::Bishop => 'b',
+                    PieceKind::Knight => 'n',
+                    _ => 'q',
+                };
+                format!("{}{}{}", square_name(*from), square_name(*to), promo)
+            }
+            // Chess960 castling is written as the king taking its own rook.
+            MoveKind::Castle { .. } => {
+                let (king_from, king_to, rook_from, _) = castle::castle_squares(self);
+                let to = if castle::is_standard_castle(king_from, rook_from) {
+                    king_to
+                } else {
+                    rook_from
+                };
+                format!("{}{}", square_name(king_from), square_name(to))
+            }
+        }
+    }
+}
+
+impl Board {
+    /// Parses a UCI move string into a legal Move for this position.
+    /// Returns None if the move string is malformed or the move is not legal.
+    pub fn parse_uci_move(&self, uci: &str) -> Option<Move> {
+        if uci.len() < 4 {
+            return None;
+        }
+        let from = parse_square(&uci[0..2])?;
+        let to = parse_square(&uci[2..4])?;
+        let promo = uci.as_bytes().get(4).and_then(|&b| match b {
+            b'q' => Some(PieceKind::Queen),
+            b'r' => Some(PieceKind::Rook),
+            b'b' => Some(PieceKind::Bishop),
+            b'n' => Some(PieceKind::Knight),
+            _ => None,
+        });
+
+        let moves = self.generate_moves(false);
+        moves.iter(self).find(|m| match m.action {
+            MoveKind::Standard { from: f, to: t, .. } => f == from && t == to && promo.is_none(),
+            MoveKind::EnPassant { from: f, to: t } => f == from && t == to,
+            MoveKind::Promote {
+                from: f,
+                to: t,
+                to_piece,
+                ..
+            } => f == from && t == to && promo.is_none_or(|p| p == to_piece),
+            MoveKind::Castle { .. } => {
+                let (king_from, king_to, rook_from, _) = castle::castle_squares(m);
+                from == king_from
+                    && (to == rook_from
+                        || to == king_to && castle::is_standard_castle(king_from, rook_from))
+            }
+        })
+    }
+
+    /// Converts the legal move `m` to Standard Algebraic Notation
+    /// (e.g. "Nf3", "exd5", "O-O", "e8=Q+", "Qh4#").
+    /// The origin file, rank or both are added only when another piece of the
+    /// same kind can reach the same square.
+    pub fn move_to_san(&self, m: &Move) -> String {
+        let mut san = match m.action {
+            MoveKind::Castle {
+                side: CastleSide::King,
+                ..
+            } => "O-O".to_string(),
+            MoveKind::Castle {
+                side: CastleSide::Queen,
+                ..
+            } => "O-O-O".to_string(),
+            _ => {
+                let (from, to) = (m.from_square(), m.to_square());
+                let from_name = square_name(from);
+                let mut san = String::new();
+
+                if m.piece.kind == PieceKind::Pawn {
+                    if m.is_capture() {
+                        san.push_str(&from_name[..1]);
+                    }
+                } else {
+                    san.push(char::from(Piece::new(Color::White, m.piece.kind)));
+
+                    let moves = self.generate_moves(false);
+                    let rivals: Vec<u8> = moves
+                        .iter(self)
+                        .filter(|other| {
+                            other.piece == m.piece
+                                && !matches!(other.action, MoveKind::Castle { .. })
+                                && other.to_square() == to
+                                && other.from_square() != from
+                        })
+                        .map(|other| other.from_square())
+                        .collect();
+                    if !rivals.is_empty() {
+                        let same_file = rivals.iter().any(|&sq| sq % 8 == from % 8);
+                        let same_rank = rivals.iter().any(|&sq| sq / 8 == from / 8);
+                        if !same_file {
+                            san.push_str(&from_name[..1]);
+                        } else if !same_rank {
+                            san.push_str(&from_name[1..]);
+                        } else {
+                            san.push_str(&from_name);
+                        }
+                    }
+                }
+
+                if m.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(&square_name(to));
+                if let Some(promoted) = m.promotion() {
+                    san.push('=');
+                    san.push(char::from(Piece::new(Color::White, promoted)));
+                }
+                san
+            }
+        };
+
+        let after = self.make_unchecked_move(m);
+        if after.position.is_in_check(after.turn) {
+            san.push(if after.generate_moves(false).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        san
+    }
+
+    /// Resolves a SAN token (e.g. "Nbd7", "exd5", "e8=Q+", "O-O") to the legal
+    /// move it names in this position. Check and annotation suffixes are ignored.
+    ///
+    /// # Errors
+    /// Fails if the token is malformed, names no legal move or is ambiguous.
+    pub fn parse_san(&self, san: &str) -> Result<Move, anyhow::Error> {
+        let token = san.trim_end_matches(['+', '#', '!', '?']);
+        let castle_side = match token {
+            "O-O" | "0-0" => Some(CastleSide::King),
+            "O-O-O" | "0-0-0" => Some(CastleSide::Queen),
+            _ => None,
+        };
+
+        let (kind, rest) = match token.chars().next() {
+            Some('N') => (PieceKind::Knight, &token[1..]),
+            Some('B') => (PieceKind::Bishop, &token[1..]),
+            Some('R') => (PieceKind::Rook, &token[1..]),
+            Some('Q') => (PieceKind::Queen, &token[1..]),
+            Some('K') => (PieceKind::King, &token[1..]),
+            _ => (PieceKind::Pawn, token),
+        };
+        let (rest, promotion) = match rest.as_bytes() {
+            [.., b'=', p] | [.., b'1'..=b'8', p @ (b'N' | b'B' | b'R' | b'Q')] => {
+                let promoted = match p {
+                    b'N' => PieceKind::Knight,
+                    b'B' => PieceKind::Bishop,
+                    b'R' => PieceKind::Rook,
+                    b'Q' => PieceKind::Queen,
+                    _ => return Err(anyhow!("invalid promotion in SAN move: {}", san)),
+                };
+                (rest[..rest.len() - 1].trim_end_matches('='), Some(promoted))
+            }
+            _ => (rest, None),
+        };
+
+        let moves = self.generate_moves(false);
+        let candidates: Vec<Move> = if let Some(side) = castle_side {
+            moves
+                .iter(self)
+                .filter(|m| matches!(m.action, MoveKind::Castle { side: s, .. } if s == side))
+                .collect()
+        } else {
+            if rest.len() < 2 || !rest.is_char_boundary(rest.len() - 2) {
+                return Err(anyhow!("malformed SAN move: {}", san));
+            }
+            let (origin, to) = rest.split_at(rest.len() - 2);
+            let to = parse_square(to).ok_or_else(|| anyhow!("malformed SAN move: {}", san))?;
+            let origin = origin.trim_end_matches('x');
+            let (mut from_file, mut from_rank) = (None, None);
+            for c in origin.bytes() {
+                match c {
+                    b'a'..=b'h' if from_file.is_none() => from_file = Some(7 - (c - b'a')),
+                    b'1'..=b'8' if from_rank.is_none() => from_rank = Some(c - b'1'),
+                    _ => return Err(anyhow!("malformed SAN move: {}", san)),
+                }
+            }
+
+            moves
+                .iter(self)
+                .filter(|m| {
+                    m.piece.kind == kind
+                        && !matches!(m.action, MoveKind::Castle { .. })
+                        && m.to_square() == to
+                        && m.promotion() == promotion
+                        && from_file.is_none_or(|f| m.from_square() % 8 == f)
+                        && from_rank.is_none_or(|r| m.from_square() / 8 == r)
+                })
+                .collect()
+        };
+
+        match candidates.as_slice() {
+            [m] => Ok(*m),
+            [] => Err(anyhow!("illegal SAN move: {}", san)),
+            _ => Err(anyhow!("ambiguous SAN move: {}", san)),
+        }
+    }
+
+    /// Plays the UCI move `uci` on a copy of the board.
+    /// Fails, leaving the board untouched, if the move is malformed or illegal.
+    pub fn try_make_move(&self, uci: &str) -> Result<Board, anyhow::Error> {
+        self.parse_uci_move(uci)
+            .map(|m| self.make_unchecked_move(&m))
+            .ok_or_else(|| anyhow!("illegal move: {}", uci))
+    }
+
+    /// Plays a `position ... moves` list in order. If a move cannot be played,
+    /// returns the position reached before it together with its index in `moves`.
+    pub fn play_uci_moves(&self, moves: &[&str]) -> (Board, Option<usize>) {
+        let (board, _, failed) = self.play_uci_moves_with_history(moves);
+        (board, failed)
+    }
+
+    /// [`Board::play_uci_moves`], also returning the hashes of the positions
+    /// the moves went through before the one reached, oldest first.
+    pub fn play_uci_moves_with_history(&self, moves: &[&str]) -> (Board, Vec<u64>, Option<usize>) {
+        let mut board = self.clone();
+        let mut history = Vec::with_capacity(moves.len());
+        for (i, uci) in moves.iter().enumerate() {
+            match board.try_make_move(uci) {
+                Ok(next) => {
+                    history.push(board.hash);
+                    board = next;
+                }
+                Err(_) => return (board, history, Some(i)),
+            }
+        }
+
+        (board, history, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::magic;
+
+    #[test]
+    fn illegal_moves_stop_the_move_list() {
+        magic::init();
+        let start = Board::new_game();
+        assert!(start.try_make_move("e2e5").is_err());
+        assert!(start.try_make_move("e7e5").is_err());
+        assert!(start.try_make_move("e2").is_err());
+
+        // The 5th move is illegal: the position after the 4th is kept.
+        let (board, failed) = start.play_uci_moves(&["e2e4", "e7e5", "g1f3", "b8c6", "f1f3"]);
+        assert_eq!(failed, Some(4));
+        let after_four = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        assert_eq!(board.to_forsyth_edwards(), after_four);
+
+        let (board, failed) = start.play_uci_moves(&["e2e4", "e7e5"]);
+        assert_eq!(failed, None);
+        assert_eq!(board.turn, Color::White);
+    }
+
+    fn san_of(board: &Board, uci: &str) -> String {
+        board.move_to_san(&board.parse_uci_move(uci).unwrap())
+    }
+
+    #[test]
+    fn san_disambiguates_by_file_rank_or_both() {
+        magic::init();
+        // Queens on a1, a3 and c1 can all reach b2.
+        let board = Board::from_forsyth_edwards("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1").unwrap();
+        assert_eq!(san_of(&board, "a1b2"), "Qa1b2");
+        assert_eq!(san_of(&board, "a3b2"), "Q3b2");
+        assert_eq!(san_of(&board, "c1b2"), "Qcb2");
+        assert_eq!(san_of(&board, "e1d2"), "Kd2");
+
+        let start = Board::new_game();
+        assert_eq!(san_of(&start, "g1f3"), "Nf3");
+        assert_eq!(san_of(&start, "e2e4"), "e4");
+        let (board, _) = start.play_uci_moves(&["e2e4", "d7d5"]);
+        assert_eq!(san_of(&board, "e4d5"), "exd5");
+    }
+
+    #[test]
+    fn san_writes_castling_promotions_and_checks() {
+        magic::init();
+        let board =
+            Board::from_forsyth_edwards("r3k2r/pppq1ppp/8/8/8/8/PPPQ1PPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        assert_eq!(san_of(&board, "e1g1"), "O-O");
+        assert_eq!(san_of(&board, "e1c1"), "O-O-O");
+        assert_eq!(san_of(&board, "d2d7"), "Qxd7+");
+
+        let board = Board::from_forsyth_edwards("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(san_of(&board, "e7e8q"), "e8=Q+");
+        assert_eq!(san_of(&board, "e7d8n"), "exd8=N");
+
+        let (board, _) = Board::new_game().play_uci_moves(&["f2f3", "e7e5", "g2g4"]);
+        assert_eq!(san_of(&board, "d8h4"), "Qh4#");
+    }
+
+    #[test]
+    fn san_round_trips_through_parse_san() {
+        magic::init();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1",
+            "3r3k/4P3/8/8/8/8/8/K7 w - - 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let moves = board.generate_moves(false);
+            for m in moves.iter(&board) {
+                let san = board.move_to_san(&m);
+                let parsed = board.parse_san(&san).unwrap();
+                assert_eq!(parsed.to_uci(), m.to_uci(), "{} in {}", san, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_san_rejects_ambiguous_and_illegal_moves() {
+        magic::init();
+        let board = Board::from_forsyth_edwards("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1").unwrap();
+        assert!(board.parse_san("Qb2").is_err());
+        assert!(board.parse_san("Qab2").is_err());
+        assert_eq!(board.parse_san("Qa1b2").unwrap().to_uci(), "a1b2");
+        assert_eq!(board.parse_san("Qcb2+").unwrap().to_uci(), "c1b2");
+
+        let start = Board::new_game();
+        assert!(start.parse_san("e5").is_err());
+        assert!(start.parse_san("O-O").is_err());
+        assert!(start.parse_san("Nf").is_err());
+        assert_eq!(start.parse_san("Nf3").unwrap().to_uci(), "g1f3");
+
+        let board = Board::from_forsyth_edwards("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert!(board.parse_san("e8").is_err());
+        assert_eq!(board.parse_san("exd8=N").unwrap().to_uci(), "e7d8n");
+        assert_eq!(board.parse_san("e8Q").unwrap().to_uci(), "e7e8q");
+    }
+}
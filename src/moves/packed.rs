@@ -0,0 +1,145 @@
+use crate::components::{board::Board, castle::CastleSide, pieces::PieceKind};
+
+use super::move_type::{Move, MoveKind};
+
+const PROMOTION: u16 = 1;
+const EN_PASSANT: u16 = 2;
+const CASTLE: u16 = 3;
+
+/// A move packed into 16 bits:
+///   bits 0-5 -> from square (the king's square for castling)
+///   bits 6-11 -> to square (the castling rook's square for castling)
+///   bits 12-13 -> promotion piece (knight, bishop, rook, queen)
+///   bits 14-15 -> flag: 0 standard, 1 promotion, 2 en passant, 3 castle
+///
+/// The moving and captured pieces are not stored: [`PackedMove::to_move`]
+/// reads them from the board the move was generated on.
+/// 0 is never a real move (from == to), which lets it mean "no move".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackedMove(u16);
+
+impl PackedMove {
+    pub fn new(m: &Move) -> Self {
+        let (from, to, promotion, flag) = match m.action {
+            MoveKind::Standard { from, to, .. } => (from, to, 0, 0),
+            MoveKind::Promote {
+                from, to, to_piece, ..
+            } => {
+                let promotion = match to_piece {
+                    PieceKind::Bishop => 1,
+                    PieceKind::Rook => 2,
+                    PieceKind::Queen => 3,
+                    _ => 0,
+                };
+                (from, to, promotion, PROMOTION)
+            }
+            MoveKind::EnPassant { from, to } => (from, to, 0, EN_PASSANT),
+            MoveKind::Castle {
+                king_from,
+                rook_from,
+                ..
+            } => (king_from, rook_from, 0, CASTLE),
+        };
+
+        PackedMove(from as u16 | (to as u16) << 6 | promotion << 12 | flag << 14)
+    }
+
+    pub(crate) fn from_bits(bits: u16) -> Self {
+        PackedMove(bits)
+    }
+
+    pub(crate) fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn from_square(self) -> u8 {
+        (self.0 & 0x3F) as u8
+    }
+
+    /// Destination square; for castling this is the rook's starting square.
+    pub fn to_square(self) -> u8 {
+        (self.0 >> 6 & 0x3F) as u8
+    }
+
+    /// Returns true if `m` is the move this packed move refers to.
+    pub fn matches(self, m: &Move) -> bool {
+        PackedMove::new(m) == self
+    }
+
+    /// Rebuilds the full move on `board`, the position it was generated on.
+    /// Returns None if there is no piece on the from square.
+    pub fn to_move(self, board: &Board) -> Option<Move> {
+        let (from, to) = (self.from_square(), self.to_square());
+        let piece = board.position.piece_at(from)?;
+        let action = match self.0 >> 14 {
+            PROMOTION => MoveKind::Promote {
+                from,
+                to,
+                to_piece: match self.0 >> 12 & 0b11 {
+                    0 => PieceKind::Knight,
+                    1 => PieceKind::Bishop,
+                    2 => PieceKind::Rook,
+                    _ => PieceKind::Queen,
+                },
+                captured: board.position.piece_at(to),
+            },
+            EN_PASSANT => MoveKind::EnPassant { from, to },
+            // Squares are numbered from h1: a rook with a lower file index
+            // than the king stands on its h-side.
+            CASTLE => MoveKind::Castle {
+                side: if to % 8 < from % 8 {
+                    CastleSide::King
+                } else {
+                    CastleSide::Queen
+                },
+                king_from: from,
+                rook_from: to,
+            },
+            _ => MoveKind::Standard {
+                from,
+                to,
+                captured: board.position.piece_at(to),
+            },
+        };
+
+        Some(Move { piece, action })
+    }
+}
+
+impl From<&Move> for PackedMove {
+    fn from(m: &Move) -> Self {
+        PackedMove::new(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::magic;
+
+    #[test]
+    fn every_generated_move_round_trips_through_the_packed_form() {
+        magic::init();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            "1r2k1r1/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let moves = board.generate_moves(false);
+            assert!(!moves.is_empty());
+            for m in moves.iter(&board) {
+                let packed = PackedMove::new(&m);
+                assert_ne!(packed, PackedMove::default(), "{}", m.to_uci());
+                let unpacked = packed.to_move(&board).unwrap();
+                assert_eq!(unpacked.piece, m.piece, "{} in {}", m.to_uci(), fen);
+                assert_eq!(unpacked.action, m.action, "{} in {}", m.to_uci(), fen);
+                assert_eq!(PackedMove::from_bits(packed.bits()), packed);
+            }
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use std::fmt;
+
+use anyhow::{Context, anyhow};
+
+use crate::components::{board::Board, pieces::Color};
+
+use super::move_type::Move;
+
+/// Game termination markers that end the movetext.
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// Replays a PGN game: the tag pairs are optional, and a `[FEN "..."]` tag
+/// replaces the standard start position.
+/// Returns every position of the game, the start position first.
+///
+/// Move numbers, `{ }` comments (nested ones too), `;` comments, `( )`
+/// variations and `$n` NAGs are skipped. A result token ends the game.
+///
+/// # Errors
+/// Fails on an invalid FEN tag, an unterminated comment, tag or variation,
+/// and on a SAN move that is malformed, ambiguous or illegal.
+pub fn replay_pgn(pgn: &str) -> Result<Vec<Board>, anyhow::Error> {
+    let tokens = tokenize(pgn)?;
+    let fen = tokens.iter().find_map(|token| match token {
+        Token::Tag("FEN", value) => Some(value),
+        _ => None,
+    });
+    let start = match fen {
+        Some(fen) => Board::from_forsyth_edwards(fen).context("invalid FEN tag")?,
+        None => Board::new_game(),
+    };
+
+    let mut boards = vec![start];
+    for token in tokens {
+        let Token::San(san) = token else {
+            continue;
+        };
+        if RESULTS.contains(&san) {
+            break;
+        }
+        let board = boards.last().expect("the start position is always there");
+        let m = board
+            .parse_san(san)
+            .with_context(|| format!("cannot play ply {} of the game", boards.len()))?;
+        boards.push(board.make_unchecked_move(&m));
+    }
+
+    Ok(boards)
+}
+
+/// Outcome of a game, displayed as its PGN result token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is not over (yet).
+    Ongoing,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        };
+        write!(f, "{}", token)
+    }
+}
+
+/// A game played from `start`, recorded move by move.
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub start: Board,
+    pub moves: Vec<Move>,
+}
+
+impl Game {
+    pub fn new(start: Board) -> Self {
+        Game {
+            start,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Records `m`, a legal move in the current position.
+    pub fn push(&mut self, m: Move) {
+        self.moves.push(m);
+    }
+
+    /// Every position of the game, the start position first.
+    pub fn boards(&self) -> Vec<Board> {
+        let mut boards = Vec::with_capacity(self.moves.len() + 1);
+        boards.push(self.start.clone());
+        for m in &self.moves {
+            let next = boards[boards.len() - 1].make_unchecked_move(m);
+            boards.push(next);
+        }
+
+        boards
+    }
+
+    /// Result decided by the final position: checkmate, stalemate, the
+    /// 50-move rule or a position repeated three times.
+    pub fn game_result(&self) -> GameResult {
+        let boards = self.boards();
+        let end = boards.last().expect("the start position is always there");
+        if end.generate_moves(false).is_empty() {
+            return match (end.position.is_in_check(end.turn), end.turn) {
+                (false, _) => GameResult::Draw,
+                (true, Color::White) => GameResult::BlackWins,
+                (true, Color::Black) => GameResult::WhiteWins,
+            };
+        }
+        let repetitions = boards.iter().filter(|b| b.hash == end.hash).count();
+        if end.reps_50 >= 100 || repetitions >= 3 {
+            return GameResult::Draw;
+        }
+
+        GameResult::Ongoing
+    }
+
+    /// Writes the game as PGN: the Seven Tag Roster with "?" placeholders,
+    /// `SetUp` and `FEN` tags when the game does not start from the standard
+    /// position, then the SAN movetext wrapped at 80 columns.
+    pub fn to_pgn(&self) -> String {
+        let result = self.game_result();
+        let mut pgn = String::new();
+        for (name, value) in [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+        ] {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push_str(&format!("[Result \"{}\"]\n", result));
+        let fen = self.start.to_forsyth_edwards();
+        if fen != Board::new_game().to_forsyth_edwards() {
+            pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen));
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        let mut board = self.start.clone();
+        let mut number = self.start.fullmove_number();
+        for (i, m) in self.moves.iter().enumerate() {
+            match board.turn {
+                Color::White => tokens.push(format!("{}.", number)),
+                Color::Black if i == 0 => tokens.push(format!("{}...", number)),
+                Color::Black => {}
+            }
+            tokens.push(board.move_to_san(m));
+            if board.turn == Color::Black {
+                number += 1;
+            }
+            board = board.make_unchecked_move(m);
+        }
+        tokens.push(result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 79 {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// A tag pair, e.g. `[Event "Casual game"]`. Escaped quotes are kept as written.
+    Tag(&'a str, &'a str),
+    /// A SAN move or a result token.
+    San(&'a str),
+}
+
+fn tokenize(pgn: &str) -> Result<Vec<Token<'_>>, anyhow::Error> {
+    let mut tokens = Vec::new();
+    let mut rest = pgn;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '{' => rest = skip_comment(rest)?,
+            ';' => rest = rest.find('\n').map_or("", |end| &rest[end..]),
+            '(' => rest = skip_variation(rest)?,
+            '[' => {
+                let end = tag_end(rest).ok_or_else(|| anyhow!("unterminated PGN tag"))?;
+                let tag = rest[1..end].trim();
+                let (name, value) = tag
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| anyhow!("invalid PGN tag: {}", tag))?;
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .ok_or_else(|| anyhow!("invalid PGN tag: {}", tag))?;
+                tokens.push(Token::Tag(name, value));
+                rest = &rest[end + 1..];
+            }
+            c if c.is_whitespace() => rest = &rest[c.len_utf8()..],
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || "{}();[]".contains(c))
+                    .unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+
+                // "12.", "12..." and "12.e4" all carry a move number. SAN has no
+                // dots, so whatever follows the last one is the move.
+                let san = word.rfind('.').map_or(word, |dot| &word[dot + 1..]);
+                let move_number = san.bytes().all(|b| b.is_ascii_digit());
+                if !move_number && !san.starts_with('$') {
+                    tokens.push(Token::San(san));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Returns the text after the `{ }` comment `text` starts with.
+fn skip_comment(text: &str) -> Result<&str, anyhow::Error> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(&text[i + 1..]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Err(anyhow!("unterminated PGN comment"))
+}
+
+/// Returns the text after the `( )` variation `text` starts with,
+/// skipping the comments inside it.
+fn skip_variation(text: &str) -> Result<&str, anyhow::Error> {
+    let mut depth = 0;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '{' => {
+                rest = skip_comment(rest)?;
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(&rest[1..]);
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Err(anyhow!("unterminated PGN variation"))
+}
+
+/// Index of the `]` closing the tag `text` starts with, ignoring quoted ones.
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::magic;
+
+    /// Placement, side to move, castling and en passant fields of the FEN.
+    fn position_fields(board: &Board) -> String {
+        let fen = board.to_forsyth_edwards();
+        fen.split(' ').take(4).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Morphy - Duke Karl / Count Isouard, Paris 1858, with comments added.
+    const OPERA_GAME: &str = r#"[Event "Paris"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {This is a weak move
+{ nested: 3...exd4 is better }} 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 $2
+7. Qb3 Qe7 (7... Qd7 8. Qxb7) 8. Nc3 c6 9. Bg5 b5 ; the text after ';' is a comment
+10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6
+15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0"#;
+
+    #[test]
+    fn replays_a_recorded_game_with_comments_and_variations() {
+        magic::init();
+        let boards = replay_pgn(OPERA_GAME).unwrap();
+        assert_eq!(boards.len(), 34);
+        let end = boards.last().unwrap();
+        assert_eq!(
+            position_fields(end),
+            "1n1Rkb1r/p4ppp/4q3/4p1B1/4P3/8/PPP2PPP/2K5 b k -"
+        );
+        assert!(end.generate_moves(false).is_empty());
+
+        // Moves after the result token are not played.
+        let boards = replay_pgn("1. e4 e5 1/2-1/2 2. Nf3").unwrap();
+        assert_eq!(boards.len(), 3);
+    }
+
+    #[test]
+    fn fen_tags_set_the_start_position() {
+        magic::init();
+        let pgn = r#"[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"] 1.e4 Kd7 2.e5 *"#;
+        let boards = replay_pgn(pgn).unwrap();
+        assert_eq!(position_fields(&boards[3]), "8/3k4/8/4P3/8/8/8/4K3 b - -");
+
+        assert!(replay_pgn("1. e4 e5 2. Ke3").is_err());
+        assert!(replay_pgn("1. e4 { unterminated").is_err());
+        assert!(replay_pgn(r#"[FEN "not a fen"] 1. e4"#).is_err());
+    }
+
+    fn game_of(start: Board, uci_moves: &[&str]) -> Game {
+        let mut game = Game::new(start);
+        let mut board = game.start.clone();
+        for uci in uci_moves {
+            let m = board.parse_uci_move(uci).unwrap();
+            board = board.make_unchecked_move(&m);
+            game.push(m);
+        }
+
+        game
+    }
+
+    #[test]
+    fn exported_mating_line_is_parseable_pgn() {
+        magic::init();
+        let game = game_of(Board::new_game(), &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(game.game_result(), GameResult::BlackWins);
+
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[Result \"0-1\"]"), "{}", pgn);
+        assert!(pgn.ends_with("\n1. f3 e5 2. g4 Qh4# 0-1\n"), "{}", pgn);
+        assert!(!pgn.contains("FEN"));
+
+        let boards = replay_pgn(&pgn).unwrap();
+        assert_eq!(boards.len(), 5);
+        assert_eq!(boards[4].hash, game.boards()[4].hash);
+    }
+
+    #[test]
+    fn exported_castling_and_promotion_replay_from_the_fen_tag() {
+        magic::init();
+        let start = Board::from_forsyth_edwards("4k3/1P6/8/8/8/8/8/R3K3 b Q - 0 30").unwrap();
+        let game = game_of(start, &["e8f7", "e1c1", "f7g6", "b7b8q"]);
+        assert_eq!(game.game_result(), GameResult::Ongoing);
+
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/1P6/8/8/8/8/8/R3K3 b Q - 0 30\"]"));
+        assert!(
+            pgn.ends_with("\n30... Kf7 31. O-O-O Kg6 32. b8=Q *\n"),
+            "{}",
+            pgn
+        );
+
+        let boards = replay_pgn(&pgn).unwrap();
+        assert_eq!(boards.last().unwrap().hash, game.boards()[4].hash);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::components::board::Board;
+
+use super::{generate::Moves, move_type::Move, packed::PackedMove};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    BadCaptures,
+    GenerateQuiets,
+    Quiets,
+    Done,
+}
+
+/// Hands out the legal moves of a position one at a time, in stages:
+///   1. the transposition table move
+///   2. captures and promotions rated 0 or more by MVV LVA
+///   3. the killer moves of the ply
+///   4. the remaining, losing captures
+///   5. the quiet moves, best rated first
+///
+/// Each stage generates its moves only when it is reached, so a node cut off by
+/// an early move never pays for generating and rating the quiet moves.
+/// The TT move and the killers can come from another position: they are
+/// checked for legality and each move is handed out once.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Option<PackedMove>,
+    killers: [Option<PackedMove>; 2],
+    /// Killers handed out (or found illegal) so far.
+    killers_tried: usize,
+    moves: Option<Moves>,
+    index: usize,
+}
+
+impl MovePicker {
+    pub fn new(tt_move: Option<PackedMove>, killers: [Option<PackedMove>; 2]) -> Self {
+        MovePicker {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            killers_tried: 0,
+            moves: None,
+            index: 0,
+        }
+    }
+
+    /// Returns the next move, or None once every legal move was handed out.
+    /// `board` must be the position the picker was created for.
+    pub fn next(&mut self, board: &Board) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    match self.tt_move.and_then(|m| board.legal_move(m)) {
+                        Some(m) => return Some(m),
+                        None => self.tt_move = None,
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.moves = Some(board.generate_captures());
+                    self.index = 0;
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match self.pick(board, Some(0)) {
+                    Some(m) => return Some(m),
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => {
+                    while self.killers_tried < self.killers.len() {
+                        let slot = self.killers_tried;
+                        self.killers_tried += 1;
+                        let killer = self.killers[slot]
+                            .filter(|&k| Some(k) != self.tt_move)
+                            .and_then(|k| board.legal_move(k))
+                            .filter(|m| !m.is_capture() && !m.is_promotion());
+                        match killer {
+                            Some(m) => return Some(m),
+                            None => self.killers[slot] = None,
+                        }
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => match self.pick(board, None) {
+                    Some(m) => return Some(m),
+                    None => self.stage = Stage::GenerateQuiets,
+                },
+                Stage::GenerateQuiets => {
+                    self.moves = Some(board.generate_quiets());
+                    self.index = 0;
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match self.pick(board, None) {
+                    Some(m) => return Some(m),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Hands out the best remaining generated move not already tried, leaving
+    /// the moves rated below `min_rating` for a later stage.
+    fn pick(&mut self, board: &Board, min_rating: Option<i32>) -> Option<Move> {
+        let moves = self.moves.as_mut()?;
+        while self.index < moves.len() {
+            let rated = moves.select(self.index);
+            if min_rating.is_some_and(|min| rated.rating < min) {
+                return None;
+            }
+            self.index += 1;
+            let tried = Some(rated.piece_move) == self.tt_move
+                || self.killers[..self.killers_tried].contains(&Some(rated.piece_move));
+            if !tried {
+                return Some(moves.get_unsorted(self.index - 1, board));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::magic;
+
+    fn picked(board: &Board, tt_move: Option<&str>, killers: [Option<&str>; 2]) -> Vec<String> {
+        let packed = |uci: &str| {
+            let m = board.legal_moves().into_iter().find(|m| m.to_uci() == uci);
+            PackedMove::new(&m.expect("the test move is legal"))
+        };
+        let mut picker = MovePicker::new(tt_move.map(packed), killers.map(|k| k.map(packed)));
+        std::iter::from_fn(|| picker.next(board))
+            .map(|m| m.to_uci())
+            .collect()
+    }
+
+    #[test]
+    fn every_legal_move_comes_out_once_in_stage_order() {
+        magic::init();
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        for fen in fens {
+            let board = Board::from_forsyth_edwards(fen).unwrap();
+            let mut legal: Vec<_> = board.legal_moves().iter().map(|m| m.to_uci()).collect();
+            let quiet: Vec<_> = board
+                .legal_moves()
+                .into_iter()
+                .filter(|m| !m.is_capture() && !m.is_promotion())
+                .map(|m| m.to_uci())
+                .collect();
+            let (tt_move, killer) = (legal[legal.len() / 2].clone(), quiet[0].clone());
+
+            let mut moves = picked(&board, Some(&tt_move), [Some(&killer), None]);
+            assert_eq!(moves[0], tt_move, "{}", fen);
+            if killer != tt_move {
+                // Only captures come between the TT move and the killer, and
+                // only losing captures and quiet moves after it.
+                let killer_at = moves.iter().position(|m| *m == killer).unwrap();
+                assert!(moves[1..killer_at].iter().all(|m| !quiet.contains(m)));
+                let first_quiet = moves[killer_at + 1..]
+                    .iter()
+                    .position(|m| quiet.contains(m))
+                    .map_or(moves.len(), |i| i + killer_at + 1);
+                assert!(moves[first_quiet..].iter().all(|m| quiet.contains(m)));
+            }
+
+            moves.sort();
+            legal.sort();
+            assert_eq!(moves, legal, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn moves_from_another_position_are_skipped() {
+        magic::init();
+        let start = Board::default();
+        let after_e4 = Board::from_forsyth_edwards(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        )
+        .unwrap();
+        // A white move used as TT move and killer in a position with Black to move.
+        let e2e4 = start
+            .legal_moves()
+            .into_iter()
+            .find(|m| m.to_uci() == "e2e4");
+        let e2e4 = e2e4.map(|m| PackedMove::new(&m));
+
+        let mut picker = MovePicker::new(e2e4, [e2e4, None]);
+        let moves: Vec<_> = std::iter::from_fn(|| picker.next(&after_e4)).collect();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.iter().all(|m| m.piece.color == after_e4.turn));
+    }
+}
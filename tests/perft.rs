@@ -0,0 +1,47 @@
+use corman::{components::board::Board, moves::magic};
+
+/// Reference positions with their node counts at depths 1 to 4, in EPD style.
+/// From the Chess Programming Wiki "Perft Results" page.
+const POSITIONS: &[(&str, [u64; 4])] = &[
+    (
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        [20, 400, 8_902, 197_281],
+    ),
+    // "Kiwipete"
+    (
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        [48, 2_039, 97_862, 4_085_603],
+    ),
+    // Rook endgame with en passant and discovered checks along the rank.
+    (
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        [14, 191, 2_812, 43_238],
+    ),
+    // Position 4: promotions and castling out of check.
+    (
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        [6, 264, 9_467, 422_333],
+    ),
+    // Position 5
+    (
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        [44, 1_486, 62_379, 2_103_487],
+    ),
+];
+
+#[test]
+fn perft_matches_the_reference_positions() {
+    magic::init();
+    for (fen, expected) in POSITIONS {
+        let mut board = Board::from_forsyth_edwards(fen).unwrap();
+        for (depth, &nodes) in (1..).zip(expected) {
+            assert_eq!(
+                board.perft(depth),
+                nodes,
+                "perft({}) diverges for {}",
+                depth,
+                fen
+            );
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use corman::components::{board::Board, pieces::Color};
+use corman::moves::notation::parse_square;
+
+/// Runs the engine on the given UCI commands and returns its output lines.
+/// `quit` is sent once every search answered: it would stop them.
+fn run_engine(commands: &[&str]) -> Vec<String> {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_corman"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("cannot start the engine");
+
+    let mut stdin = engine.stdin.take().unwrap();
+    for command in commands {
+        writeln!(stdin, "{}", command).unwrap();
+    }
+    let searches = commands.iter().filter(|c| c.starts_with("go")).count();
+    let mut output = BufReader::new(engine.stdout.take().unwrap()).lines();
+    let mut lines = Vec::new();
+    while bestmoves(&lines).len() < searches {
+        match output.next() {
+            Some(line) => lines.push(line.unwrap()),
+            None => break,
+        }
+    }
+    writeln!(stdin, "quit").unwrap();
+    drop(stdin);
+
+    lines.extend(output.map(Result::unwrap));
+    engine.wait().unwrap();
+    lines
+}
+
+fn bestmoves(lines: &[String]) -> Vec<&str> {
+    lines
+        .iter()
+        .filter_map(|l| l.strip_prefix("bestmove "))
+        .collect()
+}
+
+const ILLEGAL_FIFTH_MOVE: &str = "position startpos moves e2e4 e7e5 g1f3 b8c6 f1f3 d7d6";
+
+// One engine session: each start pays for the move generator tables.
+#[test]
+fn illegal_moves_keep_the_last_valid_position_or_block_strict_mode() {
+    let lines = run_engine(&[
+        ILLEGAL_FIFTH_MOVE,
+        "isready",
+        "go depth 2",
+        "setoption name Strict value true",
+        ILLEGAL_FIFTH_MOVE,
+        "go depth 1",
+        "position startpos moves e2e4",
+        "go depth 1",
+        // Only `stop` ends this one.
+        "go infinite",
+        "stop",
+    ]);
+
+    let warnings: Vec<_> = lines
+        .iter()
+        .filter(|l| l.contains("illegal move"))
+        .collect();
+    assert_eq!(warnings.len(), 2, "{:?}", lines);
+    assert!(warnings[0].contains("f1f3 at index 4"), "{}", warnings[0]);
+    assert!(lines.contains(&"readyok".to_string()));
+
+    let bestmoves = bestmoves(&lines);
+    assert_eq!(bestmoves.len(), 4, "{:?}", lines);
+
+    // The first search ran on the position after 4 moves: White to move,
+    // with the f1 bishop still at home.
+    let after_four = Board::from_forsyth_edwards(
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    )
+    .unwrap();
+    let from = parse_square(&bestmoves[0][..2]).unwrap();
+    let piece = after_four.position.piece_at(from);
+    assert_eq!(
+        piece.map(|p| p.color),
+        Some(Color::White),
+        "{}",
+        bestmoves[0]
+    );
+
+    // Strict mode refuses to search until a valid position is set.
+    assert_eq!(bestmoves[1], "0000");
+    assert_ne!(bestmoves[2], "0000");
+    assert_ne!(bestmoves[3], "0000");
+}
+
+#[test]
+fn searches_report_well_formed_info_lines() {
+    // Mate in 2: 1. Kg6 Kg8 2. Ra8#
+    let fen = "7k/8/5K2/8/8/8/8/R7 w - - 0 1";
+    let lines = run_engine(&[&format!("position fen {}", fen), "go depth 4"]);
+
+    let infos: Vec<Vec<&str>> = lines
+        .iter()
+        .filter(|l| l.starts_with("info depth"))
+        .map(|l| l.split_whitespace().collect())
+        .collect();
+    assert_eq!(infos.len(), 4, "{:?}", lines);
+
+    let board = Board::from_forsyth_edwards(fen).unwrap();
+    for (depth, info) in (1..).zip(&infos) {
+        let value = |key: &str| {
+            let at = info.iter().position(|t| *t == key).expect(key);
+            info[at + 1]
+        };
+        assert_eq!(value("depth"), depth.to_string());
+        assert!(value("seldepth").parse::<i32>().unwrap() >= depth);
+        assert!(value("nodes").parse::<u64>().unwrap() > 0);
+        let score = info.iter().position(|t| *t == "score").unwrap();
+        assert!(matches!(info[score + 1], "cp" | "mate"), "{:?}", info);
+        assert!(info[score + 2].parse::<i32>().is_ok(), "{:?}", info);
+
+        // The principal variation is a line of legal moves.
+        let pv = &info[info.iter().position(|t| *t == "pv").unwrap() + 1..];
+        assert!(!pv.is_empty());
+        let (_, failed) = board.play_uci_moves(pv);
+        assert_eq!(failed, None, "{:?}", info);
+    }
+    let last = infos.last().unwrap().join(" ");
+    assert!(last.contains("score mate 2 "), "{}", last);
+    assert_eq!(bestmoves(&lines), ["f6g6"]);
+}
+
+#[test]
+fn a_ponder_search_becomes_the_real_one_on_ponderhit() {
+    // Mate in 2: 1. Kg6 Kg8 2. Ra8#
+    let fen = "7k/8/5K2/8/8/8/8/R7 w - - 0 1";
+    let position = format!("position fen {}", fen);
+    let lines = run_engine(&[
+        "setoption name Ponder value true",
+        &position,
+        "go ponder wtime 10000 btime 10000",
+        // Answered during the search.
+        "isready",
+        "ponderhit",
+        // A ponder search on a reply the opponent did not play is stopped and
+        // answers once.
+        &position,
+        "go ponder depth 1",
+        "stop",
+    ]);
+
+    let bestmoves = bestmoves(&lines);
+    assert_eq!(bestmoves.len(), 2, "{:?}", lines);
+    let index = |prefix: &str| lines.iter().position(|l| l.starts_with(prefix));
+    assert!(index("readyok") < index("bestmove"), "{:?}", lines);
+    let (best, reply) = bestmoves[0].split_once(" ponder ").expect(bestmoves[0]);
+    let board = Board::from_forsyth_edwards(fen).unwrap();
+    let (_, failed) = board.play_uci_moves(&[best, reply]);
+    assert_eq!(failed, None, "{}", bestmoves[0]);
+
+    // The search went on after the ponderhit: each depth completed once.
+    let depths: Vec<i32> = lines
+        .iter()
+        .take_while(|l| !l.starts_with("bestmove"))
+        .filter_map(|l| l.strip_prefix("info depth "))
+        .map(|l| l.split_whitespace().next().unwrap().parse().unwrap())
+        .collect();
+    assert!(depths.len() > 1, "{:?}", lines);
+    assert!(
+        depths.iter().copied().eq(1..=depths.len() as i32),
+        "{:?}",
+        depths
+    );
+}
+
+#[test]
+fn searchmoves_restrict_the_reported_lines() {
+    let lines = run_engine(&[
+        "position startpos",
+        "go depth 2 searchmoves e2e4 d2d4",
+        "go depth 1 searchmoves e2e5",
+    ]);
+
+    let pvs: Vec<&str> = lines
+        .iter()
+        .filter_map(|l| l.split_once(" pv ").map(|(_, pv)| pv))
+        .collect();
+    assert_eq!(pvs.len(), 2, "{:?}", lines);
+    assert!(
+        pvs.iter()
+            .all(|pv| pv.starts_with("e2e4") || pv.starts_with("d2d4")),
+        "{:?}",
+        pvs
+    );
+
+    let bestmoves = bestmoves(&lines);
+    assert!(["e2e4", "d2d4"].contains(&bestmoves[0]), "{:?}", bestmoves);
+    // None of the searchmoves is legal: nothing is searched.
+    assert_eq!(bestmoves[1], "0000");
+    assert!(
+        lines
+            .iter()
+            .any(|l| l.contains("none of the searchmoves is legal"))
+    );
+}
+
+#[test]
+fn finished_games_only_moves_and_book_moves_are_answered_without_a_search() {
+    // A Polyglot book with a single entry: a2a3 in the start position.
+    let book = std::env::temp_dir().join(format!("corman-uci-{}.bin", std::process::id()));
+    let mut entry = 0x463b96181691fc9c_u64.to_be_bytes().to_vec();
+    entry.extend((8u16 << 6 | 16).to_be_bytes());
+    entry.extend([0, 1, 0, 0, 0, 0]);
+    std::fs::write(&book, entry).unwrap();
+    let set_book = format!("setoption name BookFile value {}", book.display());
+
+    let lines = run_engine(&[
+        "position fen rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        "go wtime 10000 btime 10000",
+        "position fen k7/8/1Q6/8/8/8/8/7K b - - 0 1",
+        "go wtime 10000 btime 10000",
+        "position fen k7/8/1K6/8/8/8/8/R7 b - - 0 1",
+        "go wtime 10000 btime 10000",
+        &set_book,
+        "position startpos",
+        "go wtime 10000 btime 10000",
+    ]);
+    std::fs::remove_file(&book).unwrap();
+
+    assert_eq!(
+        bestmoves(&lines),
+        ["0000", "0000", "a8b8", "a2a3"],
+        "{:?}",
+        lines
+    );
+    assert!(lines.contains(&"info string book move a2a3".to_string()));
+    let depths: Vec<&str> = lines
+        .iter()
+        .filter(|l| l.starts_with("info depth"))
+        .map(String::as_str)
+        .collect();
+    assert_eq!(depths[0], "info depth 0 score mate 0");
+    assert_eq!(depths[1], "info depth 0 score cp 0");
+    // The only move is played after the first iteration.
+    assert_eq!(depths.len(), 3, "{:?}", depths);
+    assert!(depths[2].starts_with("info depth 1 "), "{:?}", depths);
+}
+
+#[test]
+fn an_infinite_search_answers_only_after_stop() {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_corman"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("cannot start the engine");
+    let mut stdin = engine.stdin.take().unwrap();
+    let mut output = BufReader::new(engine.stdout.take().unwrap()).lines();
+    let mut read_until = |last: &str| {
+        let mut lines = Vec::new();
+        for line in output.by_ref() {
+            let line = line.unwrap();
+            lines.push(line.clone());
+            if line.starts_with(last) {
+                break;
+            }
+        }
+        lines
+    };
+
+    // Checkmate: there is nothing to search, the best move still waits for stop.
+    // Unlike isready, uci is only answered by the main loop, after the search.
+    let mated = "position fen rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+    for command in [mated, "go infinite", "isready", "uci"] {
+        writeln!(stdin, "{}", command).unwrap();
+    }
+    let mut lines = read_until("readyok");
+    lines.extend(read_until("uciok"));
+    assert!(bestmoves(&lines).is_empty(), "{:?}", lines);
+
+    writeln!(stdin, "stop").unwrap();
+    assert_eq!(read_until("bestmove").last().unwrap(), "bestmove 0000");
+    writeln!(stdin, "quit").unwrap();
+    engine.wait().unwrap();
+}